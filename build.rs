@@ -0,0 +1,5 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/prover.proto")
+        .expect("failed to compile proto/prover.proto");
+}
@@ -0,0 +1,25 @@
+/* C API of the axiom-scaffold prover, see src/lib.rs for the documentation of each function. */
+#ifndef AXIOM_SCAFFOLD_H
+#define AXIOM_SCAFFOLD_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+typedef struct AxiomProver AxiomProver;
+
+/* Returns NULL on failure, including a panic, see axiom_last_error. */
+AxiomProver *axiom_prover_new(const char *pinning_path, const char *pk_path, const char *params_dir);
+void axiom_prover_free(AxiomProver *prover);
+
+/* Return 0 on success and -1 on failure, including a null prover or a panic, see axiom_last_error. */
+int32_t axiom_prover_load_inputs(AxiomProver *prover, const char *bundle_path);
+int32_t axiom_prover_prove(AxiomProver *prover, const char *spec_json);
+
+/* Valid until the next call to axiom_prover_prove or axiom_prover_free. NULL if an argument is NULL. */
+const uint8_t *axiom_prover_proof(const AxiomProver *prover, size_t *len);
+const uint8_t *axiom_prover_instances(const AxiomProver *prover, size_t *len);
+
+/* The message of the last error on this thread, or NULL. Valid until the next failing call on this thread. */
+const char *axiom_last_error(void);
+
+#endif
@@ -0,0 +1,213 @@
+//! A C API to embed the prover, e.g. into Go with cgo or Node with N-API, without running a separate process.
+//! See `axiom_scaffold.h` for the declarations.
+//!
+//! A prover is created once from the pinning and proving key written by `keygen`, then proves circuits described by
+//! [`QuerySpec`]s from the chain data of an [`InputBundle`], so no provider is needed.
+//!
+//! Functions that can fail return `-1` or `NULL` and record a message for [`axiom_last_error`]. Panics are caught at
+//! the boundary and reported the same way, since unwinding into C is undefined behavior.
+
+use std::{
+    any::Any,
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::PathBuf,
+    ptr,
+};
+
+use axiom_scaffold::{
+    error::{AxiomError, Result},
+    halo2_base::halo2_proofs::{
+        halo2curves::bn256::{Bn256, Fr, G1Affine},
+        plonk::ProvingKey,
+        poly::kzg::commitment::ParamsKZG,
+    },
+    keys::{read_pk, AxiomPinning},
+    offline::InputBundle,
+    scaffold::AxiomChip,
+    spec::QuerySpec,
+    srs::SrsManager,
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+/// Records `err` for [`axiom_last_error`].
+fn set_error(err: impl ToString) {
+    let message = CString::new(err.to_string().replace('\0', " ")).unwrap();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Runs `f`, catching panics, and records the error for [`axiom_last_error`] if it fails.
+fn guard<T>(f: impl FnOnce() -> Result<T>) -> Option<T> {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => Some(value),
+        Ok(Err(err)) => {
+            set_error(err);
+            None
+        }
+        Err(panic) => {
+            set_error(format!("panic: {}", panic_message(&*panic)));
+            None
+        }
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+/// Runs `f`, returning `0` if it succeeds and `-1` if it fails or panics.
+fn status(f: impl FnOnce() -> Result<()>) -> i32 {
+    guard(f).map_or(-1, |()| 0)
+}
+
+/// # Safety
+/// `prover` must be null or a live prover.
+unsafe fn prover_arg<'a>(prover: *mut AxiomProver) -> Result<&'a mut AxiomProver> {
+    prover.as_mut().ok_or_else(|| AxiomError::MissingInput("null prover argument".to_string()))
+}
+
+/// # Safety
+/// `s` must be a valid nul terminated string.
+unsafe fn str_arg<'a>(s: *const c_char) -> Result<&'a str> {
+    if s.is_null() {
+        return Err(AxiomError::MissingInput("null string argument".to_string()));
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| AxiomError::MissingInput("string argument is not UTF-8".to_string()))
+}
+
+pub struct AxiomProver {
+    pinning: AxiomPinning,
+    pk: ProvingKey<G1Affine>,
+    params: ParamsKZG<Bn256>,
+    inputs: Option<InputBundle>,
+    proof: Vec<u8>,
+    /// Each public instance of the last proof as its 32 byte little endian representation.
+    instances: Vec<u8>,
+}
+
+/// Creates a prover from the pinning and proving key files written by `keygen`, reading the KZG parameters from
+/// `params_dir`.
+///
+/// # Safety
+/// The arguments must be valid nul terminated strings. The prover must be freed with [`axiom_prover_free`].
+#[no_mangle]
+pub unsafe extern "C" fn axiom_prover_new(
+    pinning_path: *const c_char,
+    pk_path: *const c_char,
+    params_dir: *const c_char,
+) -> *mut AxiomProver {
+    let prover = guard(|| {
+        let pinning = AxiomPinning::from_path(str_arg(pinning_path)?)?;
+        let pk = read_pk(str_arg(pk_path)?, &pinning.params)?;
+        let srs = SrsManager { dir: PathBuf::from(str_arg(params_dir)?), ..Default::default() };
+        let params = srs.params(pinning.params.degree)?;
+        Ok(AxiomProver { pinning, pk, params, inputs: None, proof: vec![], instances: vec![] })
+    });
+    prover.map_or(ptr::null_mut(), |prover| Box::into_raw(Box::new(prover)))
+}
+
+/// # Safety
+/// `prover` must be null or have been returned by [`axiom_prover_new`], and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn axiom_prover_free(prover: *mut AxiomProver) {
+    if !prover.is_null() {
+        drop(Box::from_raw(prover));
+    }
+}
+
+/// Loads the chain data used by later proofs from an input bundle written by `export_inputs`.
+///
+/// # Safety
+/// `prover` must be null or a live prover, and `bundle_path` a valid nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn axiom_prover_load_inputs(
+    prover: *mut AxiomProver,
+    bundle_path: *const c_char,
+) -> i32 {
+    status(|| {
+        let prover = prover_arg(prover)?;
+        prover.inputs = Some(InputBundle::read(str_arg(bundle_path)?)?);
+        Ok(())
+    })
+}
+
+/// Proves the circuit described by the JSON query spec `spec_json`, with the loaded input bundle. The spec must have
+/// the same shape as the one the keys were generated for.
+///
+/// # Safety
+/// `prover` must be null or a live prover, and `spec_json` a valid nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn axiom_prover_prove(
+    prover: *mut AxiomProver,
+    spec_json: *const c_char,
+) -> i32 {
+    status(|| {
+        let prover = prover_arg(prover)?;
+        let spec: QuerySpec = serde_json::from_str(str_arg(spec_json)?)
+            .map_err(|err| AxiomError::MissingInput(format!("invalid query spec: {err}")))?;
+        let inputs = prover.inputs.as_ref().ok_or_else(|| {
+            AxiomError::MissingInput("load an input bundle before proving".to_string())
+        })?;
+        let mut axiom = AxiomChip::<Fr>::prover(prover.pinning.clone());
+        axiom.load_spec_offline(inputs, &spec)?;
+        let artifacts = axiom.prove_with_pk(&prover.params, &prover.pk)?;
+        prover.proof = artifacts.proof;
+        prover.instances = artifacts.instances.iter().flat_map(|x| x.to_bytes()).collect();
+        Ok(())
+    })
+}
+
+/// Returns the bytes of the last proof and writes their length to `len`, or returns null if an argument is null.
+///
+/// # Safety
+/// `prover` must be null or a live prover, and `len` null or a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn axiom_prover_proof(
+    prover: *const AxiomProver,
+    len: *mut usize,
+) -> *const u8 {
+    match (prover.as_ref(), len.as_mut()) {
+        (Some(prover), Some(len)) => {
+            *len = prover.proof.len();
+            prover.proof.as_ptr()
+        }
+        _ => ptr::null(),
+    }
+}
+
+/// Returns the public instances of the last proof, each as its 32 byte little endian representation, and writes their
+/// total length in bytes to `len`, or returns null if an argument is null.
+///
+/// # Safety
+/// `prover` must be null or a live prover, and `len` null or a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn axiom_prover_instances(
+    prover: *const AxiomProver,
+    len: *mut usize,
+) -> *const u8 {
+    match (prover.as_ref(), len.as_mut()) {
+        (Some(prover), Some(len)) => {
+            *len = prover.instances.len();
+            prover.instances.as_ptr()
+        }
+        _ => ptr::null(),
+    }
+}
+
+/// Returns the message of the last error on this thread, or null if there was none.
+#[no_mangle]
+pub extern "C" fn axiom_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
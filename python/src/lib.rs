@@ -0,0 +1,253 @@
+//! Python bindings for the high level flow of [`AxiomChip`]: query a provider, expose values, then mock, prove, and
+//! verify. Build with `maturin develop` from this directory.
+//!
+//! ```python
+//! from axiom_scaffold_py import AxiomChip
+//!
+//! chip = AxiomChip("https://mainnet.infura.io/v3/<INFURA_ID>", degree=18)
+//! block = chip.get_block(16_000_000)
+//! chip.expose_public(chip.evaluate(block, "number"))
+//! chip.mock()
+//! ```
+
+use std::str::FromStr;
+
+use axiom_scaffold::{
+    axiom_eth::{storage::EIP1186ResponseDigest, Network},
+    config::{AutoDegree, AxiomChipConfig},
+    containers::{ByteString, EthBlock},
+    error::AxiomError,
+    halo2_base::{halo2_proofs::halo2curves::bn256::Fr, AssignedValue},
+    proof::ProofArtifacts,
+    scaffold::AxiomChip as Chip,
+    srs::SrsManager,
+};
+use ethers_core::types::{Address, H256};
+use ethers_providers::{Http, Provider};
+use pyo3::{exceptions::PyRuntimeError, exceptions::PyValueError, prelude::*};
+
+fn py_err(err: AxiomError) -> PyErr {
+    PyRuntimeError::new_err(err.to_string())
+}
+
+/// Formats a field element as a big endian hex string.
+fn fr_hex(x: &Fr) -> String {
+    let hex: String = x.to_bytes().iter().rev().map(|byte| format!("{byte:02x}")).collect();
+    format!("0x{hex}")
+}
+
+/// A value assigned in the circuit.
+#[pyclass]
+#[derive(Clone, Copy)]
+struct Value(AssignedValue<Fr>);
+
+#[pymethods]
+impl Value {
+    /// The value as a big endian hex string.
+    fn hex(&self) -> String {
+        fr_hex(self.0.value())
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Value({})", self.hex())
+    }
+}
+
+/// A block header queried with [`AxiomChip::get_block`].
+#[pyclass]
+struct Block(EthBlock<Fr>);
+
+#[pymethods]
+impl Block {
+    /// The block hash, one value per byte.
+    fn block_hash(&self) -> Vec<Value> {
+        self.0.block_hash.iter().copied().map(Value).collect()
+    }
+}
+
+impl Block {
+    fn field(&self, name: &str) -> PyResult<&ByteString<Fr>> {
+        let block = &self.0;
+        Ok(match name {
+            "parent_hash" => &block.parent_hash,
+            "ommers_hash" => &block.ommers_hash,
+            "beneficiary" => &block.beneficiary,
+            "state_root" => &block.state_root,
+            "transactions_root" => &block.transactions_root,
+            "receipts_root" => &block.receipts_root,
+            "logs_bloom" => &block.logs_bloom,
+            "difficulty" => &block.difficulty,
+            "number" => &block.number,
+            "gas_limit" => &block.gas_limit,
+            "gas_used" => &block.gas_used,
+            "timestamp" => &block.timestamp,
+            "extra_data" => &block.extra_data,
+            "mix_hash" => &block.mix_hash,
+            "nonce" => &block.nonce,
+            "basefee" => &block.basefee,
+            "withdrawals_root" => &block.withdrawals_root,
+            _ => return Err(PyValueError::new_err(format!("unknown block header field {name}"))),
+        })
+    }
+}
+
+/// The result of an `eth_getProof` query with [`AxiomChip::get_proof`].
+#[pyclass]
+struct StorageProof(EIP1186ResponseDigest<Fr>);
+
+#[pymethods]
+impl StorageProof {
+    /// The block hash as (hi, lo) 128 bit halves.
+    fn block_hash(&self) -> (Value, Value) {
+        (Value(self.0.block_hash.0), Value(self.0.block_hash.1))
+    }
+
+    fn block_number(&self) -> Value {
+        Value(self.0.block_number)
+    }
+
+    fn address(&self) -> Value {
+        Value(self.0.address)
+    }
+
+    /// The value of each queried slot as (hi, lo) 128 bit halves, in the order of the slots.
+    fn values(&self) -> Vec<(Value, Value)> {
+        self.0.slots_values.iter().map(|(_, [hi, lo])| (Value(*hi), Value(*lo))).collect()
+    }
+}
+
+/// A proof returned by [`AxiomChip::prove`].
+#[pyclass]
+struct Proof(ProofArtifacts);
+
+#[pymethods]
+impl Proof {
+    fn proof(&self) -> Vec<u8> {
+        self.0.proof.clone()
+    }
+
+    /// The public instances, each as a big endian hex string.
+    fn instances(&self) -> Vec<String> {
+        self.0.instances.iter().map(fr_hex).collect()
+    }
+
+    /// Verifies the proof against its verifying key, with the KZG parameters from `PARAMS_DIR`.
+    fn verify(&self) -> PyResult<bool> {
+        let k = self.0.vk.get_domain().k();
+        let params = SrsManager::default().params(k).map_err(|err| py_err(err.into()))?;
+        Ok(self.0.verify(&params))
+    }
+}
+
+/// An [`AxiomChip`](Chip) connected to a JSON-RPC provider. Proving consumes the circuit, so `mock` and `prove` can
+/// only be called once.
+#[pyclass]
+struct AxiomChip {
+    chip: Option<Chip<Fr>>,
+    provider: Provider<Http>,
+}
+
+impl AxiomChip {
+    fn chip(&mut self) -> PyResult<&mut Chip<Fr>> {
+        self.chip.as_mut().ok_or_else(|| PyRuntimeError::new_err("the circuit was already proven"))
+    }
+
+    fn take(&mut self) -> PyResult<Chip<Fr>> {
+        self.chip.take().ok_or_else(|| PyRuntimeError::new_err("the circuit was already proven"))
+    }
+}
+
+#[pymethods]
+impl AxiomChip {
+    /// Connects to the provider at `provider_url`. The circuit has 2^`degree` rows, chosen automatically if `None`.
+    /// `network` is "mainnet" or "goerli", detected from the provider if `None`.
+    #[new]
+    #[pyo3(signature = (provider_url, degree=None, lookup_bits=8, network=None))]
+    fn new(
+        provider_url: &str,
+        degree: Option<usize>,
+        lookup_bits: usize,
+        network: Option<&str>,
+    ) -> PyResult<Self> {
+        let auto_degree = degree.is_none().then(AutoDegree::default);
+        let config = AxiomChipConfig {
+            k: degree.unwrap_or(AutoDegree::default().max_k),
+            lookup_bits,
+            unusable_rows: 109,
+            auto_degree,
+        };
+        let mut chip = Chip::with_config(config);
+        match network {
+            Some("mainnet") => chip.set_network(Network::Mainnet),
+            Some("goerli") => chip.set_network(Network::Goerli),
+            Some(network) => {
+                return Err(PyValueError::new_err(format!("unknown network {network}")))
+            }
+            None => {}
+        }
+        let provider = Provider::<Http>::try_from(provider_url)
+            .map_err(|err| PyValueError::new_err(format!("invalid provider URL: {err}")))?;
+        Ok(Self { chip: Some(chip), provider })
+    }
+
+    fn get_block(&mut self, block_number: u32) -> PyResult<Block> {
+        let provider = self.provider.clone();
+        self.chip()?.eth_getBlockByNumber(&provider, block_number).map(Block).map_err(py_err)
+    }
+
+    /// Queries the values of `slots` of the contract at `address` at block `block_number`, as hex strings.
+    fn get_proof(
+        &mut self,
+        block_number: u32,
+        address: &str,
+        slots: Vec<&str>,
+    ) -> PyResult<StorageProof> {
+        let address = Address::from_str(address)
+            .map_err(|err| PyValueError::new_err(format!("invalid address: {err}")))?;
+        let slots = slots
+            .into_iter()
+            .map(|slot| {
+                H256::from_str(slot)
+                    .map_err(|err| PyValueError::new_err(format!("invalid slot: {err}")))
+            })
+            .collect::<PyResult<_>>()?;
+        let provider = self.provider.clone();
+        self.chip()?
+            .eth_getProof(&provider, address, slots, block_number)
+            .map(StorageProof)
+            .map_err(py_err)
+    }
+
+    /// Evaluates the header field `field` of `block`, e.g. "number" or "timestamp", as a big endian number.
+    fn evaluate(&mut self, block: &Block, field: &str) -> PyResult<Value> {
+        let field = block.field(field)?;
+        let chip = self.chip()?;
+        let value = field.evaluate(&mut chip.ctx(), chip.gate());
+        Ok(Value(value))
+    }
+
+    fn expose_public(&mut self, value: Value) -> PyResult<()> {
+        self.chip()?.expose_public(value.0);
+        Ok(())
+    }
+
+    /// Runs the mock prover, raising an error with the failed constraints if the circuit is not satisfied.
+    fn mock(&mut self) -> PyResult<()> {
+        self.take()?.mock().map_err(py_err)
+    }
+
+    /// Generates keys and proves the circuit. KZG parameters are read from, or downloaded to, `PARAMS_DIR`.
+    fn prove(&mut self) -> PyResult<Proof> {
+        self.take()?.prove(&SrsManager::default()).map(Proof).map_err(py_err)
+    }
+}
+
+#[pymodule]
+fn axiom_scaffold_py(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_class::<AxiomChip>()?;
+    m.add_class::<Block>()?;
+    m.add_class::<StorageProof>()?;
+    m.add_class::<Proof>()?;
+    m.add_class::<Value>()?;
+    Ok(())
+}
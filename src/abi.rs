@@ -0,0 +1,202 @@
+//! Gadgets for the Solidity ABI: decoding calldata and encoding assigned values.
+//!
+//! Calldata is a [`ByteString`], so the decoders work on any proven byte string. Proving the calldata of a transaction
+//! is not supported yet. The encoders rebuild the exact bytes hashed by contracts, e.g. mapping keys or commitments,
+//! so they can be hashed in the circuit instead of trusting an encoding computed outside of it.
+
+use axiom_eth::Field;
+use halo2_base::{
+    gates::{GateInstructions, RangeInstructions},
+    utils::bit_length,
+    AssignedValue, Context,
+    QuantumCell::Constant,
+};
+
+use crate::{
+    containers::{ByteString, H256Assigned},
+    gadgets::be_bytes,
+};
+
+/// The number of bytes of a function selector.
+pub const SELECTOR_BYTES: usize = 4;
+
+/// Returns whether `calldata` starts with `selector`, e.g. `ethers_core::utils::id("transfer(address,uint256)")`.
+///
+/// Calldata shorter than a selector never matches, even if its bytes are a prefix of `selector`, since the EVM pads
+/// it with zeros and the contract dispatches it to its fallback function.
+pub fn matches_selector<F: Field>(
+    ctx: &mut Context<F>,
+    range: &impl RangeInstructions<F>,
+    calldata: &ByteString<F>,
+    selector: [u8; SELECTOR_BYTES],
+) -> AssignedValue<F> {
+    let gate = range.gate();
+    if calldata.bytes.len() < SELECTOR_BYTES {
+        return ctx.load_zero();
+    }
+    let len_bits = bit_length(calldata.bytes.len() as u64);
+    let mut matches = range.is_less_than(
+        ctx,
+        Constant(F::from(SELECTOR_BYTES as u64 - 1)),
+        calldata.len,
+        len_bits,
+    );
+    for (byte, expected) in calldata.bytes.iter().zip(selector) {
+        let is_equal = gate.is_equal(ctx, *byte, Constant(F::from(expected as u64)));
+        matches = gate.and(ctx, matches, is_equal);
+    }
+    matches
+}
+
+/// A static ABI type, which is encoded in place in a fixed number of 32 byte words.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StaticType {
+    Uint256,
+    Address,
+    Bool,
+    Bytes32,
+    /// A tuple of static types, encoded as its elements one after another.
+    Tuple(Vec<StaticType>),
+}
+
+impl StaticType {
+    /// The number of 32 byte words of the encoding.
+    pub fn num_words(&self) -> usize {
+        match self {
+            StaticType::Tuple(types) => types.iter().map(StaticType::num_words).sum(),
+            _ => 1,
+        }
+    }
+}
+
+/// An ABI decoded value, see [`decode_static_args`].
+#[derive(Clone, Debug)]
+pub enum AbiValue<F: Field> {
+    Uint256(H256Assigned<F>),
+    Address(AssignedValue<F>),
+    /// Either 0 or 1.
+    Bool(AssignedValue<F>),
+    Bytes32(H256Assigned<F>),
+    Tuple(Vec<AbiValue<F>>),
+}
+
+impl<F: Field> AbiValue<F> {
+    /// A `uint256` of `value`, which must be less than 2<sup>248</sup>.
+    pub fn uint(
+        ctx: &mut Context<F>,
+        range: &impl RangeInstructions<F>,
+        value: AssignedValue<F>,
+    ) -> Self {
+        let mut bytes = vec![ctx.load_zero()];
+        bytes.extend(be_bytes(ctx, range, value, 31));
+        AbiValue::Uint256(H256Assigned::from_bytes(ctx, range.gate(), bytes.try_into().unwrap()))
+    }
+}
+
+/// Decodes the arguments of a call to a function with parameters `types` from its `calldata`, which starts with the
+/// function selector.
+///
+/// Constrains the calldata to be long enough for the arguments, and the arguments to be strictly encoded: the padding
+/// of addresses and bools must be zero and bools must be 0 or 1, as checked by Solidity. Trailing calldata is allowed.
+/// The calldata must already be constrained to be bytes.
+pub fn decode_static_args<F: Field>(
+    ctx: &mut Context<F>,
+    range: &impl RangeInstructions<F>,
+    calldata: &ByteString<F>,
+    types: &[StaticType],
+) -> Vec<AbiValue<F>> {
+    let num_words: usize = types.iter().map(StaticType::num_words).sum();
+    let min_len = SELECTOR_BYTES + 32 * num_words;
+    assert!(calldata.bytes.len() >= min_len, "calldata is too short for the arguments");
+    let len_bits = bit_length(calldata.bytes.len() as u64);
+    range.check_less_than(ctx, Constant(F::from(min_len as u64 - 1)), calldata.len, len_bits);
+
+    let mut words = calldata.bytes[SELECTOR_BYTES..min_len].chunks_exact(32);
+    types.iter().map(|ty| decode_static(ctx, range.gate(), &mut words, ty)).collect()
+}
+
+fn decode_static<'a, F: Field>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    words: &mut impl Iterator<Item = &'a [AssignedValue<F>]>,
+    ty: &StaticType,
+) -> AbiValue<F> {
+    if let StaticType::Tuple(types) = ty {
+        return AbiValue::Tuple(
+            types.iter().map(|ty| decode_static(ctx, gate, words, ty)).collect(),
+        );
+    }
+    let word: [AssignedValue<F>; 32] = words.next().unwrap().try_into().unwrap();
+    let assert_zero_padding = |ctx: &mut Context<F>, padding: &[AssignedValue<F>]| {
+        for byte in padding {
+            gate.assert_is_const(ctx, byte, &F::zero());
+        }
+    };
+    match ty {
+        StaticType::Uint256 => AbiValue::Uint256(H256Assigned::from_bytes(ctx, gate, word)),
+        StaticType::Bytes32 => AbiValue::Bytes32(H256Assigned::from_bytes(ctx, gate, word)),
+        StaticType::Address => {
+            assert_zero_padding(ctx, &word[..12]);
+            let powers = (0..20).rev().map(|i| Constant(gate.pow_of_two()[8 * i]));
+            AbiValue::Address(gate.inner_product(ctx, word[12..].iter().copied(), powers))
+        }
+        StaticType::Bool => {
+            assert_zero_padding(ctx, &word[..31]);
+            gate.assert_bit(ctx, word[31]);
+            AbiValue::Bool(word[31])
+        }
+        StaticType::Tuple(_) => unreachable!(),
+    }
+}
+
+/// The bytes of `abi.encode(values)`, for static values.
+///
+/// Range checks addresses to 20 bytes and constrains bools to be 0 or 1.
+pub fn abi_encode<F: Field>(
+    ctx: &mut Context<F>,
+    range: &impl RangeInstructions<F>,
+    values: &[AbiValue<F>],
+) -> Vec<AssignedValue<F>> {
+    values.iter().flat_map(|value| encode_value(ctx, range, value, false)).collect()
+}
+
+/// The bytes of `abi.encodePacked(values)`, where addresses take 20 bytes and bools 1 byte. Tuples are not supported,
+/// as in Solidity.
+pub fn abi_encode_packed<F: Field>(
+    ctx: &mut Context<F>,
+    range: &impl RangeInstructions<F>,
+    values: &[AbiValue<F>],
+) -> Vec<AssignedValue<F>> {
+    values.iter().flat_map(|value| encode_value(ctx, range, value, true)).collect()
+}
+
+fn encode_value<F: Field>(
+    ctx: &mut Context<F>,
+    range: &impl RangeInstructions<F>,
+    value: &AbiValue<F>,
+    packed: bool,
+) -> Vec<AssignedValue<F>> {
+    let pad = |ctx: &mut Context<F>, bytes: Vec<AssignedValue<F>>| {
+        if packed {
+            return bytes;
+        }
+        let mut word = vec![ctx.load_zero(); 32 - bytes.len()];
+        word.extend(bytes);
+        word
+    };
+    match value {
+        AbiValue::Uint256(word) | AbiValue::Bytes32(word) => word.bytes.to_vec(),
+        AbiValue::Address(address) => {
+            let bytes = be_bytes(ctx, range, *address, 20);
+            pad(ctx, bytes)
+        }
+        AbiValue::Bool(bit) => {
+            range.gate().assert_bit(ctx, *bit);
+            pad(ctx, vec![*bit])
+        }
+        AbiValue::Tuple(values) => {
+            assert!(!packed, "abi.encodePacked does not support tuples");
+            values.iter().flat_map(|value| encode_value(ctx, range, value, packed)).collect()
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use std::mem::take;
+
+use ark_std::{end_timer, start_timer};
+use halo2_base::{
+    gates::builder::{CircuitBuilderStage, MultiPhaseThreadBreakPoints},
+    halo2_proofs::{
+        halo2curves::{
+            bn256::{Bn256, Fq, Fr, G1Affine},
+            pairing::Engine,
+            CurveAffine,
+        },
+        plonk::{keygen_pk, keygen_vk, ProvingKey},
+        poly::{commitment::Params, kzg::commitment::ParamsKZG},
+    },
+    AssignedValue,
+};
+use rand_chacha::ChaCha20Rng;
+use rand_core::{OsRng, SeedableRng};
+use snark_verifier_sdk::{
+    halo2::{
+        aggregation::{aggregate, AggregationCircuit, BaseFieldEccChip, FpChip, Halo2Loader, Svk},
+        gen_proof_shplonk, PoseidonTranscript, POSEIDON_SPEC,
+    },
+    snark_verifier::{
+        loader::native::NativeLoader,
+        pcs::AccumulationSchemeProver,
+        system::halo2::{compile, Config},
+        util::arithmetic::fe_from_limbs,
+        verifier::{plonk::PlonkSuccinctVerifier, SnarkVerifier},
+    },
+    CircuitExt, Snark, BITS, LIMBS, SHPLONK,
+};
+
+use crate::{
+    error::{AxiomError, Result},
+    keys::lock_config_params,
+    proof::{prove_circuit, ProofArtifacts, ProvingOptions},
+    scaffold::AxiomChip,
+};
+
+/// The number of rows reserved at the bottom of the aggregation circuit for blinding factors.
+const AGGREGATION_UNUSABLE_ROWS: usize = 20;
+
+impl AxiomChip<Fr> {
+    /// Proves the circuit with an existing proving key and returns it as a [`Snark`] that can be aggregated.
+    ///
+    /// The proof uses a Poseidon transcript, which is cheap to verify inside the aggregation circuit.
+    /// The chip must have been created with [`AxiomChip::prover`] from the pinning returned by [`AxiomChip::gen_keys`].
+    pub fn gen_snark(self, params: &ParamsKZG<Bn256>, pk: &ProvingKey<G1Affine>) -> Snark {
+        assert!(self.pinning().is_some(), "proving with an existing key requires a pinning");
+        let _config_lock = lock_config_params();
+        let accumulator_indices =
+            self.has_accumulator.then(|| AggregationCircuit::accumulator_indices().unwrap());
+        let protocol = compile(
+            params,
+            pk.get_vk(),
+            Config::kzg()
+                .with_num_instance(vec![self.instances().len()])
+                .with_accumulator_indices(accumulator_indices),
+        );
+        let circuit = self.create(None);
+        let instances = vec![circuit.instance()];
+        let proof = gen_proof_shplonk(params, pk, circuit, instances.clone(), None);
+        Snark::new(protocol, instances, proof)
+    }
+
+    /// Verifies `snarks` inside this circuit, for example a previous proof of this same circuit, so that state can be
+    /// rolled forward without re-proving history. Returns the public instances of each snark as assigned values.
+    ///
+    /// Verification is deferred: the snarks are folded into a KZG accumulator, which is exposed as the first
+    /// 12 public instances of this circuit. Whoever verifies this circuit must also check the accumulator, either by
+    /// aggregating its snark with [`prove_aggregation`] or by verifying it inside another circuit with this function.
+    ///
+    /// Can only be called once per circuit; pass all snarks to be verified at once. Returns
+    /// [`AxiomError::InvalidSnark`] if a snark cannot be read or does not verify.
+    pub fn verify_snarks(
+        &mut self,
+        params: &ParamsKZG<Bn256>,
+        snarks: Vec<Snark>,
+    ) -> Result<Vec<Vec<AssignedValue<Fr>>>> {
+        assert!(!self.has_accumulator, "verify_snarks can only be called once per circuit");
+        let svk: Svk = params.get_g()[0].into();
+
+        // Off-circuit: succinctly verify each snark and create the proof of accumulation
+        let mut transcript_read =
+            PoseidonTranscript::<NativeLoader, &[u8]>::from_spec(&[], POSEIDON_SPEC.clone());
+        let mut accumulators = vec![];
+        for (i, snark) in snarks.iter().enumerate() {
+            transcript_read.new_stream(snark.proof.as_slice());
+            let proof = PlonkSuccinctVerifier::<SHPLONK>::read_proof(
+                &svk,
+                &snark.protocol,
+                &snark.instances,
+                &mut transcript_read,
+            )
+            .map_err(|err| AxiomError::InvalidSnark(format!("cannot read snark {i}: {err:?}")))?;
+            accumulators.extend(
+                PlonkSuccinctVerifier::<SHPLONK>::verify(
+                    &svk,
+                    &snark.protocol,
+                    &snark.instances,
+                    &proof,
+                )
+                .map_err(|err| AxiomError::InvalidSnark(format!("snark {i}: {err:?}")))?,
+            );
+        }
+        let mut transcript_write =
+            PoseidonTranscript::<NativeLoader, Vec<u8>>::from_spec(vec![], POSEIDON_SPEC.clone());
+        // the accumulation proof is part of the witness, so it is seeded like the proof of this circuit
+        let pk = Default::default();
+        match self.proving_options().rng_seed {
+            Some(seed) => {
+                let rng = ChaCha20Rng::seed_from_u64(seed);
+                SHPLONK::create_proof(&pk, &accumulators, &mut transcript_write, rng)
+            }
+            None => SHPLONK::create_proof(&pk, &accumulators, &mut transcript_write, OsRng),
+        }
+        .map_err(|err| AxiomError::InvalidSnark(format!("accumulation failed: {err:?}")))?;
+        let as_proof = transcript_write.finalize();
+
+        // In-circuit: run the same verification on the gate builder of this chip
+        let (previous_instances, accumulator) = {
+            let gate_builder = take(&mut self.builder.lock().gate_builder);
+            let fp_chip = FpChip::<Fr>::new(&self.range, BITS, LIMBS);
+            let ecc_chip = BaseFieldEccChip::new(&fp_chip);
+            let loader = Halo2Loader::new(ecc_chip, gate_builder);
+            let (previous_instances, accumulator) =
+                aggregate::<SHPLONK>(&svk, &loader, &snarks, &as_proof);
+            let (lhs, rhs) = (accumulator.lhs.assigned(), accumulator.rhs.assigned());
+            let accumulator = [lhs.x(), lhs.y(), rhs.x(), rhs.y()]
+                .into_iter()
+                .flat_map(|coordinate| coordinate.limbs().to_vec())
+                .collect::<Vec<_>>();
+            self.builder.lock().gate_builder = loader.take_ctx();
+            (previous_instances, accumulator)
+        };
+        self.instances.splice(0..0, accumulator);
+        self.has_accumulator = true;
+        Ok(previous_instances)
+    }
+}
+
+/// Creates a circuit verifying all of `snarks` at once.
+///
+/// The public instances of the aggregation circuit are the 12 limbs of the KZG accumulator, followed by the
+/// public instances of each snark in order.
+pub fn aggregation_circuit(
+    stage: CircuitBuilderStage,
+    break_points: Option<MultiPhaseThreadBreakPoints>,
+    params: &ParamsKZG<Bn256>,
+    snarks: Vec<Snark>,
+) -> AggregationCircuit {
+    let lookup_bits = params.k() as usize - 1;
+    AggregationCircuit::public::<SHPLONK>(stage, break_points, lookup_bits, params, snarks, false)
+}
+
+/// Aggregates `snarks` into a single proof, created according to `options`. The aggregation circuit has
+/// `2^params.k()` rows.
+///
+/// Will time the generation of verify key & proving key of the aggregation circuit, then create and verify the aggregate
+/// proof, including its accumulator. Returns [`AxiomError::InvalidProof`] if the aggregate proof is invalid.
+/// To verify the aggregate proof on-chain, prove with [`TranscriptKind::Keccak`](crate::proof::TranscriptKind::Keccak)
+/// and generate the verifier with [`ProofArtifacts::gen_evm_verifier`].
+///
+/// Warning: This is memory and compute intensive, the aggregation circuit typically needs `k >= 20`.
+pub fn prove_aggregation(
+    params: &ParamsKZG<Bn256>,
+    snarks: Vec<Snark>,
+    options: ProvingOptions,
+) -> Result<ProofArtifacts> {
+    let k = params.k();
+    let _config_lock = lock_config_params();
+    let circuit = aggregation_circuit(CircuitBuilderStage::Keygen, None, params, snarks.clone());
+    circuit.config(k, Some(AGGREGATION_UNUSABLE_ROWS));
+
+    let vk_time = start_timer!(|| "Generating aggregation verifying key");
+    let vk = keygen_vk(params, &circuit)?;
+    end_timer!(vk_time);
+    let pk_time = start_timer!(|| "Generating aggregation proving key");
+    let pk = keygen_pk(params, vk, &circuit)?;
+    end_timer!(pk_time);
+    let break_points = circuit.break_points();
+
+    let circuit =
+        aggregation_circuit(CircuitBuilderStage::Prover, Some(break_points), params, snarks);
+    let instance = circuit.instances().swap_remove(0);
+    prove_circuit(params, &pk, circuit, instance, options, true)
+}
+
+/// Checks the KZG accumulator in the first 12 of `instances`, i.e. the final pairing check of the snarks it
+/// accumulates, which verifying the proof that exposes it does not do.
+pub fn check_accumulator(params: &ParamsKZG<Bn256>, instances: &[Fr]) -> bool {
+    if instances.len() < 4 * LIMBS {
+        return false;
+    }
+    let coordinates: Vec<Fq> = instances[..4 * LIMBS]
+        .chunks(LIMBS)
+        .map(|limbs| fe_from_limbs::<_, _, LIMBS, BITS>(limbs.try_into().unwrap()))
+        .collect();
+    let [lhs, rhs] = [0, 2]
+        .map(|i| Option::<G1Affine>::from(G1Affine::from_xy(coordinates[i], coordinates[i + 1])));
+    match (lhs, rhs) {
+        (Some(lhs), Some(rhs)) => {
+            Bn256::pairing(&lhs, &params.g2()) == Bn256::pairing(&rhs, &params.s_g2())
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,96 @@
+use std::{
+    fs::{create_dir_all, File},
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use axiom_eth::Network;
+use ethers_core::types::{Address, Block, EIP1186ProofResponse, StorageProof, H256};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::providers::chain_id;
+
+/// An on-disk cache of fetched chain data, so that repeated runs during development do not refetch everything from
+/// the provider.
+///
+/// Block headers are stored per `(chain, block)`, account proofs per `(chain, block, address)`, and storage proofs
+/// per `(chain, block, address, slot)`, each as a JSON file in `dir/<chain id>/<block>/`.
+#[derive(Clone, Debug)]
+pub struct ChainDataCache {
+    pub dir: PathBuf,
+}
+
+impl ChainDataCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn block_dir(&self, network: Network, block_number: u64) -> PathBuf {
+        self.dir.join(chain_id(network).to_string()).join(block_number.to_string())
+    }
+
+    /// Returns the cached block `block_number`, if any.
+    pub(crate) fn block(&self, network: Network, block_number: u64) -> Option<Block<H256>> {
+        read_json(&self.block_dir(network, block_number).join("header.json"))
+    }
+
+    pub(crate) fn put_block(&self, network: Network, block: &Block<H256>) -> io::Result<()> {
+        let block_number = block.number.expect("block has no number").as_u64();
+        write_json(&self.block_dir(network, block_number), "header.json", block)
+    }
+
+    /// Returns the cached `eth_getProof` response for `slots` of `address` at block `block_number`, if the account
+    /// and all slots are cached.
+    pub(crate) fn proof(
+        &self,
+        network: Network,
+        block_number: u64,
+        address: Address,
+        slots: &[H256],
+    ) -> Option<EIP1186ProofResponse> {
+        let dir = self.block_dir(network, block_number).join(format!("{address:?}"));
+        let mut proof: EIP1186ProofResponse = read_json(&dir.join("account.json"))?;
+        proof.storage_proof = slots
+            .iter()
+            .map(|slot| read_json(&dir.join(format!("{slot:?}.json"))))
+            .collect::<Option<_>>()?;
+        Some(proof)
+    }
+
+    pub(crate) fn put_proof(
+        &self,
+        network: Network,
+        block_number: u64,
+        proof: &EIP1186ProofResponse,
+    ) -> io::Result<()> {
+        let dir = self.block_dir(network, block_number).join(format!("{:?}", proof.address));
+        let account = EIP1186ProofResponse { storage_proof: vec![], ..proof.clone() };
+        write_json(&dir, "account.json", &account)?;
+        for storage_proof in &proof.storage_proof {
+            write_json::<StorageProof>(
+                &dir,
+                &format!("{:?}.json", storage_proof.key),
+                storage_proof,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let file = File::open(path).ok()?;
+    match serde_json::from_reader(BufReader::new(file)) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            log::warn!("Ignoring unreadable cache file {}: {err}", path.display());
+            None
+        }
+    }
+}
+
+fn write_json<T: Serialize>(dir: &Path, name: &str, value: &T) -> io::Result<()> {
+    create_dir_all(dir)?;
+    let file = File::create(dir.join(name))?;
+    serde_json::to_writer(BufWriter::new(file), value)?;
+    Ok(())
+}
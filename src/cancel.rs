@@ -0,0 +1,35 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use crate::error::{AxiomError, Result};
+
+/// A flag to abort a long running job from another thread, see [`AxiomChip::set_cancellation`](crate::scaffold::AxiomChip::set_cancellation).
+///
+/// Clones share the same flag.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests that everything holding a clone of this token stops at its next check.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Returns [`AxiomError::Cancelled`] if the token was cancelled.
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(AxiomError::Cancelled);
+        }
+        Ok(())
+    }
+}
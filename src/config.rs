@@ -0,0 +1,76 @@
+use std::{env::var, str::FromStr};
+
+/// The circuit parameters of an [`AxiomChip`](crate::scaffold::AxiomChip).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AxiomChipConfig {
+    /// The circuit has 2<sup>k</sup> rows.
+    pub k: usize,
+    /// The lookup table for range checks has 2<sup>lookup_bits</sup> rows, so this must be less than `k`.
+    pub lookup_bits: usize,
+    /// The number of rows at the bottom of each column reserved for blinding factors.
+    pub unusable_rows: usize,
+    /// If set, `k` is ignored and instead chosen automatically when the circuit is created.
+    pub auto_degree: Option<AutoDegree>,
+    /// If set, the keccak circuit uses this many rows per round instead of as many as fit in 2<sup>k</sup> rows. Fewer
+    /// rows per round use more columns.
+    pub keccak_rows_per_round: Option<usize>,
+}
+
+/// Bounds for automatic selection of `k`, see [`AxiomChip::select_degree`](crate::scaffold::AxiomChip::select_degree).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AutoDegree {
+    /// The largest `k` to consider.
+    pub max_k: usize,
+    /// The maximum number of advice columns, over all phases. Smaller `k` trades rows for columns, and too many columns
+    /// make proving slower even though the circuit fits.
+    pub max_advice_columns: usize,
+}
+
+impl Default for AutoDegree {
+    fn default() -> Self {
+        Self { max_k: 23, max_advice_columns: 32 }
+    }
+}
+
+impl AxiomChipConfig {
+    /// Reads the config from the `DEGREE`, `LOOKUP_BITS`, and `UNUSABLE_ROWS` environment variables,
+    /// defaulting to `18`, `8`, and `109` respectively.
+    ///
+    /// If `DEGREE=auto`, the degree is chosen automatically, up to `MAX_DEGREE` if it is set. The keccak rows per round
+    /// are read from `KECCAK_ROWS_PER_ROUND` if it is set. Variables that are not valid numbers are logged and ignored.
+    pub fn from_env() -> Self {
+        let (k, auto_degree) = if var("DEGREE").map_or(false, |degree| degree == "auto") {
+            let mut auto = AutoDegree::default();
+            if let Some(max_k) = parse_var("MAX_DEGREE") {
+                auto.max_k = max_k;
+            }
+            (auto.max_k, Some(auto))
+        } else {
+            (parse_var("DEGREE").unwrap_or(18), None)
+        };
+        let lookup_bits = parse_var("LOOKUP_BITS").unwrap_or(8);
+        let unusable_rows = parse_var("UNUSABLE_ROWS").unwrap_or(109);
+        let keccak_rows_per_round = parse_var("KECCAK_ROWS_PER_ROUND");
+        Self { k, lookup_bits, unusable_rows, auto_degree, keccak_rows_per_round }
+    }
+}
+
+/// Parses the environment variable `name`, or returns `None` if it is not set. A value that does not parse is logged and
+/// then ignored like a missing one, since [`AxiomChipConfig::default`] reads the environment and cannot fail.
+fn parse_var<T: FromStr>(name: &str) -> Option<T> {
+    let value = var(name).ok()?;
+    match value.parse() {
+        Ok(parsed) => Some(parsed),
+        Err(_) => {
+            log::warn!("Ignoring {name}={value:?}, which is not a valid number");
+            None
+        }
+    }
+}
+
+impl Default for AxiomChipConfig {
+    /// See [`AxiomChipConfig::from_env`].
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
@@ -1,11 +1,21 @@
+use std::fmt;
+
 use axiom_eth::{
     block_header::EthBlockHeaderTraceWitness,
     rlp::{evaluate_byte_array, RlpFieldWitness},
+    storage::EIP1186ResponseDigest,
     Field,
 };
-use halo2_base::{gates::GateInstructions, utils::ScalarField, AssignedValue, Context};
+use ethers_core::types::{Address, Bytes, H256, U256};
+use halo2_base::{
+    gates::{GateInstructions, RangeInstructions},
+    utils::{fe_to_biguint, ScalarField},
+    AssignedValue, Context,
+    QuantumCell::Constant,
+};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct ByteString<F: ScalarField> {
     /// the possibly variable length of the bytestring
     pub len: AssignedValue<F>,
@@ -13,12 +23,34 @@ pub struct ByteString<F: ScalarField> {
     pub bytes: Vec<AssignedValue<F>>,
 }
 
+/// The side of a byte string to pad with zeros, see [`ByteString::to_fixed_len`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Padding {
+    /// Zeros before the bytes, i.e. right aligned, like an ABI encoded `uint`.
+    Left,
+    /// Zeros after the bytes, i.e. left aligned, like an ABI encoded `bytesN`.
+    Right,
+}
+
 impl<'a, F: ScalarField> From<&'a RlpFieldWitness<F>> for ByteString<F> {
     fn from(value: &'a RlpFieldWitness<F>) -> Self {
         Self { len: value.field_len, bytes: value.field_cells.clone() }
     }
 }
 
+/// Prints the witness value of the byte string as hex, truncated to its length.
+impl<F: ScalarField> fmt::Display for ByteString<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{}", ethers_core::utils::hex::encode(self.value_bytes()))
+    }
+}
+
+impl<F: ScalarField> fmt::Debug for ByteString<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
 impl<F: ScalarField> ByteString<F> {
     /// Evaluates a variable-length byte string to a big endian number.
     ///
@@ -31,6 +63,210 @@ impl<F: ScalarField> ByteString<F> {
     ) -> AssignedValue<F> {
         evaluate_byte_array(ctx, gate, &self.bytes, self.len)
     }
+
+    /// Evaluates a variable-length byte string to a little endian number, ignoring the bytes past its length.
+    ///
+    /// Like [`ByteString::evaluate`], the result is modulo the prime of the scalar field if the number is larger.
+    pub fn evaluate_le(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+    ) -> AssignedValue<F> {
+        let masked = self.masked_bytes(ctx, gate);
+        let powers = std::iter::successors(Some(F::one()), |power| Some(*power * F::from(256)));
+        gate.inner_product(ctx, masked, powers.take(self.bytes.len()).map(Constant))
+    }
+
+    /// The byte string with its first `len` bytes in reverse order, padded with zeros to the same maximum length, e.g.
+    /// to read a little endian field as big endian.
+    ///
+    /// Each output byte is selected from all the bytes, so this uses a number of cells quadratic in the maximum length.
+    pub fn reverse(&self, ctx: &mut Context<F>, gate: &impl GateInstructions<F>) -> Self {
+        let bytes = (0..self.bytes.len())
+            .map(|i| {
+                // out of range, i.e. negative, for `i >= len`, in which case zero is selected
+                let idx = gate.sub(ctx, self.len, Constant(F::from(i as u64 + 1)));
+                gate.select_from_idx(ctx, self.bytes.iter().copied(), idx)
+            })
+            .collect();
+        Self { len: self.len, bytes }
+    }
+
+    /// The bytes of the byte string with the bytes past its length set to zero.
+    pub(crate) fn masked_bytes(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+    ) -> Vec<AssignedValue<F>> {
+        let is_len = gate.idx_to_indicator(ctx, self.len, self.bytes.len());
+        let mut is_past_len = ctx.load_zero();
+        let mut masked = Vec::with_capacity(self.bytes.len());
+        for (byte, is_len) in self.bytes.iter().zip(is_len) {
+            is_past_len = gate.add(ctx, is_past_len, is_len);
+            masked.push(gate.mul_not(ctx, is_past_len, *byte));
+        }
+        masked
+    }
+
+    /// The first `len` bytes of the byte string as exactly `n` bytes, padded with zeros on the side given by `padding`,
+    /// e.g. to feed a variable-length field into a keccak preimage or an ABI word. Constrains `len <= n`.
+    ///
+    /// Left padding selects each output byte from all the bytes, so it uses a number of cells quadratic in the maximum
+    /// length.
+    pub fn to_fixed_len(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+        n: usize,
+        padding: Padding,
+    ) -> Vec<AssignedValue<F>> {
+        let is_len = gate.idx_to_indicator(ctx, self.len, n + 1);
+        let len_fits = gate.sum(ctx, is_len);
+        gate.assert_is_const(ctx, &len_fits, &F::one());
+        match padding {
+            Padding::Right => {
+                let mut bytes = self.masked_bytes(ctx, gate);
+                bytes.truncate(n);
+                bytes.resize(n, ctx.load_zero());
+                bytes
+            }
+            Padding::Left => {
+                let start = gate.sub(ctx, Constant(F::from(n as u64)), self.len);
+                (0..n)
+                    .map(|i| {
+                        // out of range, i.e. negative, for the `n - len` leading bytes, in which case zero is selected
+                        let idx = gate.sub(ctx, Constant(F::from(i as u64)), start);
+                        gate.select_from_idx(ctx, self.bytes.iter().copied(), idx)
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Constrains the byte string to be `expected`, e.g. a known contract address or function selector: its length to
+    /// be `expected.len()` and its first bytes to be those of `expected`. Panics if `expected` is longer than the
+    /// maximum length.
+    pub fn constrain_constant(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+        expected: &[u8],
+    ) {
+        assert!(
+            expected.len() <= self.bytes.len(),
+            "constant is longer than the byte string can be"
+        );
+        gate.assert_is_const(ctx, &self.len, &F::from(expected.len() as u64));
+        for (byte, expected) in self.bytes.iter().zip(expected) {
+            gate.assert_is_const(ctx, byte, &F::from(*expected as u64));
+        }
+    }
+
+    /// The witness value of the byte string: its first `len` bytes.
+    pub fn value_bytes(&self) -> Vec<u8> {
+        let len = self.len.value().get_lower_32() as usize;
+        self.bytes[..len].iter().map(|byte| byte.value().get_lower_32() as u8).collect()
+    }
+
+    /// The witness value of the byte string as a big endian number. Panics if it is longer than 32 bytes.
+    pub fn value_u256(&self) -> U256 {
+        U256::from_big_endian(&self.value_bytes())
+    }
+
+    /// The witness value of the byte string as a 32 byte word, left padded with zeros if it is shorter. Panics if it
+    /// is longer than 32 bytes.
+    pub fn value_h256(&self) -> H256 {
+        let bytes = self.value_bytes();
+        let mut word = [0u8; 32];
+        word[32 - bytes.len()..].copy_from_slice(&bytes);
+        H256(word)
+    }
+
+    /// Same as [`ByteString::value_bytes`], as hex when serialized.
+    pub fn extract(&self) -> Bytes {
+        self.value_bytes().into()
+    }
+
+    /// The witness value of the byte string as a big endian number, for fields of at most 8 bytes.
+    fn value_u64(&self) -> u64 {
+        self.value_u256().as_u64()
+    }
+}
+
+/// The witness value of a 32 byte word given as hi-lo 128 bit halves.
+fn extract_h256<F: ScalarField>(hi: &AssignedValue<F>, lo: &AssignedValue<F>) -> H256 {
+    let mut bytes = [0u8; 32];
+    bytes[..16].copy_from_slice(&hi.value().get_lower_128().to_be_bytes());
+    bytes[16..].copy_from_slice(&lo.value().get_lower_128().to_be_bytes());
+    H256(bytes)
+}
+
+/// The witness value of an address encoded as a field element.
+fn extract_address<F: ScalarField>(value: &AssignedValue<F>) -> Address {
+    let be_bytes = fe_to_biguint(value.value()).to_bytes_be();
+    let mut bytes = [0u8; 20];
+    bytes[20 - be_bytes.len()..].copy_from_slice(&be_bytes);
+    Address(bytes)
+}
+
+fn extract_bool<F: ScalarField>(value: &AssignedValue<F>) -> bool {
+    value.value().get_lower_32() != 0
+}
+
+/// A 32 byte word, e.g. a hash, assigned as its big endian bytes together with its value as hi-lo 128 bit halves.
+#[derive(Clone)]
+pub struct H256Assigned<F: ScalarField> {
+    pub bytes: [AssignedValue<F>; 32],
+    pub hi: AssignedValue<F>,
+    pub lo: AssignedValue<F>,
+}
+
+impl<F: ScalarField> fmt::Display for H256Assigned<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.extract())
+    }
+}
+
+impl<F: ScalarField> fmt::Debug for H256Assigned<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl<F: ScalarField> H256Assigned<F> {
+    /// Packs 32 big endian bytes into hi-lo halves. The bytes must already be constrained to be bytes.
+    pub fn from_bytes(
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+        bytes: [AssignedValue<F>; 32],
+    ) -> Self {
+        let [hi, lo] = [&bytes[..16], &bytes[16..]].map(|half| {
+            let powers = (0..16).rev().map(|i| Constant(gate.pow_of_two()[8 * i]));
+            gate.inner_product(ctx, half.iter().copied(), powers)
+        });
+        Self { bytes, hi, lo }
+    }
+
+    pub fn hi_lo(&self) -> [AssignedValue<F>; 2] {
+        [self.hi, self.lo]
+    }
+
+    /// The witness value of the word.
+    pub fn extract(&self) -> H256 {
+        extract_h256(&self.hi, &self.lo)
+    }
+}
+
+/// The hi-lo halves of the 32 byte field `field`, or zero if it has another length, e.g. a field missing from the
+/// headers before a fork.
+fn word_hi_lo<F: Field>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    field: &ByteString<F>,
+) -> [AssignedValue<F>; 2] {
+    let is_word = gate.is_equal(ctx, field.len, Constant(F::from(32)));
+    let bytes = field.bytes[..32].try_into().unwrap();
+    H256Assigned::from_bytes(ctx, gate, bytes).hi_lo().map(|half| gate.mul(ctx, half, is_word))
 }
 
 /// The max bytes per bytestring field are, for reference:
@@ -40,7 +276,7 @@ impl<F: ScalarField> ByteString<F> {
 /// const GOERLI_HEADER_FIELDS_MAX_BYTES: [usize; _] =
 /// [32, 32, 20, 32, 32, 32, 256, 7, 4, 4, 4, 4, 97, 32, 8, 6, 32];
 /// ```
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct EthBlock<F: Field> {
     pub parent_hash: ByteString<F>,
     pub ommers_hash: ByteString<F>,
@@ -60,30 +296,324 @@ pub struct EthBlock<F: Field> {
     pub basefee: ByteString<F>, // this will be 0 (or undefined) if before London
     pub withdrawals_root: ByteString<F>, // this will be 0 (or undefined) if before Shapella
 
-    pub block_hash: [AssignedValue<F>; 32],
+    pub block_hash: H256Assigned<F>,
+}
+
+/// The values of the fields of an [`EthBlock`], see [`EthBlock::evaluate_all`]. Numeric fields are single field
+/// elements, and 32 byte hashes and roots are hi-lo 128 bit halves.
+#[derive(Clone, Debug)]
+pub struct EthBlockValues<F: Field> {
+    pub number: AssignedValue<F>,
+    pub timestamp: AssignedValue<F>,
+    pub gas_used: AssignedValue<F>,
+    pub gas_limit: AssignedValue<F>,
+    /// Zero before London.
+    pub basefee: AssignedValue<F>,
+    /// Zero after the merge.
+    pub difficulty: AssignedValue<F>,
+    pub parent_hash: [AssignedValue<F>; 2],
+    pub ommers_hash: [AssignedValue<F>; 2],
+    pub state_root: [AssignedValue<F>; 2],
+    pub transactions_root: [AssignedValue<F>; 2],
+    pub receipts_root: [AssignedValue<F>; 2],
+    pub mix_hash: [AssignedValue<F>; 2],
+    /// Zero before Shapella.
+    pub withdrawals_root: [AssignedValue<F>; 2],
+    pub block_hash: [AssignedValue<F>; 2],
+}
+
+/// The witness values of the fields of an [`EthBlock`], see [`EthBlock::extract`]. Byte string fields are hex encoded
+/// with their actual length, and numeric fields are numbers.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EthBlockData {
+    pub parent_hash: Bytes,
+    pub ommers_hash: Bytes,
+    pub beneficiary: Bytes,
+    pub state_root: Bytes,
+    pub transactions_root: Bytes,
+    pub receipts_root: Bytes,
+    pub logs_bloom: Bytes,
+    pub difficulty: U256,
+    pub number: u64,
+    pub gas_limit: u64,
+    pub gas_used: u64,
+    pub timestamp: u64,
+    pub extra_data: Bytes,
+    pub mix_hash: Bytes,
+    pub nonce: Bytes,
+    /// Zero before London.
+    pub basefee: U256,
+    /// Empty before Shapella.
+    pub withdrawals_root: Bytes,
+    pub block_hash: H256,
+}
+
+impl<F: Field> EthBlock<F> {
+    /// The header fields of `witness`, whose block hash is `block_hash`, i.e. the hash bytes of `witness` packed into
+    /// hi-lo halves once when the header was assigned, see [`H256Assigned::from_bytes`].
+    pub fn new(witness: &EthBlockHeaderTraceWitness<F>, block_hash: H256Assigned<F>) -> Self {
+        Self {
+            parent_hash: witness.get("parent_hash").into(),
+            ommers_hash: witness.get("ommers_hash").into(),
+            beneficiary: witness.get("beneficiary").into(),
+            state_root: witness.get("state_root").into(),
+            transactions_root: witness.get("transactions_root").into(),
+            receipts_root: witness.get("receipts_root").into(),
+            logs_bloom: witness.get("logs_bloom").into(),
+            difficulty: witness.get("difficulty").into(),
+            number: witness.get("number").into(),
+            gas_limit: witness.get("gas_limit").into(),
+            gas_used: witness.get("gas_used").into(),
+            timestamp: witness.get("timestamp").into(),
+            extra_data: witness.get("extra_data").into(),
+            mix_hash: witness.get("mix_hash").into(),
+            nonce: witness.get("nonce").into(),
+            basefee: witness.get("basefee").into(),
+            withdrawals_root: witness.get("withdrawals_root").into(),
+            block_hash,
+        }
+    }
+
+    /// The witness values of the header fields, e.g. to log or persist what a proof is about alongside the proof.
+    pub fn extract(&self) -> EthBlockData {
+        EthBlockData {
+            parent_hash: self.parent_hash.extract(),
+            ommers_hash: self.ommers_hash.extract(),
+            beneficiary: self.beneficiary.extract(),
+            state_root: self.state_root.extract(),
+            transactions_root: self.transactions_root.extract(),
+            receipts_root: self.receipts_root.extract(),
+            logs_bloom: self.logs_bloom.extract(),
+            difficulty: self.difficulty.value_u256(),
+            number: self.number.value_u64(),
+            gas_limit: self.gas_limit.value_u64(),
+            gas_used: self.gas_used.value_u64(),
+            timestamp: self.timestamp.value_u64(),
+            extra_data: self.extra_data.extract(),
+            mix_hash: self.mix_hash.extract(),
+            nonce: self.nonce.extract(),
+            basefee: self.basefee.value_u256(),
+            withdrawals_root: self.withdrawals_root.extract(),
+            block_hash: self.block_hash.extract(),
+        }
+    }
+
+    /// Evaluates every numeric field of the header to a number and every 32 byte field to hi-lo halves, so that hashes
+    /// are not evaluated as numbers by mistake, which wraps around the scalar field.
+    pub fn evaluate_all(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+    ) -> EthBlockValues<F> {
+        EthBlockValues {
+            number: self.number.evaluate(ctx, gate),
+            timestamp: self.timestamp.evaluate(ctx, gate),
+            gas_used: self.gas_used.evaluate(ctx, gate),
+            gas_limit: self.gas_limit.evaluate(ctx, gate),
+            basefee: self.basefee.evaluate(ctx, gate),
+            difficulty: self.difficulty.evaluate(ctx, gate),
+            parent_hash: word_hi_lo(ctx, gate, &self.parent_hash),
+            ommers_hash: word_hi_lo(ctx, gate, &self.ommers_hash),
+            state_root: word_hi_lo(ctx, gate, &self.state_root),
+            transactions_root: word_hi_lo(ctx, gate, &self.transactions_root),
+            receipts_root: word_hi_lo(ctx, gate, &self.receipts_root),
+            mix_hash: word_hi_lo(ctx, gate, &self.mix_hash),
+            withdrawals_root: word_hi_lo(ctx, gate, &self.withdrawals_root),
+            block_hash: self.block_hash.hi_lo(),
+        }
+    }
+
+    /// The `prevRandao` of the block, the RANDAO value of the beacon chain for the previous slot, which is stored in the
+    /// `mix_hash` field of post-merge headers (EIP-4399). Before the merge, this is the proof of work mix hash instead.
+    ///
+    /// Constrains the field to be exactly 32 bytes, each range checked, so the hi-lo halves are a sound packing, e.g.,
+    /// to use as a source of randomness.
+    pub fn prev_randao(
+        &self,
+        ctx: &mut Context<F>,
+        range: &impl RangeInstructions<F>,
+    ) -> H256Assigned<F> {
+        let gate = range.gate();
+        gate.assert_is_const(ctx, &self.mix_hash.len, &F::from(32));
+        let bytes: [AssignedValue<F>; 32] = self.mix_hash.bytes[..32].try_into().unwrap();
+        for byte in bytes {
+            range.range_check(ctx, byte, 8);
+        }
+        H256Assigned::from_bytes(ctx, gate, bytes)
+    }
+}
+
+/// Prints the witness values of the header fields as hex, each byte string truncated to its length.
+impl<F: Field> fmt::Debug for EthBlock<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EthBlock")
+            .field("parent_hash", &self.parent_hash)
+            .field("ommers_hash", &self.ommers_hash)
+            .field("beneficiary", &self.beneficiary)
+            .field("state_root", &self.state_root)
+            .field("transactions_root", &self.transactions_root)
+            .field("receipts_root", &self.receipts_root)
+            .field("logs_bloom", &self.logs_bloom)
+            .field("difficulty", &self.difficulty)
+            .field("number", &self.number)
+            .field("gas_limit", &self.gas_limit)
+            .field("gas_used", &self.gas_used)
+            .field("timestamp", &self.timestamp)
+            .field("extra_data", &self.extra_data)
+            .field("mix_hash", &self.mix_hash)
+            .field("nonce", &self.nonce)
+            .field("basefee", &self.basefee)
+            .field("withdrawals_root", &self.withdrawals_root)
+            .field("block_hash", &self.block_hash)
+            .finish()
+    }
+}
+
+/// The nonce and balance of an account at a block, see
+/// [`AxiomChip::eth_getAccount`](crate::scaffold::AxiomChip::eth_getAccount).
+#[derive(Clone)]
+pub struct EthAccount<F: Field> {
+    pub address: AssignedValue<F>,
+    pub block_number: AssignedValue<F>,
+    /// The block hash as hi-lo 128 bit halves.
+    pub block_hash: (AssignedValue<F>, AssignedValue<F>),
+    /// Whether the account does not exist in the state trie, in which case its nonce and balance are zero.
+    pub is_empty: AssignedValue<F>,
+    pub nonce: AssignedValue<F>,
+    /// The balance in wei.
+    pub balance: AssignedValue<F>,
+}
+
+/// An account and the values of some of its storage slots at a block, with the block hash and slots as words, see
+/// [`AxiomChip::eth_getAccountStorage`](crate::scaffold::AxiomChip::eth_getAccountStorage).
+#[derive(Clone)]
+pub struct EthAccountStorage<F: Field> {
+    pub address: AssignedValue<F>,
+    pub block_number: AssignedValue<F>,
+    pub block_hash: H256Assigned<F>,
+    /// Whether the account does not exist in the state trie, in which case its fields and slot values are empty.
+    pub is_empty: AssignedValue<F>,
+    pub nonce: ByteString<F>,
+    /// The balance in wei.
+    pub balance: ByteString<F>,
+    pub storage_root: ByteString<F>,
+    pub code_hash: ByteString<F>,
+    /// Each slot with its value, which is empty if the slot does not exist.
+    pub slots: Vec<(H256Assigned<F>, ByteString<F>)>,
+}
+
+/// The witness value of a field element as hex.
+struct FieldHex<'a, F: ScalarField>(&'a AssignedValue<F>);
+
+impl<F: ScalarField> fmt::Debug for FieldHex<'_, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.0.value())
+    }
+}
+
+impl<F: Field> fmt::Debug for EthAccount<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EthAccount")
+            .field("address", &extract_address(&self.address))
+            .field("block_number", &self.block_number.value().get_lower_64())
+            .field("block_hash", &extract_h256(&self.block_hash.0, &self.block_hash.1))
+            .field("is_empty", &extract_bool(&self.is_empty))
+            .field("nonce", &FieldHex(&self.nonce))
+            .field("balance", &FieldHex(&self.balance))
+            .finish()
+    }
+}
+
+impl<F: Field> fmt::Debug for EthAccountStorage<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EthAccountStorage")
+            .field("address", &extract_address(&self.address))
+            .field("block_number", &self.block_number.value().get_lower_64())
+            .field("block_hash", &self.block_hash)
+            .field("is_empty", &extract_bool(&self.is_empty))
+            .field("nonce", &self.nonce)
+            .field("balance", &self.balance)
+            .field("storage_root", &self.storage_root)
+            .field("code_hash", &self.code_hash)
+            .field("slots", &self.slots)
+            .finish()
+    }
+}
+
+/// The witness values of an [`EthAccountStorage`], see [`EthAccountStorage::extract`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EthAccountStorageData {
+    pub address: Address,
+    pub block_number: u64,
+    pub block_hash: H256,
+    pub is_empty: bool,
+    pub nonce: Bytes,
+    pub balance: Bytes,
+    pub storage_root: Bytes,
+    pub code_hash: Bytes,
+    pub slots: Vec<(H256, Bytes)>,
+}
+
+impl<F: Field> EthAccountStorage<F> {
+    /// The witness values of the account and its slots, e.g. to log or persist what a proof is about alongside the
+    /// proof.
+    pub fn extract(&self) -> EthAccountStorageData {
+        EthAccountStorageData {
+            address: extract_address(&self.address),
+            block_number: self.block_number.value().get_lower_64(),
+            block_hash: self.block_hash.extract(),
+            is_empty: extract_bool(&self.is_empty),
+            nonce: self.nonce.extract(),
+            balance: self.balance.extract(),
+            storage_root: self.storage_root.extract(),
+            code_hash: self.code_hash.extract(),
+            slots: self
+                .slots
+                .iter()
+                .map(|(slot, value)| (slot.extract(), value.extract()))
+                .collect(),
+        }
+    }
+}
+
+/// The witness values of a storage proof query, see
+/// [`AxiomChip::eth_getProof`](crate::scaffold::AxiomChip::eth_getProof), with words as 32 bytes instead of hi-lo
+/// halves.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageDigestData {
+    pub address: Address,
+    pub block_number: u64,
+    pub block_hash: H256,
+    pub address_is_empty: bool,
+    pub slots: Vec<StorageSlotData>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageSlotData {
+    pub slot: H256,
+    /// Zero if the slot does not exist.
+    pub value: H256,
+    pub is_empty: bool,
 }
 
-impl<'a, F: Field> From<&'a EthBlockHeaderTraceWitness<F>> for EthBlock<F> {
-    fn from(value: &'a EthBlockHeaderTraceWitness<F>) -> Self {
+impl<'a, F: Field> From<&'a EIP1186ResponseDigest<F>> for StorageDigestData {
+    fn from(digest: &'a EIP1186ResponseDigest<F>) -> Self {
+        let slots = digest
+            .slots_values
+            .iter()
+            .zip(digest.slot_is_empty.iter())
+            .map(|(([slot_hi, slot_lo], [value_hi, value_lo]), is_empty)| StorageSlotData {
+                slot: extract_h256(slot_hi, slot_lo),
+                value: extract_h256(value_hi, value_lo),
+                is_empty: extract_bool(is_empty),
+            })
+            .collect();
         Self {
-            parent_hash: value.get("parent_hash").into(),
-            ommers_hash: value.get("ommers_hash").into(),
-            beneficiary: value.get("beneficiary").into(),
-            state_root: value.get("state_root").into(),
-            transactions_root: value.get("transactions_root").into(),
-            receipts_root: value.get("receipts_root").into(),
-            logs_bloom: value.get("logs_bloom").into(),
-            difficulty: value.get("difficulty").into(),
-            number: value.get("number").into(),
-            gas_limit: value.get("gas_limit").into(),
-            gas_used: value.get("gas_used").into(),
-            timestamp: value.get("timestamp").into(),
-            extra_data: value.get("extra_data").into(),
-            mix_hash: value.get("mix_hash").into(),
-            nonce: value.get("nonce").into(),
-            basefee: value.get("basefee").into(),
-            withdrawals_root: value.get("withdrawals_root").into(),
-            block_hash: value.block_hash.clone().try_into().unwrap(),
+            address: extract_address(&digest.address),
+            block_number: digest.block_number.value().get_lower_64(),
+            block_hash: extract_h256(&digest.block_hash.0, &digest.block_hash.1),
+            address_is_empty: extract_bool(&digest.address_is_empty),
+            slots,
         }
     }
 }
@@ -0,0 +1,199 @@
+use std::{collections::BTreeSet, fmt, fs::File, io, ops::Range, path::Path};
+
+use axiom_eth::Field;
+use ethers_core::types::{Address, H256};
+use halo2_base::{
+    halo2_proofs::dev::{FailureLocation, VerifyFailure},
+    AssignedValue,
+};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::{
+    containers::{EthBlockData, StorageDigestData},
+    scaffold::AxiomChip,
+};
+
+/// The query that assigned a group of cells, used to explain mock prover failures and to report the resources used by
+/// each query.
+#[derive(Clone, Debug, Serialize)]
+pub enum QuerySource {
+    /// The `index`-th block header query.
+    BlockHeader { index: usize },
+    /// The `index`-th account and storage proof query.
+    Storage { index: usize, address: Address, slots: Vec<H256> },
+}
+
+impl fmt::Display for QuerySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BlockHeader { index } => write!(f, "block header #{index}"),
+            Self::Storage { index, address, slots } => {
+                write!(f, "storage proof #{index} (address {address:?}")?;
+                for slot in slots {
+                    write!(f, ", slot {slot:?}")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// The first phase cells assigned by a query, as ranges of cell indices within each thread, together with the lookup
+/// cells and keccak capacity it used.
+#[derive(Clone, Debug)]
+pub(crate) struct QuerySpan {
+    pub(crate) source: QuerySource,
+    pub(crate) cells: Vec<(usize, Range<usize>)>,
+    pub(crate) lookup_cells: usize,
+    pub(crate) keccak_capacity: usize,
+}
+
+impl<F: Field> AxiomChip<F> {
+    /// Returns the number of advice cells in each first phase thread.
+    pub(crate) fn phase0_thread_lens(&self) -> Vec<usize> {
+        self.builder.lock().gate_builder.threads[0].iter().map(|ctx| ctx.advice.len()).collect()
+    }
+
+    /// Returns the number of cells to range check with the lookup table in each first phase thread.
+    fn phase0_thread_lookups(&self) -> Vec<usize> {
+        let builder = self.builder.lock();
+        builder.gate_builder.threads[0].iter().map(|ctx| ctx.cells_to_lookup.len()).collect()
+    }
+
+    /// Runs `assign` and records the cells it assigns as coming from `source`.
+    pub(crate) fn track_query<R>(
+        &mut self,
+        source: QuerySource,
+        assign: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        let before = self.phase0_thread_lens();
+        let lookups_before = self.phase0_thread_lookups().iter().sum::<usize>();
+        let keccak_before = self.keccak.lock().capacity();
+        let result = assign(self);
+        let cells = self
+            .phase0_thread_lens()
+            .into_iter()
+            .enumerate()
+            .map(|(thread, end)| (thread, before.get(thread).copied().unwrap_or(0)..end))
+            .filter(|(_, cells)| !cells.is_empty())
+            .collect();
+        let lookup_cells = self.phase0_thread_lookups().iter().sum::<usize>() - lookups_before;
+        let keccak_capacity = self.keccak.lock().capacity() - keccak_before;
+        self.query_spans.push(QuerySpan { source, cells, lookup_cells, keccak_capacity });
+        result
+    }
+
+    /// Records that each of `sources` assigned all cells of one thread, starting from thread `first_thread`, and that
+    /// they used the keccak capacity added since it was `keccak_before` in equal shares.
+    #[cfg(feature = "providers")]
+    pub(crate) fn track_threads(
+        &mut self,
+        sources: Vec<QuerySource>,
+        first_thread: usize,
+        keccak_before: usize,
+    ) {
+        let thread_lens = self.phase0_thread_lens();
+        let thread_lookups = self.phase0_thread_lookups();
+        let keccak_capacity =
+            (self.keccak.lock().capacity() - keccak_before) / sources.len().max(1);
+        for (i, source) in sources.into_iter().enumerate() {
+            let thread = first_thread + i;
+            let cells = vec![(thread, 0..thread_lens[thread])];
+            let lookup_cells = thread_lookups[thread];
+            self.query_spans.push(QuerySpan { source, cells, lookup_cells, keccak_capacity });
+        }
+    }
+
+    /// Writes the values of all block headers, storage query results, and public instances assigned so far to `path`
+    /// as JSON, see [`EthBlock::extract`](crate::containers::EthBlock::extract) and [`StorageDigestData`]. This allows
+    /// debugging failed constraints against the actual chain data.
+    pub fn dump_witness(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let blocks: Vec<EthBlockData> = (0..self.header_witness().len())
+            .map(|index| self.header_block(index).extract())
+            .collect();
+        let storage: Vec<StorageDigestData> =
+            self.storage_digests.iter().map(StorageDigestData::from).collect();
+        let instances: Vec<_> = self.instances().iter().map(hex).collect();
+        let dump = json!({ "blocks": blocks, "storage": storage, "instances": instances });
+        serde_json::to_writer_pretty(File::create(path)?, &dump)?;
+        Ok(())
+    }
+}
+
+/// The big endian hex encoding of a field element.
+fn hex<F: Field>(value: &AssignedValue<F>) -> String {
+    format!("{:?}", value.value())
+}
+
+/// Describes which queries assigned the cells at the rows where `failures` happened.
+///
+/// Only the row of a failure is known, so every query with a cell in that row of a first phase column is reported.
+/// `thread_lens` and `break_points` are the first phase thread lengths and break points of the circuit.
+pub(crate) fn explain_failures(
+    spans: &[QuerySpan],
+    thread_lens: &[usize],
+    break_points: &[usize],
+    failures: &[VerifyFailure],
+) -> Vec<String> {
+    failures
+        .iter()
+        .filter_map(|failure| {
+            let row = failure_row(failure)?;
+            let sources: BTreeSet<_> = (0..=break_points.len())
+                .filter_map(|column| layout_index(break_points, column, row))
+                .filter_map(|index| thread_cell(thread_lens, index))
+                .flat_map(|(thread, cell)| {
+                    spans.iter().enumerate().filter(move |(_, span)| {
+                        span.cells.iter().any(|(t, cells)| *t == thread && cells.contains(&cell))
+                    })
+                })
+                .map(|(i, _)| i)
+                .collect();
+            (!sources.is_empty()).then(|| {
+                let sources: Vec<_> =
+                    sources.iter().map(|&i| spans[i].source.to_string()).collect();
+                format!("failure at row {row} inside {}", sources.join(" or "))
+            })
+        })
+        .collect()
+}
+
+fn failure_row(failure: &VerifyFailure) -> Option<usize> {
+    match failure {
+        VerifyFailure::ConstraintNotSatisfied { location, .. }
+        | VerifyFailure::Lookup { location, .. }
+        | VerifyFailure::Permutation { location, .. } => Some(match location {
+            FailureLocation::InRegion { offset, .. } => *offset,
+            FailureLocation::OutsideRegion { row } => *row,
+        }),
+        VerifyFailure::CellNotAssigned { offset, .. } => usize::try_from(*offset).ok(),
+        _ => None,
+    }
+}
+
+/// Returns the index, in layout order, of the cell at `row` of the `column`-th first phase advice column.
+///
+/// As in halo2-lib, a column ends with the cell at its break point row, which is then copied to the first row of the
+/// next column.
+fn layout_index(break_points: &[usize], column: usize, row: usize) -> Option<usize> {
+    let mut start = 0;
+    for (c, &break_point) in break_points.iter().enumerate() {
+        if c == column {
+            return (row <= break_point).then(|| (start + row).saturating_sub((c > 0) as usize));
+        }
+        start += break_point + 1 - (c > 0) as usize;
+    }
+    (start + row).checked_sub((column > 0) as usize)
+}
+
+/// Returns the thread and the index within the thread of the cell at `index` in layout order.
+fn thread_cell(thread_lens: &[usize], mut index: usize) -> Option<(usize, usize)> {
+    for (thread, &len) in thread_lens.iter().enumerate() {
+        if index < len {
+            return Some((thread, index));
+        }
+        index -= len;
+    }
+    None
+}
@@ -0,0 +1,301 @@
+//! Proofs that an ENS name resolved to an address at a block.
+//!
+//! The registry stores the resolver of each name, keyed by its namehash, and the resolver stores the address record. Both
+//! are proven with storage proofs at the same block, and the namehash and every slot are derived in the circuit.
+
+use axiom_eth::{storage::EIP1186ResponseDigest, util::encode_addr_to_field, Field};
+use ethers_core::{
+    types::{Address, H160, H256},
+    utils::keccak256,
+};
+use halo2_base::{
+    gates::{GateInstructions, RangeInstructions},
+    AssignedValue, Context,
+    QuantumCell::Constant,
+};
+#[cfg(feature = "providers")]
+use {
+    crate::{
+        error::Result,
+        slots::{mapping_slot_value, slot_offset_value},
+    },
+    ethers_core::types::U256,
+    ethers_providers::Middleware,
+    tokio::runtime::Runtime,
+};
+
+use crate::{
+    abi::{abi_encode, AbiValue},
+    containers::H256Assigned,
+    scaffold::AxiomChip,
+    slots::{constant_word, split_bits},
+};
+
+/// The ENS registry, at the same address on mainnet and Goerli.
+pub const ENS_REGISTRY: Address = H160([
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x2e, 0x07, 0x4e, 0xc6, 0x9a, 0x0d, 0xfb, 0x29, 0x97, 0xba,
+    0x6c, 0x7d, 0x2e, 0x1e,
+]);
+/// The slot of `mapping(bytes32 => Record) records` in the registry, where `Record` is `(owner, resolver, ttl)`.
+const REGISTRY_RECORDS_SLOT: u64 = 0;
+/// The coin type of Ethereum addresses in address records (SLIP-44), which `addr(node)` returns.
+const COIN_TYPE_ETH: u64 = 60;
+/// The last byte of a slot holding a 20 byte `bytes` value, which is twice its length.
+const ADDRESS_BYTES_LEN_BYTE: u64 = 2 * 20;
+
+/// Where a resolver stores its address records.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResolverLayout {
+    /// The slot of `mapping(bytes32 => uint64) recordVersions`, for resolvers whose records are versioned.
+    pub record_versions_slot: Option<u64>,
+    /// The slot of the address records, `mapping(uint64 => mapping(bytes32 => mapping(uint256 => bytes)))` keyed by
+    /// version, node and coin type if the records are versioned, and `mapping(bytes32 => mapping(uint256 => bytes))`
+    /// otherwise.
+    pub addresses_slot: u64,
+}
+
+impl ResolverLayout {
+    /// The layout of the ENS `PublicResolver`, whose records are versioned.
+    pub const PUBLIC_RESOLVER: Self = Self { record_versions_slot: Some(0), addresses_slot: 2 };
+}
+
+/// The resolution of an ENS name at a block, see [`AxiomChip::ens_resolve`].
+#[derive(Clone, Debug)]
+pub struct EnsResolution<F: Field> {
+    /// The namehash of the name, which is only as public as the name: expose it to show which name was resolved.
+    pub node: H256Assigned<F>,
+    pub block_number: AssignedValue<F>,
+    /// The block hash as hi-lo 128 bit halves.
+    pub block_hash: (AssignedValue<F>, AssignedValue<F>),
+    pub resolver: AssignedValue<F>,
+    /// The address the name resolved to, or zero if it has no address record.
+    pub address: AssignedValue<F>,
+}
+
+/// The namehash of `name`, which must already be normalized.
+pub fn namehash(name: &str) -> H256 {
+    if name.is_empty() {
+        return H256::zero();
+    }
+    name.rsplit('.')
+        .fold(H256::zero(), |node, label| H256(keccak256([node.0, keccak256(label)].concat())))
+}
+
+/// The resolver slot of the registry record of `node`.
+#[cfg(feature = "providers")]
+fn resolver_slot(node: H256) -> H256 {
+    let record = mapping_slot_value(node, H256::from_low_u64_be(REGISTRY_RECORDS_SLOT));
+    slot_offset_value(record, 1)
+}
+
+/// The slot of the Ethereum address record of `node`, with `version` if the records are versioned.
+#[cfg(feature = "providers")]
+fn address_slot(node: H256, layout: ResolverLayout, version: Option<u64>) -> H256 {
+    let addresses = H256::from_low_u64_be(layout.addresses_slot);
+    let records = match version {
+        Some(version) => mapping_slot_value(H256::from_low_u64_be(version), addresses),
+        None => addresses,
+    };
+    let node_records = mapping_slot_value(node, records);
+    mapping_slot_value(H256::from_low_u64_be(COIN_TYPE_ETH), node_records)
+}
+
+#[cfg(feature = "providers")]
+impl<F: Field> AxiomChip<F> {
+    /// Proves the address the ENS name `name` resolved to at block `block_number`, with the storage proofs of its
+    /// resolver in the registry and of its address record in the resolver, see [`AxiomChip::constrain_ens_resolution`].
+    ///
+    /// `layout` is the storage layout of the resolver of the name, usually [`ResolverLayout::PUBLIC_RESOLVER`].
+    pub fn ens_resolve<M: Middleware>(
+        &mut self,
+        provider: &M,
+        name: &str,
+        block_number: u32,
+        layout: ResolverLayout,
+    ) -> Result<EnsResolution<F>> {
+        Runtime::new()?.block_on(self.ens_resolve_async(provider, name, block_number, layout))
+    }
+
+    /// Same as [`AxiomChip::ens_resolve`], for use from within an async context.
+    pub async fn ens_resolve_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        name: &str,
+        block_number: u32,
+        layout: ResolverLayout,
+    ) -> Result<EnsResolution<F>> {
+        let node = namehash(name);
+        let registry = self
+            .eth_getProof_async(provider, ENS_REGISTRY, vec![resolver_slot(node)], block_number)
+            .await?;
+        let [hi, lo] = registry.slots_values[0].1.map(|x| x.value().get_lower_128());
+        let mut resolver = [0u8; 20];
+        resolver[..4].copy_from_slice(&(hi as u32).to_be_bytes());
+        resolver[4..].copy_from_slice(&lo.to_be_bytes());
+        let resolver = Address::from(resolver);
+
+        let mut slots = vec![];
+        let mut version = None;
+        if let Some(record_versions_slot) = layout.record_versions_slot {
+            let versions_slot =
+                mapping_slot_value(node, H256::from_low_u64_be(record_versions_slot));
+            let value = self
+                .fetch_storage_at(provider, block_number as u64, resolver, versions_slot)
+                .await?;
+            slots.push(versions_slot);
+            version = Some(U256::from_big_endian(&value.0).low_u64());
+        }
+        slots.push(address_slot(node, layout, version));
+        let records = self.eth_getProof_async(provider, resolver, slots, block_number).await?;
+        Ok(self.constrain_ens_resolution(name, &registry, &records, layout))
+    }
+}
+
+impl<F: Field> AxiomChip<F> {
+    /// The namehash of `name` in the circuit, where the bytes of `name` are private witnesses. `name` must already be
+    /// normalized. The circuit layout depends on the lengths of its labels.
+    pub fn ens_namehash(&self, name: &str) -> H256Assigned<F> {
+        let mut node = constant_word(&mut self.ctx(), self.gate(), H256::zero());
+        if name.is_empty() {
+            return node;
+        }
+        for label in name.rsplit('.') {
+            let label = self.ctx().assign_witnesses(label.bytes().map(|byte| F::from(byte as u64)));
+            let label_hash = self.keccak_word(label);
+            node = self.keccak_word(node.bytes.into_iter().chain(label_hash.bytes).collect());
+        }
+        node
+    }
+
+    /// Constrains `registry` to be the storage proof of the resolver of the ENS name `name` in the registry, and
+    /// `records` to be the storage proof of its address record in that resolver at the same block, whose layout is
+    /// `layout`. If the records are versioned, the first slot of `records` must be the version of the name's records
+    /// and the second its address record, otherwise the only slot is the address record.
+    ///
+    /// Address records are `bytes`, so the slot of a set record holds the 20 address bytes followed by the length byte.
+    pub fn constrain_ens_resolution(
+        &self,
+        name: &str,
+        registry: &EIP1186ResponseDigest<F>,
+        records: &EIP1186ResponseDigest<F>,
+        layout: ResolverLayout,
+    ) -> EnsResolution<F> {
+        let node = self.ens_namehash(name);
+        let word =
+            |value: u64| constant_word(&mut self.ctx(), self.gate(), H256::from_low_u64_be(value));
+
+        let record = self.mapping_slot(&node.bytes, &word(REGISTRY_RECORDS_SLOT));
+        self.constrain_slot(registry, 0, self.slot_offset(&record, 1));
+        let addresses = word(layout.addresses_slot);
+        let (addresses, index) = match layout.record_versions_slot {
+            Some(record_versions_slot) => {
+                let versions_slot = self.mapping_slot(&node.bytes, &word(record_versions_slot));
+                self.constrain_slot(records, 0, versions_slot.hi_lo());
+                let [_, version] = self.storage_value(records, 0);
+                let key = {
+                    let range = self.range();
+                    let mut ctx = self.ctx();
+                    let version = AbiValue::uint(&mut ctx, range, version);
+                    abi_encode(&mut ctx, range, &[version])
+                };
+                (self.mapping_slot(&key, &addresses), 1)
+            }
+            None => (addresses, 0),
+        };
+        let node_records = self.mapping_slot(&node.bytes, &addresses);
+        let address_slot = self.mapping_slot(&word(COIN_TYPE_ETH).bytes, &node_records);
+        self.constrain_slot(records, index, address_slot.hi_lo());
+
+        let record = self.storage_value(registry, 0);
+        let address_record = self.storage_value(records, index);
+        let range = self.range();
+        let gate = range.gate();
+        let mut ctx = self.ctx();
+        let registry_address = ctx.load_constant(encode_addr_to_field(&ENS_REGISTRY));
+        ctx.constrain_equal(&registry.address, &registry_address);
+        constrain_same_block(
+            &mut ctx,
+            (&registry.block_number, &registry.block_hash),
+            (&records.block_number, &records.block_hash),
+        );
+        // The resolver is the lowest 20 bytes of the record slot, below the ttl.
+        let [hi, lo] = record;
+        let (_, resolver_hi) = split_bits(&mut ctx, range, hi, 32, 128);
+        let resolver = gate.mul_add(&mut ctx, resolver_hi, Constant(gate.pow_of_two()[128]), lo);
+        ctx.constrain_equal(&records.address, &resolver);
+        // The slot is zero if the record is not set.
+        let [hi, lo] = address_record;
+        let (address_lo, len_byte) = split_bits(&mut ctx, range, lo, 96, 128);
+        let is_address =
+            gate.is_equal(&mut ctx, len_byte, Constant(F::from(ADDRESS_BYTES_LEN_BYTE)));
+        let is_unset = gate.is_zero(&mut ctx, len_byte);
+        let is_valid = gate.or(&mut ctx, is_address, is_unset);
+        gate.assert_is_const(&mut ctx, &is_valid, &F::one());
+        let address = gate.mul_add(&mut ctx, hi, Constant(gate.pow_of_two()[32]), address_lo);
+
+        EnsResolution {
+            node,
+            block_number: registry.block_number,
+            block_hash: registry.block_hash,
+            resolver,
+            address,
+        }
+    }
+}
+
+/// Constrains two storage proofs, given by their block numbers and hashes, to be at the same block. The hashes are
+/// compared as well as the numbers, since each proof is only checked against the header of its own block.
+fn constrain_same_block<F: Field>(
+    ctx: &mut Context<F>,
+    (number, hash): (&AssignedValue<F>, &(AssignedValue<F>, AssignedValue<F>)),
+    (other_number, other_hash): (&AssignedValue<F>, &(AssignedValue<F>, AssignedValue<F>)),
+) {
+    ctx.constrain_equal(number, other_number);
+    ctx.constrain_equal(&hash.0, &other_hash.0);
+    ctx.constrain_equal(&hash.1, &other_hash.1);
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::*;
+    use crate::{config::AxiomChipConfig, error::AxiomError};
+
+    fn mock_same_block(hash: [u64; 2], other_hash: [u64; 2]) -> crate::error::Result<()> {
+        let axiom = AxiomChip::<Fr>::with_config(AxiomChipConfig {
+            k: 10,
+            lookup_bits: 8,
+            unusable_rows: 109,
+            auto_degree: None,
+            keccak_rows_per_round: None,
+        });
+        {
+            let mut ctx = axiom.ctx();
+            let number = ctx.load_witness(Fr::from(17));
+            let [hash, other_hash] = [hash, other_hash]
+                .map(|[hi, lo]| (ctx.load_witness(Fr::from(hi)), ctx.load_witness(Fr::from(lo))));
+            constrain_same_block(&mut ctx, (&number, &hash), (&number, &other_hash));
+        }
+        axiom.mock()
+    }
+
+    #[test]
+    fn test_same_block() {
+        assert!(mock_same_block([1, 2], [1, 2]).is_ok());
+        // Proofs at the same number but different hashes, e.g. on different chains, must not be combined.
+        assert!(matches!(mock_same_block([1, 2], [1, 3]), Err(AxiomError::Constraints(_))));
+        assert!(matches!(mock_same_block([1, 2], [4, 2]), Err(AxiomError::Constraints(_))));
+    }
+
+    #[test]
+    fn test_namehash() {
+        assert_eq!(namehash(""), H256::zero());
+        let eth: H256 =
+            "0x93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae".parse().unwrap();
+        assert_eq!(namehash("eth"), eth);
+        let foo_eth: H256 =
+            "0xde9b09fd7c5f901e23a3f19fecc54828e9c848539801e86591bd9801b019f84f".parse().unwrap();
+        assert_eq!(namehash("foo.eth"), foo_eth);
+    }
+}
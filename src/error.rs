@@ -0,0 +1,119 @@
+use std::{fmt, io};
+
+use ethers_core::types::U256;
+#[cfg(feature = "providers")]
+use ethers_providers::ProviderError;
+use halo2_base::halo2_proofs::{dev::VerifyFailure, plonk};
+
+/// The ways building or proving an [`AxiomChip`](crate::scaffold::AxiomChip) circuit can fail.
+#[derive(Debug)]
+pub enum AxiomError {
+    /// A JSON-RPC request to the provider failed.
+    #[cfg(feature = "providers")]
+    Provider(ProviderError),
+    /// The provider does not know the requested block.
+    BlockNotFound(u64),
+    /// Offline inputs do not contain the data needed by a query.
+    MissingInput(String),
+    /// Pre-fetched inputs passed to a query are malformed, e.g. a block header that is not valid RLP.
+    InvalidInput(String),
+    /// The provider is for a chain other than Ethereum mainnet or Goerli.
+    UnsupportedNetwork(U256),
+    /// The circuit does not fit in 2<sup>max_k</sup> rows.
+    CapacityExceeded {
+        max_k: usize,
+    },
+    /// The keccak hashes of the circuit need more rows than there are in 2<sup>k</sup> rows.
+    KeccakCapacityExceeded {
+        needed_rows: usize,
+        k: usize,
+        available_rows: usize,
+    },
+    /// The mock prover found constraints that are not satisfied.
+    Constraints(Vec<VerifyFailure>),
+    /// Proving is estimated to need more memory than allowed by [`ProvingOptions::max_memory`](crate::proof::ProvingOptions::max_memory).
+    MemoryExceeded {
+        estimated: usize,
+        limit: usize,
+    },
+    /// A proof of an `eth_getProof` response has more nodes than the circuit allows, see
+    /// [`ProofDepths`](crate::scaffold::ProofDepths).
+    ProofTooDeep {
+        depth: usize,
+        max_depth: usize,
+    },
+    /// Key generation or proving failed.
+    Plonk(plonk::Error),
+    /// The public outputs of a circuit cannot be encoded in the requested format, see [`v2`](crate::v2).
+    InvalidOutput(String),
+    /// A proof was created but does not verify.
+    InvalidProof,
+    /// A snark to verify inside a circuit cannot be read or does not verify, see
+    /// [`AxiomChip::verify_snarks`](crate::scaffold::AxiomChip::verify_snarks).
+    InvalidSnark(String),
+    /// The job was stopped by a [`CancellationToken`](crate::cancel::CancellationToken).
+    Cancelled,
+    Io(io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, AxiomError>;
+
+impl fmt::Display for AxiomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "providers")]
+            Self::Provider(err) => write!(f, "provider error: {err}"),
+            Self::BlockNotFound(number) => write!(f, "block {number} not found"),
+            Self::MissingInput(msg) => write!(f, "missing input: {msg}"),
+            Self::InvalidInput(msg) => write!(f, "invalid input: {msg}"),
+            Self::UnsupportedNetwork(chain_id) => write!(f, "unsupported chain id {chain_id}"),
+            Self::CapacityExceeded { max_k } => {
+                write!(f, "circuit does not fit in 2^{max_k} rows")
+            }
+            Self::KeccakCapacityExceeded { needed_rows, k, available_rows } => {
+                write!(f, "need ~{needed_rows} keccak rows, capacity at k={k} is {available_rows}")
+            }
+            Self::Constraints(failures) => {
+                write!(f, "{} constraint failures", failures.len())?;
+                for failure in failures {
+                    write!(f, "\n{failure}")?;
+                }
+                Ok(())
+            }
+            Self::MemoryExceeded { estimated, limit } => write!(
+                f,
+                "proving is estimated to need {estimated} bytes of memory, more than the limit of {limit}"
+            ),
+            Self::ProofTooDeep { depth, max_depth } => {
+                write!(f, "proof has {depth} nodes, more than the max depth {max_depth}")
+            }
+            Self::Plonk(err) => write!(f, "halo2 error: {err}"),
+            Self::InvalidOutput(msg) => write!(f, "invalid output: {msg}"),
+            Self::InvalidProof => write!(f, "proof verification failed"),
+            Self::InvalidSnark(msg) => write!(f, "invalid snark: {msg}"),
+            Self::Cancelled => write!(f, "cancelled"),
+            Self::Io(err) => write!(f, "io error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for AxiomError {}
+
+#[cfg(feature = "providers")]
+impl From<ProviderError> for AxiomError {
+    fn from(err: ProviderError) -> Self {
+        Self::Provider(err)
+    }
+}
+
+impl From<plonk::Error> for AxiomError {
+    fn from(err: plonk::Error) -> Self {
+        Self::Plonk(err)
+    }
+}
+
+impl From<io::Error> for AxiomError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
@@ -0,0 +1,100 @@
+use std::path::Path;
+
+use halo2_base::halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::VerifyingKey,
+    poly::kzg::commitment::ParamsKZG,
+};
+use snark_verifier_sdk::{
+    evm::{gen_evm_verifier_gwc, gen_evm_verifier_shplonk},
+    halo2::aggregation::AggregationCircuit,
+};
+
+use crate::{
+    keys::KeyCircuit,
+    proof::{MultiOpenScheme, ProofArtifacts, TranscriptKind},
+    scaffold::AxiomChip,
+};
+
+impl AxiomChip<Fr> {
+    /// Generates a Yul verifier contract for this circuit and returns its EVM creation bytecode.
+    /// If `path` is provided, the Yul source is also written there.
+    ///
+    /// `vk` must be the verifying key of this circuit and `params` the KZG parameters it was generated with.
+    /// The verifier uses the multi-open scheme from the proving options of this chip and only accepts proofs
+    /// created with [`TranscriptKind::Keccak`]. If the circuit verifies snarks with [`AxiomChip::verify_snarks`], the
+    /// verifier also checks their accumulator. Compiling the contract requires `solc` to be installed.
+    pub fn gen_evm_verifier(
+        &self,
+        params: &ParamsKZG<Bn256>,
+        vk: &VerifyingKey<G1Affine>,
+        path: Option<&Path>,
+    ) -> Vec<u8> {
+        if self.proving_options().transcript != TranscriptKind::Keccak {
+            log::warn!("The EVM verifier only accepts proofs created with a Keccak transcript");
+        }
+        let num_instance = self.instances().len();
+        let multi_open = self.proving_options().multi_open;
+        gen_verifier(params, vk, num_instance, multi_open, self.has_accumulator, path)
+    }
+}
+
+/// Generates the verifier of a circuit with `num_instance` public instances, whose first 12 are a KZG accumulator if
+/// `has_accumulator`.
+fn gen_verifier(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    num_instance: usize,
+    multi_open: MultiOpenScheme,
+    has_accumulator: bool,
+    path: Option<&Path>,
+) -> Vec<u8> {
+    let num_instance = vec![num_instance];
+    // An accumulator takes the same instances as in an aggregation circuit, so the verifier must run its pairing check
+    // to accept only proofs whose accumulated snarks are valid.
+    match (multi_open, has_accumulator) {
+        (MultiOpenScheme::Shplonk, false) => {
+            gen_evm_verifier_shplonk::<KeyCircuit>(params, vk, num_instance, path)
+        }
+        (MultiOpenScheme::Shplonk, true) => {
+            gen_evm_verifier_shplonk::<AggregationCircuit>(params, vk, num_instance, path)
+        }
+        (MultiOpenScheme::Gwc, false) => {
+            gen_evm_verifier_gwc::<KeyCircuit>(params, vk, num_instance, path)
+        }
+        (MultiOpenScheme::Gwc, true) => {
+            gen_evm_verifier_gwc::<AggregationCircuit>(params, vk, num_instance, path)
+        }
+    }
+}
+
+/// Encodes a proof and its public instances as calldata for the verifier generated by [`AxiomChip::gen_evm_verifier`].
+///
+/// The calldata is each instance as a 32 byte big endian word, in order, followed by the raw proof bytes.
+/// The verifier only accepts proofs created with a Keccak transcript.
+pub fn encode_calldata(instances: &[Fr], proof: &[u8]) -> Vec<u8> {
+    snark_verifier_sdk::evm::encode_calldata(&[instances.to_vec()], proof)
+}
+
+impl ProofArtifacts {
+    /// Generates a Yul verifier contract for the circuit of this proof and returns its EVM creation bytecode, like
+    /// [`AxiomChip::gen_evm_verifier`], e.g. for an aggregate proof from
+    /// [`prove_aggregation`](crate::aggregation::prove_aggregation).
+    pub fn gen_evm_verifier(&self, params: &ParamsKZG<Bn256>, path: Option<&Path>) -> Vec<u8> {
+        if self.transcript != TranscriptKind::Keccak {
+            log::warn!("The EVM verifier only accepts proofs created with a Keccak transcript");
+        }
+        let num_instance = self.instances.len();
+        gen_verifier(params, &self.vk, num_instance, self.multi_open, self.has_accumulator, path)
+    }
+
+    /// See [`encode_calldata`].
+    pub fn calldata(&self) -> Vec<u8> {
+        assert_eq!(
+            self.transcript,
+            TranscriptKind::Keccak,
+            "EVM verifiers require a Keccak transcript"
+        );
+        encode_calldata(&self.instances, &self.proof)
+    }
+}
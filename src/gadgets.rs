@@ -0,0 +1,123 @@
+//! Gadgets that assign user inputs and reconstruct EVM encodings from assigned values.
+
+use axiom_eth::Field;
+use halo2_base::{
+    gates::{GateInstructions, RangeInstructions},
+    AssignedValue, Context,
+    QuantumCell::Constant,
+};
+
+use crate::{containers::ByteString, scaffold::AxiomChip};
+
+/// The number of bytes of an account nonce, which is at most 2<sup>64</sup> - 1 (EIP-2681).
+const NONCE_BYTES: usize = 8;
+/// The number of bytes of an address.
+const ADDRESS_BYTES: usize = 20;
+
+/// Decomposes `value`, which must fit in `num_bytes < 32` bytes, into its big endian bytes, each range checked.
+pub(crate) fn be_bytes<F: Field>(
+    ctx: &mut Context<F>,
+    range: &impl RangeInstructions<F>,
+    value: AssignedValue<F>,
+    num_bytes: usize,
+) -> Vec<AssignedValue<F>> {
+    let le_bytes = value.value().to_u64_limbs(num_bytes, 8);
+    let bytes = ctx.assign_witnesses(le_bytes.into_iter().rev().map(F::from));
+    for byte in &bytes {
+        range.range_check(ctx, *byte, 8);
+    }
+    let powers = (0..num_bytes).rev().map(|i| Constant(range.gate().pow_of_two()[8 * i]));
+    let recomposed = range.gate().inner_product(ctx, bytes.iter().copied(), powers);
+    ctx.constrain_equal(&recomposed, &value);
+    bytes
+}
+
+impl<F: Field> AxiomChip<F> {
+    /// Assigns the caller data `bytes`, e.g. a key or a preimage, as a private witness byte string padded to `max_len`
+    /// bytes. Constrains each byte to be a byte, the length to be at most `max_len`, and the padding to be zeros.
+    /// Panics if `bytes` is longer than `max_len`.
+    pub fn load_bytes(&self, bytes: &[u8], max_len: usize) -> ByteString<F> {
+        assert!(bytes.len() <= max_len, "{} bytes do not fit in {max_len} bytes", bytes.len());
+        let range = self.range();
+        let gate = range.gate();
+        let mut ctx = self.ctx();
+        let len = ctx.load_witness(F::from(bytes.len() as u64));
+        range.check_less_than_safe(&mut ctx, len, max_len as u64 + 1);
+        let padded =
+            bytes.iter().map(|byte| F::from(*byte as u64)).chain(std::iter::repeat(F::zero()));
+        let bytes = ctx.assign_witnesses(padded.take(max_len));
+        for byte in &bytes {
+            range.range_check(&mut ctx, *byte, 8);
+        }
+        let string = ByteString { len, bytes };
+        for (byte, masked) in string.bytes.iter().zip(string.masked_bytes(&mut ctx, gate)) {
+            ctx.constrain_equal(byte, &masked);
+        }
+        string
+    }
+
+    /// The address of the contract created with `CREATE` by `sender` when its nonce is `nonce`, which is the last 20
+    /// bytes of `keccak(rlp([sender, nonce]))`.
+    ///
+    /// With the nonce of an account from [`AxiomChip::eth_getAccount`], this proves which address the account's next
+    /// contract creation deploys to. Detecting that a transaction is a contract creation needs transaction proofs,
+    /// which are not supported yet.
+    pub fn create_address(
+        &self,
+        sender: AssignedValue<F>,
+        nonce: AssignedValue<F>,
+    ) -> AssignedValue<F> {
+        let range = self.range();
+        let gate = range.gate();
+        let mut ctx = self.ctx();
+        let sender_bytes = be_bytes(&mut ctx, range, sender, ADDRESS_BYTES);
+        let nonce_bytes = be_bytes(&mut ctx, range, nonce, NONCE_BYTES);
+
+        // the number of bytes of the nonce without leading zeros
+        let mut seen_nonzero = ctx.load_zero();
+        let mut nonce_len = ctx.load_zero();
+        for byte in &nonce_bytes {
+            let is_zero = gate.is_zero(&mut ctx, *byte);
+            let is_nonzero = gate.not(&mut ctx, is_zero);
+            seen_nonzero = gate.or(&mut ctx, seen_nonzero, is_nonzero);
+            nonce_len = gate.add(&mut ctx, nonce_len, seen_nonzero);
+        }
+        // A nonce in [1, 0x7f] is encoded as itself, any other as 0x80 + its length followed by its bytes, so zero is
+        // encoded as 0x80.
+        let last_byte = nonce_bytes[NONCE_BYTES - 1];
+        let is_one_byte = gate.is_equal(&mut ctx, nonce_len, Constant(F::one()));
+        let is_small = range.is_less_than(&mut ctx, last_byte, Constant(F::from(0x80)), 8);
+        let is_single = gate.and(&mut ctx, is_one_byte, is_small);
+        let len_prefix = gate.add(&mut ctx, nonce_len, Constant(F::from(0x80)));
+        let nonce_first = gate.select(&mut ctx, last_byte, len_prefix, is_single);
+        let not_single = gate.not(&mut ctx, is_single);
+        let nonce_rlp_len = gate.mul_add(&mut ctx, not_single, nonce_len, Constant(F::one()));
+        let nonce_rest: Vec<_> = (0..NONCE_BYTES)
+            .map(|i| {
+                let idx =
+                    gate.sub(&mut ctx, Constant(F::from((NONCE_BYTES + i) as u64)), nonce_len);
+                gate.select_from_idx(&mut ctx, nonce_bytes.iter().copied(), idx)
+            })
+            .collect();
+
+        // The payload is at most 30 bytes, so the list prefix is a single byte.
+        let payload_len =
+            gate.add(&mut ctx, nonce_rlp_len, Constant(F::from(1 + ADDRESS_BYTES as u64)));
+        let list_prefix = gate.add(&mut ctx, payload_len, Constant(F::from(0xc0)));
+        let sender_prefix = ctx.load_constant(F::from(0x80 + ADDRESS_BYTES as u64));
+        let rlp_len = gate.add(&mut ctx, payload_len, Constant(F::one()));
+        let rlp: Vec<_> = [list_prefix, sender_prefix]
+            .into_iter()
+            .chain(sender_bytes)
+            .chain([nonce_first])
+            .chain(nonce_rest)
+            .collect();
+        let rlp_bytes = rlp.iter().map(|byte| byte.value().get_lower_32() as u8).collect();
+
+        let mut keccak = self.keccak.lock();
+        let index = keccak.keccak_var_len(&mut ctx, range, rlp, Some(rlp_bytes), rlp_len, 23);
+        let hash = &keccak.var_len_queries[index].output_assigned;
+        let powers = (0..ADDRESS_BYTES).rev().map(|i| Constant(gate.pow_of_two()[8 * i]));
+        gate.inner_product(&mut ctx, hash[32 - ADDRESS_BYTES..].iter().copied(), powers)
+    }
+}
@@ -0,0 +1,220 @@
+//! Proofs of the voting power recorded in OpenZeppelin-style `Checkpoints` at a past block.
+//!
+//! A checkpoint array is sorted by the block its checkpoint took effect, so the checkpoint in effect at a target block is
+//! the last one at or before it. Its neighbor is proven as well, to show the next checkpoint, if any, is after the target.
+
+use axiom_eth::{storage::EIP1186ResponseDigest, Field};
+use halo2_base::{
+    gates::{GateInstructions, RangeInstructions},
+    AssignedValue,
+    QuantumCell::Constant,
+};
+#[cfg(feature = "providers")]
+use {
+    crate::{
+        error::{AxiomError, Result},
+        slots::{address_mapping_slot_value, slot_offset_value},
+    },
+    axiom_eth::util::encode_addr_to_field,
+    ethers_core::{
+        types::{Address, H256, U256},
+        utils::keccak256,
+    },
+    ethers_providers::Middleware,
+    tokio::runtime::Runtime,
+};
+
+use crate::{scaffold::AxiomChip, slots::packed_field};
+
+/// Where a contract stores a checkpoint array and how its checkpoints are packed: each checkpoint is one slot holding
+/// the block number in its lowest `key_bits` bits and the value in the bits above.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CheckpointsLayout {
+    /// The slot of the array, or of the mapping from account to array.
+    pub slot: u64,
+    pub key_bits: usize,
+}
+
+impl CheckpointsLayout {
+    /// The `Checkpoint { uint32 fromBlock; uint224 votes; }` arrays of OpenZeppelin 4 `ERC20Votes`, at `slot`.
+    pub const fn erc20_votes(slot: u64) -> Self {
+        Self { slot, key_bits: 32 }
+    }
+
+    /// The `Checkpoints.Trace208` of OpenZeppelin 5 `Votes`, whose checkpoints are `(uint48 key, uint208 value)`, at
+    /// `slot`. The key is a block number with the default clock.
+    pub const fn trace208(slot: u64) -> Self {
+        Self { slot, key_bits: 48 }
+    }
+}
+
+/// The checkpoint in effect at a target block, see [`AxiomChip::constrain_checkpoint`].
+#[derive(Clone, Debug)]
+pub struct Checkpoint<F: Field> {
+    pub contract: AssignedValue<F>,
+    /// The block of the storage proof, at or after the target block.
+    pub block_number: AssignedValue<F>,
+    /// The block hash as hi-lo 128 bit halves.
+    pub block_hash: (AssignedValue<F>, AssignedValue<F>),
+    /// The account the checkpoints are keyed by, if the layout slot is a mapping.
+    pub account: Option<AssignedValue<F>>,
+    pub target_block: AssignedValue<F>,
+    /// The index of the checkpoint in the array.
+    pub index: AssignedValue<F>,
+    /// The block the checkpoint took effect.
+    pub from_block: AssignedValue<F>,
+    /// The value, e.g. the votes, as hi-lo 128 bit halves.
+    pub value: [AssignedValue<F>; 2],
+}
+
+#[cfg(feature = "providers")]
+impl<F: Field> AxiomChip<F> {
+    /// Proves the checkpoint in effect at `target_block` of the checkpoint array of `contract` described by `layout`,
+    /// the entry for `account` if the array is in a mapping, with a storage proof at block `block_number`.
+    ///
+    /// The checkpoint is found by binary search with the provider, then the array length, the checkpoint and the one
+    /// after it are proven with a single storage proof, see [`AxiomChip::constrain_checkpoint`]. Fails if there is no
+    /// checkpoint at or before `target_block`.
+    pub fn checkpoint_at<M: Middleware>(
+        &mut self,
+        provider: &M,
+        contract: Address,
+        account: Option<Address>,
+        target_block: u32,
+        block_number: u32,
+        layout: CheckpointsLayout,
+    ) -> Result<Checkpoint<F>> {
+        Runtime::new()?.block_on(self.checkpoint_at_async(
+            provider,
+            contract,
+            account,
+            target_block,
+            block_number,
+            layout,
+        ))
+    }
+
+    /// Same as [`AxiomChip::checkpoint_at`], for use from within an async context.
+    pub async fn checkpoint_at_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        contract: Address,
+        account: Option<Address>,
+        target_block: u32,
+        block_number: u32,
+        layout: CheckpointsLayout,
+    ) -> Result<Checkpoint<F>> {
+        let array_slot = address_mapping_slot_value(
+            H256::from_low_u64_be(layout.slot),
+            &Vec::from_iter(account),
+        );
+        let data_slot = H256(keccak256(array_slot.0));
+        let block = block_number as u64;
+        let len = self.fetch_storage_at(provider, block, contract, array_slot).await?;
+        let len = U256::from_big_endian(&len.0).low_u64();
+
+        // the number of checkpoints at or before the target block
+        let (mut lo, mut hi) = (0, len);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            let slot = slot_offset_value(data_slot, mid);
+            let checkpoint = self.fetch_storage_at(provider, block, contract, slot).await?;
+            let from_block =
+                U256::from_big_endian(&checkpoint.0).low_u64() & ((1u64 << layout.key_bits) - 1);
+            if from_block <= target_block as u64 {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        if lo == 0 {
+            return Err(AxiomError::MissingInput(format!(
+                "no checkpoint at or before block {target_block}"
+            )));
+        }
+        let index = lo - 1;
+
+        let slots = vec![
+            array_slot,
+            slot_offset_value(data_slot, index),
+            slot_offset_value(data_slot, index + 1),
+        ];
+        let digest = self.eth_getProof_async(provider, contract, slots, block_number).await?;
+        let (account, target_block, index) = {
+            let mut ctx = self.ctx();
+            (
+                account.map(|account| ctx.load_witness(encode_addr_to_field(&account))),
+                ctx.load_witness(F::from(target_block as u64)),
+                ctx.load_witness(F::from(index)),
+            )
+        };
+        Ok(self.constrain_checkpoint(&digest, account, target_block, index, layout))
+    }
+}
+
+impl<F: Field> AxiomChip<F> {
+    /// Constrains `digest` to be the storage proof of the length of the checkpoint array described by `layout`, of its
+    /// checkpoint at `index` and of the slot after it, in this order, and that checkpoint to be the one in effect at
+    /// `target_block`: it took effect at or before `target_block`, and it is either the last checkpoint or the next
+    /// one took effect after `target_block`. `target_block` must be at most the block of the proof, after which the
+    /// checkpoints before it can no longer change.
+    ///
+    /// `account` is the key of the mapping at the layout slot, if the arrays are in a mapping.
+    pub fn constrain_checkpoint(
+        &self,
+        digest: &EIP1186ResponseDigest<F>,
+        account: Option<AssignedValue<F>>,
+        target_block: AssignedValue<F>,
+        index: AssignedValue<F>,
+        layout: CheckpointsLayout,
+    ) -> Checkpoint<F> {
+        assert_eq!(digest.slots_values.len(), 3, "a checkpoint is proven with three slots");
+        assert!(layout.key_bits < 128);
+        let array_slot =
+            self.address_mapping_slot(self.constant_slot(layout.slot), &Vec::from_iter(account));
+        self.constrain_slot(digest, 0, array_slot.hi_lo());
+        let data_slot = self.keccak_word(array_slot.bytes.to_vec());
+        self.constrain_slot(digest, 1, self.slot_index(&data_slot, index));
+        let next = self.gate().add(&mut self.ctx(), index, Constant(F::one()));
+        self.constrain_slot(digest, 2, self.slot_index(&data_slot, next));
+
+        let [[len_hi, len], checkpoint, next_checkpoint] =
+            [0, 1, 2].map(|i| self.storage_value(digest, i));
+
+        let range = self.range();
+        let gate = range.gate();
+        let mut ctx = self.ctx();
+        gate.assert_is_const(&mut ctx, &len_hi, &F::zero());
+        range.range_check(&mut ctx, len, 64);
+        range.check_less_than(&mut ctx, index, len, 64);
+
+        let key_bits = layout.key_bits;
+        let from_block = packed_field(&mut ctx, range, checkpoint, 0, key_bits);
+        let value_lo = packed_field(&mut ctx, range, checkpoint, key_bits, 128);
+        let value_hi = packed_field(&mut ctx, range, checkpoint, key_bits + 128, 128 - key_bits);
+        range.range_check(&mut ctx, target_block, key_bits);
+        // from_block <= target_block
+        let target_end = gate.add(&mut ctx, target_block, Constant(F::one()));
+        range.check_less_than(&mut ctx, from_block, target_end, key_bits + 1);
+        // the checkpoint is the last one, or the next one took effect after target_block
+        let next_from_block = packed_field(&mut ctx, range, next_checkpoint, 0, key_bits);
+        let is_last = gate.is_equal(&mut ctx, next, len);
+        let next_is_after = range.is_less_than(&mut ctx, target_block, next_from_block, key_bits);
+        let is_in_effect = gate.or(&mut ctx, is_last, next_is_after);
+        gate.assert_is_const(&mut ctx, &is_in_effect, &F::one());
+        // target_block <= block_number
+        let block_end = gate.add(&mut ctx, digest.block_number, Constant(F::one()));
+        range.check_less_than(&mut ctx, target_block, block_end, 64);
+
+        Checkpoint {
+            contract: digest.address,
+            block_number: digest.block_number,
+            block_hash: digest.block_hash,
+            account,
+            target_block,
+            index,
+            from_block,
+            value: [value_hi, value_lo],
+        }
+    }
+}
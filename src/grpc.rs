@@ -0,0 +1,87 @@
+//! A gRPC front end of [`ProverServer`], enabled by the `grpc` feature. See `proto/prover.proto` for the service.
+
+use std::{io, net::SocketAddr, pin::Pin};
+
+use futures::{stream, Stream};
+use tonic::{transport::Server, Request, Response, Status};
+
+use crate::{server::JobStatus, server::ProverServer, spec::QuerySpec};
+
+#[allow(clippy::all)]
+mod proto {
+    tonic::include_proto!("axiom.prover");
+}
+
+use proto::{
+    prover_server::{Prover, ProverServer as ProverService},
+    JobUpdate, Stage, SubmitRequest, SubmitResponse, WatchJobRequest,
+};
+
+impl From<JobStatus> for JobUpdate {
+    fn from(status: JobStatus) -> Self {
+        let stage = match &status {
+            JobStatus::WitnessGen => Stage::WitnessGen,
+            JobStatus::Keygen => Stage::Keygen,
+            JobStatus::Proving => Stage::Proving,
+            JobStatus::Done { .. } => Stage::Done,
+            JobStatus::Failed { .. } => Stage::Failed,
+        };
+        let mut update = JobUpdate { stage: stage as i32, ..Default::default() };
+        match status {
+            JobStatus::Done { proof, instances } => {
+                update.proof = proof;
+                update.instances = instances.iter().map(|word| word.to_vec()).collect();
+            }
+            JobStatus::Failed { error } => update.error = error,
+            _ => {}
+        }
+        update
+    }
+}
+
+#[tonic::async_trait]
+impl Prover for ProverServer {
+    type WatchJobStream = Pin<Box<dyn Stream<Item = Result<JobUpdate, Status>> + Send>>;
+
+    async fn submit(
+        &self,
+        request: Request<SubmitRequest>,
+    ) -> Result<Response<SubmitResponse>, Status> {
+        let spec: QuerySpec = serde_json::from_str(&request.into_inner().spec_json)
+            .map_err(|err| Status::invalid_argument(format!("invalid query spec: {err}")))?;
+        let id = ProverServer::submit(self, spec)
+            .map_err(|err| Status::invalid_argument(format!("invalid query spec: {err}")))?;
+        Ok(Response::new(SubmitResponse { id }))
+    }
+
+    async fn watch_job(
+        &self,
+        request: Request<WatchJobRequest>,
+    ) -> Result<Response<Self::WatchJobStream>, Status> {
+        let id = request.into_inner().id;
+        let rx = self.job(id).ok_or_else(|| Status::not_found(format!("no job {id}")))?;
+        // Yield the current status, then each change, ending after the job has finished.
+        let updates = stream::unfold(Some((rx, true)), |state| async move {
+            let (mut rx, first) = state?;
+            if !first && rx.changed().await.is_err() {
+                return None;
+            }
+            let status = rx.borrow_and_update().clone();
+            let next = if status.is_finished() { None } else { Some((rx, false)) };
+            Some((Ok(JobUpdate::from(status)), next))
+        });
+        Ok(Response::new(Box::pin(updates)))
+    }
+}
+
+impl ProverServer {
+    /// Serves the gRPC API on `addr` until the server fails.
+    pub async fn serve_grpc(self, addr: SocketAddr) -> io::Result<()> {
+        log::info!("Serving gRPC proof jobs on {addr}");
+        Server::builder()
+            .add_service(ProverService::new(self))
+            .serve(addr)
+            .await
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+    }
+}
@@ -0,0 +1,108 @@
+use std::{
+    env::set_var,
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use axiom_eth::{
+    rlp::{
+        builder::{RlcThreadBreakPoints, RlcThreadBuilder},
+        RlpChip,
+    },
+    EthCircuitBuilder, EthConfigParams,
+};
+use halo2_base::halo2_proofs::{
+    halo2curves::bn256::{Fr, G1Affine},
+    plonk::{ProvingKey, VerifyingKey},
+    SerdeFormat,
+};
+use parking_lot::{const_mutex, Mutex, MutexGuard};
+use serde::{Deserialize, Serialize};
+
+use crate::scaffold::KeccakRlcs;
+
+/// A nameable stand-in for the circuit type returned by [`AxiomChip::create`](crate::scaffold::AxiomChip::create).
+///
+/// Deserializing keys and generating verifiers only needs the circuit's `configure`, which does not depend on the
+/// phase 1 closure, so any closure type works here.
+pub(crate) type KeyCircuit =
+    EthCircuitBuilder<Fr, fn(&mut RlcThreadBuilder<Fr>, RlpChip<Fr>, KeccakRlcs<Fr>)>;
+
+/// Everything chosen at keygen time that determines the circuit layout: the column configuration and the break points
+/// between columns. A prover must build its circuit from the same pinning as the keys it proves with.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AxiomPinning {
+    pub params: EthConfigParams,
+    pub break_points: RlcThreadBreakPoints,
+}
+
+impl AxiomPinning {
+    /// Reads a pinning from a JSON file.
+    pub fn from_path(path: impl AsRef<Path>) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Writes the pinning to a JSON file.
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()
+    }
+}
+
+static CONFIG_PARAMS: Mutex<()> = const_mutex(());
+
+/// The circuit configuration is read from the `ETH_CONFIG_PARAMS` environment variable when the
+/// circuit is configured, which happens when a key is deserialized. This sets it to `config`.
+///
+/// The caller must hold [`lock_config_params`].
+pub(crate) fn set_config_params(config: &EthConfigParams) {
+    set_var("ETH_CONFIG_PARAMS", serde_json::to_string(config).unwrap());
+}
+
+/// Locks the process-global circuit configuration. Circuits are configured from environment variables during keygen,
+/// proving and key deserialization, so everything from creating a circuit until it is no longer configured must hold
+/// this lock, or circuits on other threads could be configured with the wrong parameters.
+pub fn lock_config_params() -> MutexGuard<'static, ()> {
+    CONFIG_PARAMS.lock()
+}
+
+pub fn write_pk(pk: &ProvingKey<G1Affine>, path: impl AsRef<Path>) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    pk.write(&mut writer, SerdeFormat::RawBytes)?;
+    writer.flush()
+}
+
+/// Reads a proving key written by [`write_pk`].
+///
+/// `config` must be the circuit configuration the key was generated with, i.e., the `params` of its [`AxiomPinning`].
+pub fn read_pk(
+    path: impl AsRef<Path>,
+    config: &EthConfigParams,
+) -> io::Result<ProvingKey<G1Affine>> {
+    let _config_lock = lock_config_params();
+    set_config_params(config);
+    let mut reader = BufReader::new(File::open(path)?);
+    ProvingKey::read::<_, KeyCircuit>(&mut reader, SerdeFormat::RawBytes)
+}
+
+pub fn write_vk(vk: &VerifyingKey<G1Affine>, path: impl AsRef<Path>) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    vk.write(&mut writer, SerdeFormat::RawBytes)?;
+    writer.flush()
+}
+
+/// Reads a verifying key written by [`write_vk`].
+///
+/// `config` must be the circuit configuration the key was generated with, i.e., the `params` of its [`AxiomPinning`].
+pub fn read_vk(
+    path: impl AsRef<Path>,
+    config: &EthConfigParams,
+) -> io::Result<VerifyingKey<G1Affine>> {
+    let _config_lock = lock_config_params();
+    set_config_params(config);
+    let mut reader = BufReader::new(File::open(path)?);
+    VerifyingKey::read::<_, KeyCircuit>(&mut reader, SerdeFormat::RawBytes)
+}
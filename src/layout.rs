@@ -0,0 +1,45 @@
+use std::path::Path;
+
+use axiom_eth::Field;
+use halo2_base::halo2_proofs::{dev::CircuitLayout, plonk::Circuit};
+use plotters::{
+    coord::Shift,
+    prelude::{BitMapBackend, DrawingArea, DrawingBackend, IntoDrawingArea, SVGBackend, WHITE},
+};
+
+use crate::{keys::lock_config_params, scaffold::AxiomChip};
+
+/// The size in pixels of a rendered layout.
+const LAYOUT_SIZE: (u32, u32) = (1024, 3096);
+
+impl<F: Field> AxiomChip<F> {
+    /// Creates a circuit and renders its layout to `path`: the columns of the configured circuit, with the cells used
+    /// by the queries and the keccak and RLC computations highlighted. This shows where the rows are going when tuning `DEGREE`.
+    ///
+    /// The image is an SVG if `path` has an `svg` extension, and a PNG otherwise.
+    pub fn render_layout(mut self, path: impl AsRef<Path>) {
+        self.resolve_degree().expect("failed to select circuit degree");
+        let _config_lock = lock_config_params();
+        let k = self.config().k as u32;
+        let path = path.as_ref();
+        let circuit = self.create(None);
+        let title = format!("AxiomChip layout, k = {k}");
+        if path.extension().map_or(false, |ext| ext == "svg") {
+            render(SVGBackend::new(path, LAYOUT_SIZE).into_drawing_area(), &title, k, &circuit);
+        } else {
+            render(BitMapBackend::new(path, LAYOUT_SIZE).into_drawing_area(), &title, k, &circuit);
+        }
+    }
+}
+
+fn render<F: Field, DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    title: &str,
+    k: u32,
+    circuit: &impl Circuit<F>,
+) {
+    root.fill(&WHITE).unwrap();
+    let root = root.titled(title, ("sans-serif", 60)).unwrap();
+    CircuitLayout::default().render(k, circuit, &root).unwrap();
+    root.present().unwrap();
+}
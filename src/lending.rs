@@ -0,0 +1,309 @@
+//! Proofs of positions in lending protocols at a block, read from the storage of Aave v3 and Compound v3 (Comet).
+//!
+//! Both protocols pack several fields into a slot, which are decoded in the circuit with
+//! [`packed_field`](crate::slots::packed_field). The amounts are the raw stored values: scaled or principal balances
+//! together with the indexes that convert them to present balances, so the conversion can be done in the circuit that
+//! needs it.
+
+use axiom_eth::{storage::EIP1186ResponseDigest, Field};
+use halo2_base::{
+    gates::{GateInstructions, RangeInstructions},
+    AssignedValue,
+    QuantumCell::Constant,
+};
+#[cfg(feature = "providers")]
+use {
+    crate::{
+        error::Result,
+        slots::{address_mapping_slot_value, slot_offset_value},
+    },
+    axiom_eth::util::encode_addr_to_field,
+    ethers_core::types::{Address, H256},
+    ethers_providers::Middleware,
+    tokio::runtime::Runtime,
+};
+
+use crate::{
+    scaffold::AxiomChip,
+    slots::{packed_field, split_bits},
+};
+
+/// The slot of `mapping(address => UserState) _userState` in Aave v3 aTokens and debt tokens, after the 52 slots of
+/// `VersionedInitializable`.
+pub const AAVE_V3_USER_STATE_SLOT: u64 = 52;
+/// The slot of `mapping(address => ReserveData) _reserves` in the Aave v3 `Pool`, after the 52 slots of
+/// `VersionedInitializable`.
+pub const AAVE_V3_RESERVES_SLOT: u64 = 52;
+/// The slot of the packed `baseSupplyIndex`, `baseBorrowIndex` and tracking indexes in Compound v3 `Comet`.
+pub const COMPOUND_V3_INDEXES_SLOT: u64 = 0;
+/// The slot of `mapping(address => UserBasic) userBasic` in Compound v3 `Comet`.
+pub const COMPOUND_V3_USER_BASIC_SLOT: u64 = 5;
+/// The slot of `mapping(address => mapping(address => UserCollateral)) userCollateral` in Compound v3 `Comet`.
+pub const COMPOUND_V3_USER_COLLATERAL_SLOT: u64 = 6;
+
+/// The number of bits of the signed `principal` of a Compound v3 `UserBasic`.
+const PRINCIPAL_BITS: usize = 104;
+
+/// The balance of a user in an Aave v3 aToken or debt token, see [`AxiomChip::aave_v3_user_state`].
+#[derive(Clone, Debug)]
+pub struct AaveV3UserState<F: Field> {
+    pub token: AssignedValue<F>,
+    pub user: AssignedValue<F>,
+    pub block_number: AssignedValue<F>,
+    /// The block hash as hi-lo 128 bit halves.
+    pub block_hash: (AssignedValue<F>, AssignedValue<F>),
+    /// The balance divided by the reserve index at each of its updates, in units of the underlying asset.
+    pub scaled_balance: AssignedValue<F>,
+    /// The reserve index at the last update of the balance (`additionalData`).
+    pub index: AssignedValue<F>,
+}
+
+/// The indexes of an Aave v3 reserve, in ray (10<sup>27</sup>) fixed point, see
+/// [`AxiomChip::aave_v3_reserve_indexes`].
+#[derive(Clone, Debug)]
+pub struct AaveV3ReserveIndexes<F: Field> {
+    pub pool: AssignedValue<F>,
+    pub asset: AssignedValue<F>,
+    pub block_number: AssignedValue<F>,
+    /// The block hash as hi-lo 128 bit halves.
+    pub block_hash: (AssignedValue<F>, AssignedValue<F>),
+    /// The index that converts scaled aToken balances to balances, as of the last update of the reserve.
+    pub liquidity_index: AssignedValue<F>,
+    /// The index that converts scaled variable debt to debt, as of the last update of the reserve.
+    pub variable_borrow_index: AssignedValue<F>,
+}
+
+/// The position of a user in a Compound v3 market, see [`AxiomChip::compound_v3_position`].
+#[derive(Clone, Debug)]
+pub struct CompoundV3Position<F: Field> {
+    pub comet: AssignedValue<F>,
+    pub user: AssignedValue<F>,
+    pub block_number: AssignedValue<F>,
+    /// The block hash as hi-lo 128 bit halves.
+    pub block_hash: (AssignedValue<F>, AssignedValue<F>),
+    /// The indexes that convert principals to present balances, scaled by 10<sup>15</sup>.
+    pub base_supply_index: AssignedValue<F>,
+    pub base_borrow_index: AssignedValue<F>,
+    /// Whether the principal is negative, i.e. the user borrows the base asset.
+    pub is_borrow: AssignedValue<F>,
+    /// The absolute value of the principal in the base asset.
+    pub principal: AssignedValue<F>,
+    /// The collateral assets and the balance of the user in each.
+    pub collateral: Vec<(AssignedValue<F>, AssignedValue<F>)>,
+}
+
+#[cfg(feature = "providers")]
+impl<F: Field> AxiomChip<F> {
+    /// Proves the scaled balance of `user` in the Aave v3 aToken or debt token at `token` at block `block_number`.
+    pub fn aave_v3_user_state<M: Middleware>(
+        &mut self,
+        provider: &M,
+        token: Address,
+        user: Address,
+        block_number: u32,
+    ) -> Result<AaveV3UserState<F>> {
+        Runtime::new()?.block_on(self.aave_v3_user_state_async(provider, token, user, block_number))
+    }
+
+    /// Same as [`AxiomChip::aave_v3_user_state`], for use from within an async context.
+    pub async fn aave_v3_user_state_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        token: Address,
+        user: Address,
+        block_number: u32,
+    ) -> Result<AaveV3UserState<F>> {
+        let slot =
+            address_mapping_slot_value(H256::from_low_u64_be(AAVE_V3_USER_STATE_SLOT), &[user]);
+        let digest = self.eth_getProof_async(provider, token, vec![slot], block_number).await?;
+        let user = self.ctx().load_witness(encode_addr_to_field(&user));
+        Ok(self.constrain_aave_v3_user_state(&digest, user))
+    }
+
+    /// Proves the liquidity and variable borrow indexes of the reserve of `asset` in the Aave v3 `Pool` at `pool` at
+    /// block `block_number`.
+    pub fn aave_v3_reserve_indexes<M: Middleware>(
+        &mut self,
+        provider: &M,
+        pool: Address,
+        asset: Address,
+        block_number: u32,
+    ) -> Result<AaveV3ReserveIndexes<F>> {
+        Runtime::new()?.block_on(self.aave_v3_reserve_indexes_async(
+            provider,
+            pool,
+            asset,
+            block_number,
+        ))
+    }
+
+    /// Same as [`AxiomChip::aave_v3_reserve_indexes`], for use from within an async context.
+    pub async fn aave_v3_reserve_indexes_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        pool: Address,
+        asset: Address,
+        block_number: u32,
+    ) -> Result<AaveV3ReserveIndexes<F>> {
+        let reserve =
+            address_mapping_slot_value(H256::from_low_u64_be(AAVE_V3_RESERVES_SLOT), &[asset]);
+        let slots = vec![slot_offset_value(reserve, 1), slot_offset_value(reserve, 2)];
+        let digest = self.eth_getProof_async(provider, pool, slots, block_number).await?;
+        let asset = self.ctx().load_witness(encode_addr_to_field(&asset));
+        Ok(self.constrain_aave_v3_reserve_indexes(&digest, asset))
+    }
+
+    /// Proves the base asset principal of `user` and its balance of each of `collateral_assets` in the Compound v3
+    /// market at `comet` at block `block_number`, together with the base indexes of the market.
+    pub fn compound_v3_position<M: Middleware>(
+        &mut self,
+        provider: &M,
+        comet: Address,
+        user: Address,
+        collateral_assets: &[Address],
+        block_number: u32,
+    ) -> Result<CompoundV3Position<F>> {
+        Runtime::new()?.block_on(self.compound_v3_position_async(
+            provider,
+            comet,
+            user,
+            collateral_assets,
+            block_number,
+        ))
+    }
+
+    /// Same as [`AxiomChip::compound_v3_position`], for use from within an async context.
+    pub async fn compound_v3_position_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        comet: Address,
+        user: Address,
+        collateral_assets: &[Address],
+        block_number: u32,
+    ) -> Result<CompoundV3Position<F>> {
+        let mut slots = vec![
+            H256::from_low_u64_be(COMPOUND_V3_INDEXES_SLOT),
+            address_mapping_slot_value(H256::from_low_u64_be(COMPOUND_V3_USER_BASIC_SLOT), &[user]),
+        ];
+        let user_collateral = H256::from_low_u64_be(COMPOUND_V3_USER_COLLATERAL_SLOT);
+        slots.extend(
+            collateral_assets
+                .iter()
+                .map(|asset| address_mapping_slot_value(user_collateral, &[user, *asset])),
+        );
+        let digest = self.eth_getProof_async(provider, comet, slots, block_number).await?;
+        let (user, assets) = {
+            let mut ctx = self.ctx();
+            let user = ctx.load_witness(encode_addr_to_field(&user));
+            let assets = collateral_assets
+                .iter()
+                .map(|asset| ctx.load_witness(encode_addr_to_field(asset)))
+                .collect();
+            (user, assets)
+        };
+        Ok(self.constrain_compound_v3_position(&digest, user, assets))
+    }
+}
+
+impl<F: Field> AxiomChip<F> {
+    /// Constrains `digest` to be the storage proof of the `UserState` of `user` in an Aave v3 aToken or debt token,
+    /// which packs the `uint128` scaled balance with the `uint128` index of its last update.
+    pub fn constrain_aave_v3_user_state(
+        &self,
+        digest: &EIP1186ResponseDigest<F>,
+        user: AssignedValue<F>,
+    ) -> AaveV3UserState<F> {
+        let slot = self.constant_slot(AAVE_V3_USER_STATE_SLOT);
+        let user_state = self.address_mapping_slot(slot, &[user]);
+        self.constrain_slot(digest, 0, user_state.hi_lo());
+        let [index, scaled_balance] = self.storage_value(digest, 0);
+        AaveV3UserState {
+            token: digest.address,
+            user,
+            block_number: digest.block_number,
+            block_hash: digest.block_hash,
+            scaled_balance,
+            index,
+        }
+    }
+
+    /// Constrains `digest` to be the storage proofs of the second and third slots of the `ReserveData` of `asset` in an
+    /// Aave v3 `Pool`, which pack the liquidity index and the variable borrow index below their current rates.
+    pub fn constrain_aave_v3_reserve_indexes(
+        &self,
+        digest: &EIP1186ResponseDigest<F>,
+        asset: AssignedValue<F>,
+    ) -> AaveV3ReserveIndexes<F> {
+        let slot = self.constant_slot(AAVE_V3_RESERVES_SLOT);
+        let reserve = self.address_mapping_slot(slot, &[asset]);
+        for offset in 1..=2 {
+            self.constrain_slot(digest, offset - 1, self.slot_offset(&reserve, offset as u64));
+        }
+        let [liquidity_index, variable_borrow_index] =
+            [0, 1].map(|i| self.storage_value(digest, i)[1]);
+        AaveV3ReserveIndexes {
+            pool: digest.address,
+            asset,
+            block_number: digest.block_number,
+            block_hash: digest.block_hash,
+            liquidity_index,
+            variable_borrow_index,
+        }
+    }
+
+    /// Constrains `digest` to be the storage proofs of the base indexes of a Compound v3 market, the `UserBasic` of
+    /// `user` and the `UserCollateral` of `user` in each of `collateral_assets`, in this order.
+    ///
+    /// The principal is an `int104` in two's complement, which is returned as its sign and absolute value.
+    pub fn constrain_compound_v3_position(
+        &self,
+        digest: &EIP1186ResponseDigest<F>,
+        user: AssignedValue<F>,
+        collateral_assets: Vec<AssignedValue<F>>,
+    ) -> CompoundV3Position<F> {
+        assert_eq!(digest.slots_values.len(), 2 + collateral_assets.len());
+        let indexes = self.constant_slot(COMPOUND_V3_INDEXES_SLOT);
+        self.constrain_slot(digest, 0, indexes.hi_lo());
+        let user_basic =
+            self.address_mapping_slot(self.constant_slot(COMPOUND_V3_USER_BASIC_SLOT), &[user]);
+        self.constrain_slot(digest, 1, user_basic.hi_lo());
+        let user_collateral = self.constant_slot(COMPOUND_V3_USER_COLLATERAL_SLOT);
+        for (i, asset) in collateral_assets.iter().enumerate() {
+            let slot = self.address_mapping_slot(user_collateral.clone(), &[user, *asset]);
+            self.constrain_slot(digest, 2 + i, slot.hi_lo());
+        }
+
+        let values: Vec<_> =
+            (0..digest.slots_values.len()).map(|i| self.storage_value(digest, i)).collect();
+
+        let range = self.range();
+        let gate = range.gate();
+        let mut ctx = self.ctx();
+        let indexes = values[0];
+        let base_supply_index = packed_field(&mut ctx, range, indexes, 0, 64);
+        let base_borrow_index = packed_field(&mut ctx, range, indexes, 64, 64);
+        let raw_principal = packed_field(&mut ctx, range, values[1], 0, PRINCIPAL_BITS);
+        let (is_borrow, _) =
+            split_bits(&mut ctx, range, raw_principal, PRINCIPAL_BITS - 1, PRINCIPAL_BITS);
+        let negated =
+            gate.sub(&mut ctx, Constant(gate.pow_of_two()[PRINCIPAL_BITS]), raw_principal);
+        let principal = gate.select(&mut ctx, negated, raw_principal, is_borrow);
+        let collateral = collateral_assets
+            .into_iter()
+            .enumerate()
+            .map(|(i, asset)| (asset, values[2 + i][1]))
+            .collect();
+
+        CompoundV3Position {
+            comet: digest.address,
+            user,
+            block_number: digest.block_number,
+            block_hash: digest.block_hash,
+            base_supply_index,
+            base_borrow_index,
+            is_borrow,
+            principal,
+            collateral,
+        }
+    }
+}
@@ -1,5 +1,45 @@
+pub mod abi;
+pub mod aggregation;
+#[cfg(feature = "providers")]
+pub mod cache;
+pub mod cancel;
+pub mod config;
 pub mod containers;
+pub mod debug;
+pub mod ens;
+pub mod error;
+pub mod evm;
+pub mod gadgets;
+pub mod governance;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod keys;
+#[cfg(feature = "dev-graph")]
+pub mod layout;
+pub mod lending;
+#[cfg(feature = "providers")]
+pub mod mock_chain;
+pub mod mpt;
+pub mod offline;
+pub mod ommers;
+pub mod phase1;
+pub mod pool;
+pub mod proof;
+pub mod providers;
+pub mod ptau;
+pub mod queries;
+pub mod safe;
 pub mod scaffold;
+pub mod schema;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod slots;
+pub mod spec;
+pub mod split;
+pub mod srs;
+pub mod stats;
+pub mod tokens;
+pub mod v2;
 
 // re-expose for convenience
 pub use axiom_eth;
@@ -1,28 +1,231 @@
-use std::env::var;
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
 
-use axiom_eth::{
-    providers::{GOERLI_PROVIDER_URL, MAINNET_PROVIDER_URL},
-    Network,
+use axiom_eth::Network;
+use axiom_scaffold::{
+    config::{AutoDegree, AxiomChipConfig},
+    error::AxiomError,
+    keys::{read_pk, read_vk, write_pk, write_vk, AxiomPinning},
+    proof::{verify, ProvingOptions, TranscriptKind},
+    scaffold::AxiomChip,
+    spec::QuerySpec,
+    srs::SrsManager,
+    v2::{AxiomV2Query, Callback},
 };
-use axiom_scaffold::scaffold::AxiomChip;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use ethers_core::types::Address;
 use ethers_providers::{Http, Provider};
 use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+use serde::Deserialize;
 
-fn main() {
-    env_logger::init();
+type Result<T> = std::result::Result<T, AxiomError>;
 
-    let infura_id = var("INFURA_ID").expect("Infura ID not found: set INFURA_ID env var");
-    let network = Network::Mainnet;
-    let provider_url = match network {
-        Network::Mainnet => MAINNET_PROVIDER_URL,
-        Network::Goerli => GOERLI_PROVIDER_URL,
-    };
-    let provider = Provider::<Http>::try_from(format!("{provider_url}{infura_id}").as_str())
-        .expect("could not instantiate HTTP Provider");
+#[derive(Parser)]
+#[command(name = "axiom-scaffold", about = "Mock, key, prove, and verify an Axiom circuit")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    #[command(flatten)]
+    args: CircuitArgs,
+    /// TOML file with defaults for any of the options above. Options given as flags take precedence.
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Subcommand)]
+enum Command {
+    /// Serves an HTTP API that proves submitted query specs, see `axiom_scaffold::server`.
+    #[cfg(feature = "server")]
+    Serve {
+        #[arg(long, default_value = "127.0.0.1:3000")]
+        addr: std::net::SocketAddr,
+        /// The maximum number of jobs generating keys or proving at the same time.
+        #[arg(long, default_value_t = 1)]
+        max_concurrency: usize,
+        /// Also serves the gRPC API of `proto/prover.proto` on this address.
+        #[cfg(feature = "grpc")]
+        #[arg(long)]
+        grpc_addr: Option<std::net::SocketAddr>,
+    },
+    /// Runs the mock prover on the circuit.
+    Mock,
+    /// Generates the proving key, verifying key, and pinning of the circuit into the data directory.
+    Keygen,
+    /// Proves the circuit with the keys from `keygen`, writing the proof and public instances to the data directory.
+    Prove {
+        /// Also writes the proof as an Axiom V2 query with this callback contract, see `axiom_scaffold::v2`.
+        /// Requires `--transcript keccak`.
+        #[arg(long)]
+        axiom_v2_callback: Option<Address>,
+    },
+    /// Verifies the proof from `prove` against the verifying key from `keygen`.
+    Verify,
+    /// Generates a Yul verifier contract for the circuit and its EVM bytecode into the data directory.
+    GenVerifier,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum NetworkArg {
+    Mainnet,
+    Goerli,
+}
+
+impl From<NetworkArg> for Network {
+    fn from(network: NetworkArg) -> Self {
+        match network {
+            NetworkArg::Mainnet => Network::Mainnet,
+            NetworkArg::Goerli => Network::Goerli,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum TranscriptArg {
+    Blake2b,
+    Poseidon,
+    Keccak,
+}
+
+impl From<TranscriptArg> for TranscriptKind {
+    fn from(transcript: TranscriptArg) -> Self {
+        match transcript {
+            TranscriptArg::Blake2b => TranscriptKind::Blake2b,
+            TranscriptArg::Poseidon => TranscriptKind::Poseidon,
+            TranscriptArg::Keccak => TranscriptKind::Keccak,
+        }
+    }
+}
+
+/// The options of every subcommand. Each can also be set in the `--config` file, using the flag name with underscores.
+#[derive(Args, Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CircuitArgs {
+    /// JSON-RPC URL of an archive node.
+    #[arg(long, global = true)]
+    provider_url: Option<String>,
+    /// Network of the provider. Detected from its chain ID if not set.
+    #[arg(long, global = true, value_enum)]
+    network: Option<NetworkArg>,
+    /// A JSON or TOML query spec describing the circuit. If not set, the example circuit of `build_circuit` is used.
+    #[arg(long, global = true)]
+    spec: Option<PathBuf>,
+    /// The block whose header is queried by the example circuit. Defaults to 16000000.
+    #[arg(long, global = true)]
+    block_number: Option<u32>,
+    /// The circuit has 2^degree rows, or `auto` to choose the smallest that fits. Defaults to 18.
+    #[arg(long, global = true)]
+    degree: Option<String>,
+    /// Defaults to 8.
+    #[arg(long, global = true)]
+    lookup_bits: Option<usize>,
+    /// Defaults to 109.
+    #[arg(long, global = true)]
+    unusable_rows: Option<usize>,
+    /// Directory of the KZG parameters. Defaults to `./params`.
+    #[arg(long, global = true)]
+    params_dir: Option<PathBuf>,
+    /// Reject KZG parameters without a known SHA-256 checksum, instead of only warning.
+    #[arg(long, global = true)]
+    require_srs_checksum: bool,
+    /// Directory keys, proofs, and verifiers are written to and read from. Defaults to `./data`.
+    #[arg(long, global = true)]
+    data_dir: Option<PathBuf>,
+    /// Transcript used for proofs. Defaults to blake2b, use keccak for proofs verified on-chain.
+    #[arg(long, global = true, value_enum)]
+    transcript: Option<TranscriptArg>,
+}
 
-    let mut axiom = AxiomChip::<Fr>::default();
+impl CircuitArgs {
+    /// Fills the options not set in `self` from `other`.
+    fn or(self, other: Self) -> Self {
+        Self {
+            provider_url: self.provider_url.or(other.provider_url),
+            network: self.network.or(other.network),
+            spec: self.spec.or(other.spec),
+            block_number: self.block_number.or(other.block_number),
+            degree: self.degree.or(other.degree),
+            lookup_bits: self.lookup_bits.or(other.lookup_bits),
+            unusable_rows: self.unusable_rows.or(other.unusable_rows),
+            params_dir: self.params_dir.or(other.params_dir),
+            require_srs_checksum: self.require_srs_checksum || other.require_srs_checksum,
+            data_dir: self.data_dir.or(other.data_dir),
+            transcript: self.transcript.or(other.transcript),
+        }
+    }
 
-    let block = axiom.eth_getBlockByNumber(&provider, 16_000_000);
+    fn chip_config(&self) -> Result<AxiomChipConfig> {
+        let mut config = AxiomChipConfig {
+            k: 18,
+            lookup_bits: self.lookup_bits.unwrap_or(8),
+            unusable_rows: self.unusable_rows.unwrap_or(109),
+            auto_degree: None,
+            keccak_rows_per_round: None,
+        };
+        match self.degree.as_deref() {
+            None => {}
+            Some("auto") => {
+                let auto = AutoDegree::default();
+                config.auto_degree = Some(auto);
+                config.k = auto.max_k;
+            }
+            Some(degree) => {
+                config.k = degree.parse().map_err(|_| {
+                    AxiomError::MissingInput(format!(
+                        "degree must be a number or `auto`, got {degree}"
+                    ))
+                })?
+            }
+        }
+        Ok(config)
+    }
+
+    fn provider(&self) -> Result<Provider<Http>> {
+        let url = self.provider_url.as_deref().ok_or_else(|| {
+            AxiomError::MissingInput("set the provider URL with --provider-url".to_string())
+        })?;
+        Provider::<Http>::try_from(url)
+            .map_err(|err| AxiomError::MissingInput(format!("invalid provider URL {url}: {err}")))
+    }
+
+    fn srs(&self) -> SrsManager {
+        let mut srs = SrsManager::default();
+        if let Some(dir) = &self.params_dir {
+            srs.dir = dir.clone();
+        }
+        srs.require_checksum = self.require_srs_checksum;
+        srs
+    }
+
+    fn proving_options(&self) -> ProvingOptions {
+        ProvingOptions {
+            transcript: self.transcript.map(Into::into).unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    fn data_path(&self, file: &str) -> PathBuf {
+        self.data_dir.clone().unwrap_or_else(|| "data".into()).join(file)
+    }
+}
+
+/// Writes the circuit into `axiom`, from the `--spec` if one is given. Otherwise, replace the rest with your own circuit.
+fn build_circuit(axiom: &mut AxiomChip<Fr>, args: &CircuitArgs) -> Result<()> {
+    if let Some(network) = args.network {
+        axiom.set_network(network.into());
+    }
+    axiom.set_proving_options(args.proving_options());
+    if let Some(path) = &args.spec {
+        axiom.load_spec(&args.provider()?, &QuerySpec::from_path(path)?)?;
+        return Ok(());
+    }
+    let block_number = args.block_number.unwrap_or(16_000_000);
+
+    let block = axiom.eth_getBlockByNumber(&args.provider()?, block_number)?;
     // Debug display of a block header field:
     // dbg!(block.number);
     // Note that block.number.bytes has fixed length 4, but the variable string length is specified by block.number.len
@@ -31,13 +234,158 @@ fn main() {
     // `AxiomChip` also has access to all functions in other chips like `GateChip` and `RangeChip`.
     // For example,
     let number = block.number.evaluate(&mut axiom.ctx(), axiom.gate());
-    assert_eq!(number.value(), &Fr::from(16_000_000u64));
+    assert_eq!(number.value(), &Fr::from(block_number as u64));
 
     // All variables are private by default. You can expose an `AssignedValue` to be public by calling `expose_public`:
     axiom.expose_public(number);
+    Ok(())
+}
 
-    axiom.mock();
+fn read_instances(path: &Path) -> Result<Vec<Fr>> {
+    let hex: Vec<String> = serde_json::from_reader(BufReader::new(File::open(path)?))
+        .map_err(|err| AxiomError::Io(err.into()))?;
+    hex.iter()
+        .map(|word| {
+            let mut repr = [0u8; 32];
+            let bytes = (0..word.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(word.get(i..i + 2)?, 16).ok())
+                .collect::<Option<Vec<_>>>();
+            match bytes {
+                Some(bytes) if bytes.len() == 32 => repr.copy_from_slice(&bytes),
+                _ => return Err(AxiomError::MissingInput(format!("invalid instance {word}"))),
+            }
+            Option::from(Fr::from_bytes(&repr))
+                .ok_or_else(|| AxiomError::MissingInput(format!("invalid instance {word}")))
+        })
+        .collect()
+}
+
+/// Writes each instance as the hex of its 32 byte little endian representation.
+fn write_instances(path: &Path, instances: &[Fr]) -> Result<()> {
+    let hex: Vec<String> = instances
+        .iter()
+        .map(|instance| instance.to_bytes().iter().map(|byte| format!("{byte:02x}")).collect())
+        .collect();
+    serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), &hex)
+        .map_err(|err| AxiomError::Io(err.into()))
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+
+    let cli = Cli::parse();
+    let args = match &cli.config {
+        Some(path) => {
+            let file: CircuitArgs = toml::from_str(&fs::read_to_string(path)?).map_err(|err| {
+                AxiomError::MissingInput(format!("invalid config {}: {err}", path.display()))
+            })?;
+            cli.args.or(file)
+        }
+        None => cli.args,
+    };
+    let pinning_path = args.data_path("circuit.json");
+    let pk_path = args.data_path("circuit.pk");
+    let vk_path = args.data_path("circuit.vk");
+    let proof_path = args.data_path("circuit.proof");
+    let instances_path = args.data_path("circuit.instances.json");
 
-    // Uncomment to run the real prover; can be slow / memory intensive depending on your machine
-    // axiom.prove();
+    match cli.command {
+        #[cfg(feature = "server")]
+        Command::Serve {
+            addr,
+            max_concurrency,
+            #[cfg(feature = "grpc")]
+            grpc_addr,
+        } => {
+            let server = axiom_scaffold::server::ProverServer::new(
+                args.provider()?,
+                args.chip_config()?,
+                args.proving_options(),
+                args.srs(),
+                max_concurrency,
+            );
+            let runtime = tokio::runtime::Runtime::new()?;
+            #[cfg(feature = "grpc")]
+            if let Some(grpc_addr) = grpc_addr {
+                let server = server.clone();
+                runtime.spawn(async move {
+                    if let Err(err) = server.serve_grpc(grpc_addr).await {
+                        log::error!("gRPC server failed: {err}");
+                    }
+                });
+            }
+            runtime.block_on(server.serve(addr))?;
+        }
+        Command::Mock => {
+            let mut axiom = AxiomChip::<Fr>::with_config(args.chip_config()?);
+            build_circuit(&mut axiom, &args)?;
+            axiom.mock()?;
+        }
+        Command::Keygen => {
+            let mut axiom = AxiomChip::<Fr>::with_config(args.chip_config()?);
+            build_circuit(&mut axiom, &args)?;
+            let (pk, pinning) = axiom.gen_keys(&args.srs())?;
+            fs::create_dir_all(args.data_path(""))?;
+            write_pk(&pk, &pk_path)?;
+            write_vk(pk.get_vk(), &vk_path)?;
+            pinning.write(&pinning_path)?;
+            println!("Wrote keys and pinning to {}", args.data_path("").display());
+        }
+        Command::Prove { axiom_v2_callback } => {
+            let pinning = AxiomPinning::from_path(&pinning_path)?;
+            let pk = read_pk(&pk_path, &pinning.params)?;
+            let params = args.srs().params(pinning.params.degree)?;
+            let mut axiom = AxiomChip::<Fr>::prover(pinning);
+            build_circuit(&mut axiom, &args)?;
+            // The data query is read from the chip, which proving consumes.
+            let v2_query = match axiom_v2_callback {
+                Some(target) => {
+                    Some((axiom.axiom_v2_data_query()?, axiom.has_accumulator(), target))
+                }
+                None => None,
+            };
+            let artifacts = axiom.prove_with_pk(&params, &pk)?;
+            fs::write(&proof_path, &artifacts.proof)?;
+            write_instances(&instances_path, &artifacts.instances)?;
+            println!("Wrote proof to {}", proof_path.display());
+            if let Some((data_query, has_accumulator, target)) = v2_query {
+                let callback = Callback { target, ..Default::default() };
+                let query = AxiomV2Query::new(data_query, &artifacts, has_accumulator, callback)?;
+                let query_path = args.data_path("circuit.axiom_v2.json");
+                serde_json::to_writer_pretty(BufWriter::new(File::create(&query_path)?), &query)
+                    .map_err(|err| AxiomError::Io(err.into()))?;
+                println!(
+                    "Wrote Axiom V2 query {:?} to {}",
+                    query.query_hash(),
+                    query_path.display()
+                );
+            }
+        }
+        Command::Verify => {
+            let pinning = AxiomPinning::from_path(&pinning_path)?;
+            let vk = read_vk(&vk_path, &pinning.params)?;
+            let params = args.srs().params(pinning.params.degree)?;
+            let proof = fs::read(&proof_path)?;
+            let instances = read_instances(&instances_path)?;
+            let options = args.proving_options();
+            if !verify(&params, &vk, &proof, &instances, options.transcript, options.multi_open) {
+                return Err(AxiomError::InvalidProof);
+            }
+            println!("Proof is valid!");
+        }
+        Command::GenVerifier => {
+            let pinning = AxiomPinning::from_path(&pinning_path)?;
+            let vk = read_vk(&vk_path, &pinning.params)?;
+            let params = args.srs().params(pinning.params.degree)?;
+            let mut axiom = AxiomChip::<Fr>::prover(pinning);
+            build_circuit(&mut axiom, &args)?;
+            let bytecode =
+                axiom.gen_evm_verifier(&params, &vk, Some(&args.data_path("verifier.yul")));
+            let hex: String = bytecode.iter().map(|byte| format!("{byte:02x}")).collect();
+            fs::write(args.data_path("verifier.bin"), hex)?;
+            println!("Wrote verifier to {}", args.data_path("verifier.yul").display());
+        }
+    }
+    Ok(())
 }
@@ -0,0 +1,205 @@
+use std::{fmt, io, path::Path};
+
+use async_trait::async_trait;
+use ethers_core::types::{Address, H256, U256, U64};
+use ethers_providers::{JsonRpcClient, JsonRpcError, Provider, ProviderError, RpcError};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::offline::InputBundle;
+
+/// The JSON-RPC error code of missing fixtures, the generic server error code that nodes also return e.g. for
+/// unknown blocks.
+const MISSING_ERROR_CODE: i64 = -32000;
+
+/// A JSON-RPC client that serves blocks and proofs from an [`InputBundle`] instead of a node, so that circuits can be
+/// tested without network access. Use it through [`MockChainProvider::provider`], which can be passed to any query.
+///
+/// The methods used by queries are supported: `eth_chainId`, `eth_blockNumber`, `eth_getBlockByNumber`,
+/// `eth_getUncleByBlockNumberAndIndex`, `eth_getProof`, and `eth_getStorageAt`. `eth_blockNumber` returns the latest
+/// block number recorded in the bundle, or else its highest block.
+#[derive(Clone, Debug)]
+pub struct MockChainProvider {
+    pub inputs: InputBundle,
+}
+
+impl MockChainProvider {
+    pub fn new(inputs: InputBundle) -> Self {
+        Self { inputs }
+    }
+
+    /// Reads the fixtures from an [`InputBundle`] JSON file, e.g., one written by
+    /// [`AxiomChip::export_inputs`](crate::scaffold::AxiomChip::export_inputs).
+    pub fn from_path(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(InputBundle::read(path)?))
+    }
+
+    pub fn provider(self) -> Provider<Self> {
+        Provider::new(self)
+    }
+
+    fn respond(&self, method: &str, params: Value) -> Result<Value, MockChainError> {
+        let param = |i: usize| params.get(i).cloned().unwrap_or(Value::Null);
+        match method {
+            "eth_chainId" => Ok(serde_json::to_value(U256::from(self.inputs.chain_id))?),
+            "eth_blockNumber" => {
+                let latest = self.inputs.latest_block_number.or_else(|| {
+                    self.inputs
+                        .blocks
+                        .iter()
+                        .filter_map(|block| block.number)
+                        .max()
+                        .map(|n| n.as_u64())
+                });
+                let latest =
+                    latest.ok_or_else(|| MockChainError::missing("no blocks".to_string()))?;
+                Ok(serde_json::to_value(U64::from(latest))?)
+            }
+            "eth_getBlockByNumber" => {
+                let block_number: U64 = serde_json::from_value(param(0))?;
+                let block = self
+                    .inputs
+                    .block(block_number.as_u64())
+                    .ok_or_else(|| MockChainError::missing(format!("no block {block_number}")))?;
+                Ok(serde_json::to_value(block)?)
+            }
+            "eth_getUncleByBlockNumberAndIndex" => {
+                let block_number: U64 = serde_json::from_value(param(0))?;
+                let index: U64 = serde_json::from_value(param(1))?;
+                let uncle = self
+                    .inputs
+                    .uncles(block_number.as_u64())
+                    .and_then(|uncles| uncles.get(index.as_usize()))
+                    .ok_or_else(|| {
+                        MockChainError::missing(format!("no uncle {index} of block {block_number}"))
+                    })?;
+                Ok(serde_json::to_value(uncle)?)
+            }
+            "eth_getStorageAt" => {
+                let address: Address = serde_json::from_value(param(0))?;
+                // The position is a quantity, e.g. "0x3", not a 32 byte word.
+                let position: U256 = serde_json::from_value(param(1))?;
+                let mut slot = H256::zero();
+                position.to_big_endian(&mut slot.0);
+                let block_number: U64 = serde_json::from_value(param(2))?;
+                let value = self
+                    .inputs
+                    .storage_at(block_number.as_u64(), address, slot)
+                    .ok_or_else(|| {
+                        MockChainError::missing(format!(
+                            "no value of slot {slot:?} of {address:?} at block {block_number}"
+                        ))
+                    })?;
+                Ok(serde_json::to_value(value)?)
+            }
+            "eth_getProof" => {
+                let address: Address = serde_json::from_value(param(0))?;
+                let slots: Vec<H256> = serde_json::from_value(param(1))?;
+                let block_number: U64 = serde_json::from_value(param(2))?;
+                let proof =
+                    self.inputs.proof(block_number.as_u64(), address, &slots).ok_or_else(|| {
+                        MockChainError::missing(format!(
+                            "no proof for {address:?} and slots {slots:?} at block {block_number}"
+                        ))
+                    })?;
+                Ok(serde_json::to_value(proof)?)
+            }
+            _ => Err(MockChainError::missing(format!("unsupported method {method}"))),
+        }
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for MockChainProvider {
+    type Error = MockChainError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: fmt::Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let response = self.respond(method, serde_json::to_value(params)?)?;
+        Ok(serde_json::from_value(response)?)
+    }
+}
+
+/// An error of [`MockChainProvider`].
+#[derive(Debug)]
+pub enum MockChainError {
+    /// The fixtures do not contain the requested data. This is reported as a JSON-RPC error response, like a node
+    /// that does not have the data, so that it is not retried.
+    Missing(JsonRpcError),
+    Serde(serde_json::Error),
+}
+
+impl MockChainError {
+    fn missing(message: String) -> Self {
+        Self::Missing(JsonRpcError { code: MISSING_ERROR_CODE, message, data: None })
+    }
+}
+
+impl fmt::Display for MockChainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing(err) => write!(f, "missing fixture: {}", err.message),
+            Self::Serde(err) => write!(f, "serialization error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for MockChainError {}
+
+impl RpcError for MockChainError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            Self::Missing(err) => Some(err),
+            Self::Serde(_) => None,
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            Self::Serde(err) => Some(err),
+            Self::Missing(_) => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for MockChainError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Serde(err)
+    }
+}
+
+impl From<MockChainError> for ProviderError {
+    fn from(err: MockChainError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axiom_eth::Network;
+    use ethers_providers::Middleware;
+    use tokio::runtime::Runtime;
+
+    use super::*;
+    use crate::offline::StorageInput;
+
+    #[test]
+    fn test_get_storage_at() {
+        let address = Address::from_low_u64_be(0xabcd);
+        let slot = H256::from_low_u64_be(3);
+        let value = H256::from_low_u64_be(42);
+        let mut inputs = InputBundle::new(Network::Mainnet);
+        inputs.storage.push(StorageInput { block_number: 100, address, slot, value });
+        let provider = MockChainProvider::new(inputs).provider();
+        let runtime = Runtime::new().unwrap();
+        let fetched = runtime.block_on(provider.get_storage_at(address, slot, Some(100u64.into())));
+        assert_eq!(fetched.unwrap(), value);
+        let missing = H256::from_low_u64_be(4);
+        assert!(runtime
+            .block_on(provider.get_storage_at(address, missing, Some(100u64.into())))
+            .is_err());
+    }
+}
@@ -0,0 +1,106 @@
+//! Proofs of inclusion or exclusion of a key in any Merkle Patricia trie with 32 byte keys, e.g. a custom trie whose
+//! root is committed to in a contract, beyond the account and storage proofs of `eth_getProof`.
+//!
+//! The proof is parsed by the MPT chip of `axiom-eth`, whose RLCs are constrained in phase 1 like those of storage
+//! proofs.
+
+use axiom_eth::{mpt::MPTFixedKeyInput, providers::is_assigned_slot, Field};
+use ethers_core::{
+    types::{Bytes, H256},
+    utils::rlp::Rlp,
+};
+use halo2_base::AssignedValue;
+
+use crate::{
+    containers::H256Assigned,
+    error::{AxiomError, Result},
+    scaffold::{AxiomChip, ACCOUNT_PROOF_MAX_DEPTH},
+    schema::ACCOUNT_RLP_MAX_BYTES,
+};
+
+/// The most nodes in the proof of [`AxiomChip::prove_mpt_exclusion`], as for account proofs.
+pub const MPT_PROOF_MAX_DEPTH: usize = ACCOUNT_PROOF_MAX_DEPTH;
+/// The longest value of a key included in the trie that [`AxiomChip::prove_mpt_exclusion`] can prove, as for account
+/// proofs.
+pub const MPT_VALUE_MAX_BYTES: usize = ACCOUNT_RLP_MAX_BYTES;
+
+/// The value of a key that is not in the trie, which the MPT chip does not check against the proof.
+const EMPTY_VALUE: u8 = 0x80;
+
+/// The bytes of the assigned word `word`.
+fn word_value<F: Field>(word: &H256Assigned<F>) -> H256 {
+    H256(word.bytes.map(|byte| byte.value().get_lower_32() as u8))
+}
+
+impl<F: Field> AxiomChip<F> {
+    /// Proves whether `key` is missing from the Merkle Patricia trie with root `root`, given the nodes `proof_nodes` of
+    /// its path from the root, as in an `eth_getProof` response. Returns a constrained boolean that is one if `key` is
+    /// not in the trie, and zero if it is.
+    ///
+    /// `key` is the path in the trie, so for the secure tries of the state and of storage it is the keccak hash of the
+    /// address or slot. The circuit layout does not depend on the proof, which has at most [`MPT_PROOF_MAX_DEPTH`]
+    /// nodes, and the value of an included key must be at most [`MPT_VALUE_MAX_BYTES`] bytes.
+    ///
+    /// Returns [`AxiomError::ProofTooDeep`] if the proof has too many nodes, and [`AxiomError::InvalidInput`] if it
+    /// has none, if the last node of an inclusion proof is not a leaf, or if the value is too long.
+    pub fn prove_mpt_exclusion(
+        &mut self,
+        root: &H256Assigned<F>,
+        key: &H256Assigned<F>,
+        proof_nodes: &[Bytes],
+    ) -> Result<AssignedValue<F>> {
+        let depth = proof_nodes.len();
+        if depth > MPT_PROOF_MAX_DEPTH {
+            return Err(AxiomError::ProofTooDeep { depth, max_depth: MPT_PROOF_MAX_DEPTH });
+        }
+        let Some(last) = proof_nodes.last() else {
+            return Err(AxiomError::InvalidInput("the MPT proof has no nodes".to_string()));
+        };
+        let path = word_value(key);
+        let slot_is_empty = !is_assigned_slot(&path, proof_nodes);
+        let value = if slot_is_empty {
+            vec![EMPTY_VALUE]
+        } else {
+            // the last node of an inclusion proof is the leaf `[encoded path, value]`
+            Rlp::new(last).val_at::<Vec<u8>>(1).map_err(|err| {
+                AxiomError::InvalidInput(format!(
+                    "the last node of the MPT proof is not a leaf: {err}"
+                ))
+            })?
+        };
+        if value.len() > MPT_VALUE_MAX_BYTES {
+            return Err(AxiomError::InvalidInput(format!(
+                "the value of the key has {} bytes, more than the maximum of {MPT_VALUE_MAX_BYTES}",
+                value.len()
+            )));
+        }
+        let input = MPTFixedKeyInput {
+            path,
+            value,
+            root_hash: word_value(root),
+            proof: proof_nodes.iter().map(|node| node.to_vec()).collect(),
+            value_max_byte_len: MPT_VALUE_MAX_BYTES,
+            max_depth: MPT_PROOF_MAX_DEPTH,
+            slot_is_empty,
+        };
+
+        let proof = input.assign(&mut self.ctx());
+        {
+            let mut ctx = self.ctx();
+            for (a, b) in proof.root_hash_bytes.iter().zip(&root.bytes) {
+                ctx.constrain_equal(a, b);
+            }
+            for (a, b) in proof.key_bytes.iter().zip(&key.bytes) {
+                ctx.constrain_equal(a, b);
+            }
+        }
+        let is_excluded = proof.slot_is_empty;
+        let witness = self.eth_chip().parse_mpt_inclusion_fixed_key_phase0(
+            &mut self.ctx(),
+            &mut self.keccak.lock(),
+            proof,
+        );
+        self.mpt_witness.push(witness);
+        Ok(is_excluded)
+    }
+}
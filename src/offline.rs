@@ -0,0 +1,399 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter},
+    path::Path,
+};
+
+use axiom_eth::{providers::get_block_rlp, storage::EIP1186ResponseDigest, Field, Network};
+use ethers_core::{
+    types::{Address, Block, EIP1186ProofResponse, H256, U256},
+    utils::{
+        keccak256,
+        rlp::{DecoderError, Rlp},
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    containers::EthBlock,
+    error::{AxiomError, Result},
+    ommers::Ommers,
+    providers::{block_storage_input, chain_id, network_from_chain_id},
+    scaffold::{header_rlp_max_bytes, AxiomChip, ProofDepths},
+};
+
+/// The index of the state root among the fields of a block header.
+const HEADER_STATE_ROOT_INDEX: usize = 3;
+/// The index of the block number among the fields of a block header.
+const HEADER_NUMBER_INDEX: usize = 8;
+
+/// The version of the [`InputBundle`] format written by this crate.
+pub const INPUT_BUNDLE_VERSION: u32 = 1;
+
+/// Chain data captured from a provider, so that witnesses can be generated later without network access.
+///
+/// A bundle exported with [`AxiomChip::export_inputs`] holds every provider response used by the queries, so every
+/// query can be replayed from it with a [`MockChainProvider`](crate::mock_chain::MockChainProvider). Without a
+/// provider, the blocks, proofs and uncles can be used with the `*_offline` queries.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InputBundle {
+    pub version: u32,
+    pub chain_id: u64,
+    pub blocks: Vec<Block<H256>>,
+    pub proofs: Vec<ProofInput>,
+    #[serde(default)]
+    pub uncles: Vec<UncleInput>,
+    /// The `eth_getStorageAt` responses used to find which slots to prove, e.g. by [`AxiomChip::safe_config`].
+    #[serde(default)]
+    pub storage: Vec<StorageInput>,
+    /// The latest block number when it was queried, e.g. by [`AxiomChip::eth_getBlockAtTimestamp`].
+    #[serde(default)]
+    pub latest_block_number: Option<u64>,
+}
+
+/// An `eth_getProof` response at block `block_number`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProofInput {
+    pub block_number: u64,
+    pub proof: EIP1186ProofResponse,
+}
+
+/// The uncle headers of block `block_number`, in order.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UncleInput {
+    pub block_number: u64,
+    pub uncles: Vec<Block<H256>>,
+}
+
+/// The `eth_getStorageAt` response for `slot` of `address` at block `block_number`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StorageInput {
+    pub block_number: u64,
+    pub address: Address,
+    pub slot: H256,
+    pub value: H256,
+}
+
+/// The hash, number and state root of the block with the RLP encoded header `header_rlp` of `network`, which is all
+/// the circuit inputs need besides the header itself.
+///
+/// Returns [`AxiomError::InvalidInput`] if `header_rlp` is not a block header or is longer than the headers of
+/// `network` can be.
+fn header_block(header_rlp: &[u8], network: Network) -> Result<Block<H256>> {
+    let max_bytes = header_rlp_max_bytes(network);
+    if header_rlp.len() > max_bytes {
+        return Err(AxiomError::InvalidInput(format!(
+            "block header has {} bytes, more than the {max_bytes} of {network:?} headers",
+            header_rlp.len()
+        )));
+    }
+    let invalid =
+        |err: DecoderError| AxiomError::InvalidInput(format!("invalid block header RLP: {err}"));
+    let header = Rlp::new(header_rlp);
+    let number: U256 = header.val_at(HEADER_NUMBER_INDEX).map_err(invalid)?;
+    let number = u64::try_from(number)
+        .map_err(|_| AxiomError::InvalidInput(format!("block number {number} is too large")))?;
+    Ok(Block {
+        hash: Some(H256(keccak256(header_rlp))),
+        number: Some(number.into()),
+        state_root: header.val_at(HEADER_STATE_ROOT_INDEX).map_err(invalid)?,
+        ..Default::default()
+    })
+}
+
+impl InputBundle {
+    /// Creates an empty bundle for `network`.
+    pub fn new(network: Network) -> Self {
+        Self {
+            version: INPUT_BUNDLE_VERSION,
+            chain_id: chain_id(network),
+            blocks: vec![],
+            proofs: vec![],
+            uncles: vec![],
+            storage: vec![],
+            latest_block_number: None,
+        }
+    }
+
+    pub fn network(&self) -> Result<Network> {
+        network_from_chain_id(self.chain_id.into())
+    }
+
+    /// Reads a bundle from a JSON file, failing if it was written in a different version of the format.
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Self> {
+        let bundle: Self = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        if bundle.version != INPUT_BUNDLE_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "input bundle version {} is not supported, expected {INPUT_BUNDLE_VERSION}",
+                    bundle.version
+                ),
+            ));
+        }
+        Ok(bundle)
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), self)?;
+        Ok(())
+    }
+
+    /// Returns block `block_number`, if the bundle contains it.
+    pub fn block(&self, block_number: u64) -> Option<&Block<H256>> {
+        self.blocks.iter().find(|block| block.number.map(|n| n.as_u64()) == Some(block_number))
+    }
+
+    /// Returns the `eth_getProof` response for `slots` of `address` at block `block_number`, if the bundle contains it.
+    pub fn proof(
+        &self,
+        block_number: u64,
+        address: Address,
+        slots: &[H256],
+    ) -> Option<&EIP1186ProofResponse> {
+        self.proofs
+            .iter()
+            .find(|input| {
+                input.block_number == block_number
+                    && input.proof.address == address
+                    && input.proof.storage_proof.iter().map(|pf| pf.key).eq(slots.iter().copied())
+            })
+            .map(|input| &input.proof)
+    }
+
+    /// Returns the uncle headers of block `block_number`, if the bundle contains them.
+    pub fn uncles(&self, block_number: u64) -> Option<&[Block<H256>]> {
+        self.uncles
+            .iter()
+            .find(|input| input.block_number == block_number)
+            .map(|input| &input.uncles[..])
+    }
+
+    /// Returns the value of `slot` of `address` at block `block_number`, from an `eth_getStorageAt` response or from the
+    /// storage proofs, if the bundle contains it.
+    pub fn storage_at(&self, block_number: u64, address: Address, slot: H256) -> Option<H256> {
+        let read = self.storage.iter().find(|input| {
+            input.block_number == block_number && input.address == address && input.slot == slot
+        });
+        if let Some(read) = read {
+            return Some(read.value);
+        }
+        self.proofs
+            .iter()
+            .filter(|input| input.block_number == block_number && input.proof.address == address)
+            .flat_map(|input| &input.proof.storage_proof)
+            .find(|pf| pf.key == slot)
+            .map(|pf| {
+                let mut value = H256::zero();
+                pf.value.to_big_endian(&mut value.0);
+                value
+            })
+    }
+}
+
+impl<F: Field> AxiomChip<F> {
+    /// Writes every provider response used by queries so far to `path` as an [`InputBundle`], so that the witnesses can
+    /// be generated again offline, e.g., to reproduce or audit a proof: the blocks, `eth_getProof` and
+    /// `eth_getStorageAt` responses, uncle headers, and the latest block number.
+    ///
+    /// Headers passed directly to [`AxiomChip::eth_getBlockByNumber_from_rlp`] are not recorded.
+    pub fn export_inputs(&self, path: impl AsRef<Path>) -> Result<()> {
+        let network = self.network().ok_or_else(|| {
+            AxiomError::MissingInput(
+                "no query has been made, so the network is unknown".to_string(),
+            )
+        })?;
+        let bundle = InputBundle {
+            blocks: self.recorded_blocks.clone(),
+            proofs: self.recorded_proofs.clone(),
+            uncles: self.recorded_uncles.clone(),
+            storage: self.recorded_storage.clone(),
+            latest_block_number: self.recorded_latest_block,
+            ..InputBundle::new(network)
+        };
+        bundle.write(path)?;
+        Ok(())
+    }
+
+    /// Records `block` for [`AxiomChip::export_inputs`].
+    pub(crate) fn record_block(&mut self, block: &Block<H256>) {
+        if !self.recorded_blocks.iter().any(|recorded| recorded.number == block.number) {
+            self.recorded_blocks.push(block.clone());
+        }
+    }
+
+    /// Sets the network of this chip to `network` if it has none yet. Returns [`AxiomError::InvalidInput`] if it is
+    /// already set to another network, since the queries of a circuit must all be against the same chain.
+    pub(crate) fn use_network(&mut self, network: Network) -> Result<()> {
+        match self.network() {
+            Some(current) if chain_id(current) != chain_id(network) => {
+                Err(AxiomError::InvalidInput(format!(
+                    "the chip is on chain {}, not chain {}",
+                    chain_id(current),
+                    chain_id(network)
+                )))
+            }
+            Some(_) => Ok(()),
+            None => {
+                self.set_network(network);
+                Ok(())
+            }
+        }
+    }
+
+    /// Records the uncle headers `uncles` of block `block_number` for [`AxiomChip::export_inputs`].
+    pub(crate) fn record_uncles(&mut self, block_number: u64, uncles: &[Block<H256>]) {
+        if !self.recorded_uncles.iter().any(|recorded| recorded.block_number == block_number) {
+            self.recorded_uncles.push(UncleInput { block_number, uncles: uncles.to_vec() });
+        }
+    }
+
+    /// Same as [`AxiomChip::eth_getBlockByNumber`], but from the RLP encoded block header instead of a provider, see
+    /// [`AxiomChip::prove_block_header_rlp`].
+    pub fn eth_getBlockByNumber_from_rlp(
+        &mut self,
+        block_rlp: Vec<u8>,
+        network: Network,
+    ) -> Result<EthBlock<F>> {
+        self.prove_block_header_rlp(&block_rlp, network)
+    }
+
+    /// Proves the RLP encoded block header `header_rlp` of `network`, e.g. from local archival data or a source other
+    /// than JSON-RPC, without a provider. Like a block queried by number, a block proven before is reused, and later
+    /// queries of the block reuse this one.
+    ///
+    /// Headers proven this way are not recorded for [`AxiomChip::export_inputs`]. Returns [`AxiomError::InvalidInput`]
+    /// if `header_rlp` is not a block header or is longer than the headers of `network` can be, or if the chip already
+    /// has queries on another network.
+    pub fn prove_block_header_rlp(
+        &mut self,
+        header_rlp: &[u8],
+        network: Network,
+    ) -> Result<EthBlock<F>> {
+        let block_number = header_block(header_rlp, network)?.number.unwrap_or_default().as_u64();
+        self.use_network(network)?;
+        Ok(self.assign_block_rlp(block_number, header_rlp.to_vec(), network))
+    }
+
+    /// Same as [`AxiomChip::eth_getProof`], but from a previously fetched `eth_getProof` response against `block`
+    /// instead of a provider.
+    ///
+    /// Returns [`AxiomError::ProofTooDeep`] if a proof is deeper than the default [`ProofDepths`], see
+    /// [`AxiomChip::eth_getProof_from_response_with_depths`], and [`AxiomError::InvalidInput`] if `block` has no hash or
+    /// number or if the chip already has queries on another network.
+    pub fn eth_getProof_from_response(
+        &mut self,
+        block: &Block<H256>,
+        proof: EIP1186ProofResponse,
+        network: Network,
+    ) -> Result<EIP1186ResponseDigest<F>> {
+        self.eth_getProof_from_response_with_depths(block, proof, network, ProofDepths::default())
+    }
+
+    /// Same as [`AxiomChip::eth_getProof_from_response`], with the proofs padded to `depths` nodes. Returns
+    /// [`AxiomError::ProofTooDeep`] if a proof has more nodes.
+    pub fn eth_getProof_from_response_with_depths(
+        &mut self,
+        block: &Block<H256>,
+        proof: EIP1186ProofResponse,
+        network: Network,
+        depths: ProofDepths,
+    ) -> Result<EIP1186ResponseDigest<F>> {
+        depths.check(&proof)?;
+        let block_number = block
+            .number
+            .ok_or_else(|| AxiomError::InvalidInput("block has no number".to_string()))?
+            .as_u64();
+        self.use_network(network)?;
+        let (account, storage) = (depths.account, depths.storage);
+        let input = block_storage_input(
+            block.clone(),
+            get_block_rlp(block),
+            proof.clone(),
+            account,
+            storage,
+        )?;
+        self.record_block(block);
+        self.recorded_proofs.push(ProofInput { block_number, proof });
+        Ok(self.assign_storage_input(input, network))
+    }
+
+    /// Same as [`AxiomChip::eth_getProof_from_response`], but against the block with the RLP encoded header `block_rlp`,
+    /// e.g. to prove `eth_getProof` responses captured in an archive dump together with raw headers.
+    ///
+    /// Like headers proven with [`AxiomChip::prove_block_header_rlp`], the response is not recorded for
+    /// [`AxiomChip::export_inputs`]. Returns [`AxiomError::ProofTooDeep`] if a proof is deeper than the default
+    /// [`ProofDepths`], and [`AxiomError::InvalidInput`] if `block_rlp` is not a block header of `network` or if the
+    /// chip already has queries on another network.
+    pub fn prove_eip1186_response(
+        &mut self,
+        block_rlp: &[u8],
+        proof: EIP1186ProofResponse,
+        network: Network,
+    ) -> Result<EIP1186ResponseDigest<F>> {
+        let depths = ProofDepths::default();
+        depths.check(&proof)?;
+        let block = header_block(block_rlp, network)?;
+        self.use_network(network)?;
+        let input =
+            block_storage_input(block, block_rlp.to_vec(), proof, depths.account, depths.storage)?;
+        Ok(self.assign_storage_input(input, network))
+    }
+
+    /// Same as [`AxiomChip::eth_getBlockByNumber`], but with the block taken from `inputs` instead of a provider.
+    ///
+    /// Returns [`AxiomError::InvalidInput`] if the chip already has queries on another network than `inputs`.
+    pub fn eth_getBlockByNumber_offline(
+        &mut self,
+        inputs: &InputBundle,
+        block_number: u32,
+    ) -> Result<EthBlock<F>> {
+        let block = inputs
+            .block(block_number as u64)
+            .ok_or(AxiomError::BlockNotFound(block_number as u64))?;
+        let network = inputs.network()?;
+        self.use_network(network)?;
+        self.record_block(block);
+        Ok(self.assign_block(block, network))
+    }
+
+    /// Same as [`AxiomChip::eth_getOmmers`], but with the block and its uncles taken from
+    /// `inputs` instead of a provider.
+    pub fn eth_getOmmers_offline(
+        &mut self,
+        inputs: &InputBundle,
+        block_number: u32,
+    ) -> Result<Ommers<F>> {
+        let uncles = inputs.uncles(block_number as u64).ok_or_else(|| {
+            AxiomError::MissingInput(format!("no uncles of block {block_number}"))
+        })?;
+        let header = self.eth_getBlockByNumber_offline(inputs, block_number)?;
+        self.record_uncles(block_number as u64, uncles);
+        let network = inputs.network()?;
+        Ok(self.assign_ommers(header, uncles, network))
+    }
+
+    /// Same as [`AxiomChip::eth_getProof`], but with the block and proofs taken from `inputs` instead of a provider.
+    pub fn eth_getProof_offline(
+        &mut self,
+        inputs: &InputBundle,
+        address: Address,
+        slots: Vec<H256>,
+        block_number: u32,
+    ) -> Result<EIP1186ResponseDigest<F>> {
+        let block_number = block_number as u64;
+        let block = inputs.block(block_number).ok_or(AxiomError::BlockNotFound(block_number))?;
+        let proof = inputs.proof(block_number, address, &slots).ok_or_else(|| {
+            AxiomError::MissingInput(format!(
+                "no proof for {address:?} and slots {slots:?} at block {block_number}"
+            ))
+        })?;
+        let network = inputs.network()?;
+        self.eth_getProof_from_response_with_depths(
+            block,
+            proof.clone(),
+            network,
+            ProofDepths::default(),
+        )
+    }
+}
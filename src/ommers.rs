@@ -0,0 +1,197 @@
+//! Proofs of the ommers (uncle) list of a block against the `ommers_hash` of its header.
+//!
+//! The ommers hash is the keccak hash of the RLP list of the uncle headers. Each uncle header is decomposed like any
+//! other header, and the list is tied to them in the second phase: its RLC must be the concatenation of the RLCs of the
+//! list prefix and of each uncle header, which the keccak chip already computes for the uncle hashes.
+
+use axiom_eth::{
+    providers::get_block_rlp, rlp::builder::RlcThreadBuilder, EthChip, Field, Network,
+};
+use ethers_core::{
+    types::{Block, H256},
+    utils::rlp::RlpStream,
+};
+use halo2_base::{
+    gates::{GateInstructions, RangeInstructions},
+    utils::bit_length,
+    AssignedValue,
+    QuantumCell::Constant,
+};
+#[cfg(feature = "providers")]
+use {
+    crate::{error::Result, providers::get_uncle},
+    ethers_providers::Middleware,
+    tokio::runtime::Runtime,
+};
+
+use crate::{
+    containers::EthBlock,
+    scaffold::{header_rlp_max_bytes, AxiomChip},
+    schema::dummy_block,
+};
+
+/// The most uncles a block can have.
+pub const MAX_OMMERS: usize = 2;
+
+/// The uncles of a block, see [`AxiomChip::eth_getOmmers`].
+#[derive(Clone, Debug)]
+pub struct Ommers<F: Field> {
+    pub block: EthBlock<F>,
+    /// Always [`MAX_OMMERS`] headers, so the circuit layout does not depend on the number of uncles. Only the first
+    /// `num_ommers` are uncles of `block`, the rest are dummy headers.
+    pub ommers: Vec<EthBlock<F>>,
+    pub num_ommers: AssignedValue<F>,
+}
+
+/// The phase 0 witness of an ommers list, whose RLC is constrained in phase 1.
+#[derive(Clone, Debug)]
+pub(crate) struct OmmersTraceWitness<F: Field> {
+    /// The RLP list prefix, padded with zeros to 3 bytes.
+    prefix: Vec<AssignedValue<F>>,
+    prefix_len: AssignedValue<F>,
+    /// The keccak queries of the uncle headers, whose inputs are the uncle headers.
+    ommer_query_idxs: Vec<usize>,
+    /// The keccak query of the ommers list.
+    list_query_idx: usize,
+    num_ommers: AssignedValue<F>,
+}
+
+impl<F: Field> OmmersTraceWitness<F> {
+    /// Constrains the RLC of the ommers list to be the concatenation of its prefix and the uncle headers.
+    pub(crate) fn phase1(self, builder: &mut RlcThreadBuilder<F>, eth_chip: &EthChip<F>) {
+        let (ctx_gate, ctx_rlc) = builder.rlc_ctx_pair();
+        let gate = eth_chip.gate();
+        let rlc = eth_chip.rlc();
+        let var_len_rlcs = eth_chip.keccak_var_len_rlcs();
+        let (list, _) = &var_len_rlcs[self.list_query_idx];
+        rlc.load_rlc_cache((ctx_gate, ctx_rlc), gate, bit_length(list.max_len as u64));
+
+        let prefix = rlc.compute_rlc((ctx_gate, ctx_rlc), gate, self.prefix, self.prefix_len);
+        let mut fragments = vec![(prefix.rlc_val, prefix.len)];
+        for idx in self.ommer_query_idxs {
+            let (header, _) = &var_len_rlcs[idx];
+            fragments.push((header.rlc_val, header.len));
+        }
+        let num_fragments = gate.add(ctx_gate, self.num_ommers, Constant(F::one()));
+        rlc.constrain_rlc_concat(
+            ctx_gate,
+            gate,
+            fragments,
+            (list.rlc_val, list.len),
+            Some(num_fragments),
+        );
+    }
+}
+
+#[cfg(feature = "providers")]
+impl<F: Field> AxiomChip<F> {
+    /// Proves the header of block `block_number` together with its uncle headers, see [`AxiomChip::assign_ommers`].
+    pub fn eth_getOmmers<M: Middleware>(
+        &mut self,
+        provider: &M,
+        block_number: u32,
+    ) -> Result<Ommers<F>> {
+        Runtime::new()?.block_on(self.eth_getOmmers_async(provider, block_number))
+    }
+
+    /// Same as [`AxiomChip::eth_getOmmers`], for use from within an async context.
+    pub async fn eth_getOmmers_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        block_number: u32,
+    ) -> Result<Ommers<F>> {
+        let network = self.resolve_network(provider).await?;
+        let block = self.fetch_block(provider, network, block_number as u64).await?;
+        let mut ommers = vec![];
+        for index in 0..block.uncles.len() {
+            self.check_cancelled()?;
+            ommers.push(get_uncle(&self.rpc, provider, block_number as u64, index).await?);
+        }
+        self.record_block(&block);
+        self.record_uncles(block_number as u64, &ommers);
+        let header = self.assign_block(&block, network);
+        Ok(self.assign_ommers(header, &ommers, network))
+    }
+}
+
+impl<F: Field> AxiomChip<F> {
+    /// Decomposes the uncle headers `ommers` of `block` and constrains the keccak hash of their RLP list to be the
+    /// `ommers_hash` of `block`.
+    ///
+    /// The uncles are headers of blocks that are not in the chain, so they are only authenticated through `block`.
+    pub fn assign_ommers(
+        &mut self,
+        block: EthBlock<F>,
+        ommers: &[Block<H256>],
+        network: Network,
+    ) -> Ommers<F> {
+        assert!(ommers.len() <= MAX_OMMERS, "a block has at most {MAX_OMMERS} uncles");
+        let ommer_rlps: Vec<Vec<u8>> = ommers.iter().map(get_block_rlp).collect();
+        let mut list = RlpStream::new_list(ommers.len());
+        for rlp in &ommer_rlps {
+            list.append_raw(rlp, 1);
+        }
+        let list = list.out().to_vec();
+
+        let first_header = self.header_witness().len();
+        let dummy = get_block_rlp(&dummy_block());
+        let headers: Vec<_> = (0..MAX_OMMERS)
+            .map(|i| self.assign_block_header(ommer_rlps.get(i).unwrap_or(&dummy).clone(), network))
+            .collect();
+        let ommer_query_idxs: Vec<usize> = (first_header..first_header + MAX_OMMERS)
+            .map(|i| self.header_witness()[i].block_hash_query_idx)
+            .collect();
+        let header_lens: Vec<_> = (first_header..first_header + MAX_OMMERS)
+            .map(|i| self.header_witness()[i].rlp_witness.rlp_len)
+            .collect();
+
+        let range = self.range();
+        let gate = range.gate();
+        let mut ctx = self.ctx();
+        let num_ommers = ctx.load_witness(F::from(ommers.len() as u64));
+        range.check_less_than_safe(&mut ctx, num_ommers, MAX_OMMERS as u64 + 1);
+        let is_empty = gate.is_zero(&mut ctx, num_ommers);
+        let has_first = gate.not(&mut ctx, is_empty);
+        let has_second = gate.is_equal(&mut ctx, num_ommers, Constant(F::from(2)));
+        let first_len = gate.mul(&mut ctx, has_first, header_lens[0]);
+        let payload_len = gate.mul_add(&mut ctx, has_second, header_lens[1], first_len);
+
+        // An empty list is the single byte 0xc0. Uncle headers are longer than 55 bytes, so otherwise the prefix is
+        // 0xf9 followed by the payload length in two bytes.
+        let prefix_len = if ommers.is_empty() { 1 } else { 3 };
+        let mut prefix_bytes = list[..prefix_len].to_vec();
+        prefix_bytes.resize(3, 0);
+        let prefix = ctx.assign_witnesses(prefix_bytes.iter().map(|byte| F::from(*byte as u64)));
+        let first_byte =
+            gate.select(&mut ctx, Constant(F::from(0xf9)), Constant(F::from(0xc0)), has_first);
+        ctx.constrain_equal(&prefix[0], &first_byte);
+        let encoded_len = gate.mul_add(&mut ctx, prefix[1], Constant(F::from(256)), prefix[2]);
+        ctx.constrain_equal(&encoded_len, &payload_len);
+        let prefix_len =
+            gate.mul_add(&mut ctx, has_first, Constant(F::from(2)), Constant(F::one()));
+        let list_len = gate.add(&mut ctx, prefix_len, payload_len);
+
+        let max_len = 3 + MAX_OMMERS * header_rlp_max_bytes(network);
+        let mut padded = list;
+        padded.resize(max_len, 0);
+        let list_assigned = ctx.assign_witnesses(padded.iter().map(|byte| F::from(*byte as u64)));
+        let mut keccak = self.keccak.lock();
+        let list_query_idx =
+            keccak.keccak_var_len(&mut ctx, range, list_assigned, Some(padded), list_len, 1);
+        let list_hash = &keccak.var_len_queries[list_query_idx].output_assigned;
+        for (hash_byte, ommers_hash_byte) in list_hash.iter().zip(&block.ommers_hash.bytes) {
+            ctx.constrain_equal(hash_byte, ommers_hash_byte);
+        }
+        drop(keccak);
+        drop(ctx);
+
+        self.ommers_witness.push(OmmersTraceWitness {
+            prefix,
+            prefix_len,
+            ommer_query_idxs,
+            list_query_idx,
+            num_ommers,
+        });
+        Ommers { block, ommers: headers, num_ommers }
+    }
+}
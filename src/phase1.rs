@@ -0,0 +1,123 @@
+//! User constraints in the second phase of the circuit, after the challenge for random linear combinations is drawn.
+//!
+//! A random linear combination (RLC) of a byte string with the challenge as the base is, with overwhelming probability,
+//! a unique fingerprint of the string, so one RLC constraint can replace a constraint per byte. The challenge is only
+//! known in the second phase, so RLCs are computed in a [`Phase1Hook`] with an [`RlcContext`].
+//!
+//! New kinds of proofs, e.g. of receipts or of custom tries, implement [`AxiomQuery`] to take part in both phases like
+//! the built-in queries.
+
+use axiom_eth::{
+    rlp::{builder::RlcThreadBuilder, rlc::RlcTrace},
+    EthChip, Field,
+};
+use halo2_base::{
+    gates::GateInstructions, halo2_proofs::circuit::Value, utils::bit_length, AssignedValue,
+};
+use std::{fmt, sync::Arc};
+
+use crate::{containers::ByteString, scaffold::AxiomChip};
+
+/// A query added with [`AxiomChip::add_query`], which assigns its witness in the first phase and adds the constraints
+/// that use RLCs in the second phase, after the built-in queries.
+pub trait AxiomQuery<F: Field>: fmt::Debug + Send + Sync {
+    /// Assigns the first phase witness of the query with the chip, e.g. with [`AxiomChip::ctx`] and
+    /// [`AxiomChip::keccak`], and keeps what [`AxiomQuery::phase1`] needs.
+    fn phase0(&mut self, chip: &mut AxiomChip<F>);
+
+    /// Adds the second phase constraints of the query. The circuit is synthesized again each time it is measured, keyed
+    /// or proven, so this must assign the same cells each time, cloning the kept witness where `axiom-eth` takes it by
+    /// value.
+    fn phase1(&self, builder: &mut RlcThreadBuilder<F>, eth_chip: &EthChip<F>);
+}
+
+/// A user callback that adds constraints in the second phase, see
+/// [`AxiomChip::set_phase1_hook`](crate::scaffold::AxiomChip::set_phase1_hook). Its arguments create an
+/// [`RlcContext`].
+///
+/// The circuit is synthesized again each time it is measured, keyed or proven, so the callback is a `Fn` shared by the
+/// clones of a chip rather than a `FnOnce`.
+#[derive(Clone)]
+pub struct Phase1Hook<F: Field>(Arc<dyn Fn(&mut RlcThreadBuilder<F>, &EthChip<F>) + Send + Sync>);
+
+impl<F: Field> Phase1Hook<F> {
+    pub fn new(
+        hook: impl Fn(&mut RlcThreadBuilder<F>, &EthChip<F>) + Send + Sync + 'static,
+    ) -> Self {
+        Self(Arc::new(hook))
+    }
+
+    pub(crate) fn call(&self, builder: &mut RlcThreadBuilder<F>, eth_chip: &EthChip<F>) {
+        (self.0)(builder, eth_chip)
+    }
+}
+
+impl<F: Field> fmt::Debug for Phase1Hook<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Phase1Hook")
+    }
+}
+
+/// Computes and compares the RLCs of byte strings assigned in the first phase, from within a [`Phase1Hook`], whose
+/// arguments are the only way to create one.
+///
+/// The bytes of each string must be constrained to be bytes in the first phase, as those of the containers of queries
+/// are, and only the first `len` bytes of a string are part of its RLC.
+pub struct RlcContext<'a, 'chip, F: Field> {
+    builder: &'a mut RlcThreadBuilder<F>,
+    eth_chip: &'a EthChip<'chip, F>,
+}
+
+impl<'a, 'chip, F: Field> RlcContext<'a, 'chip, F> {
+    pub fn new(builder: &'a mut RlcThreadBuilder<F>, eth_chip: &'a EthChip<'chip, F>) -> Self {
+        Self { builder, eth_chip }
+    }
+
+    /// The challenge of the RLCs. It is a witness value for debugging only: constraints that depend on it must go through
+    /// the RLCs computed here.
+    pub fn gamma(&self) -> Value<F> {
+        *self.eth_chip.rlc().gamma()
+    }
+
+    /// The RLC of `string`.
+    pub fn rlc(&mut self, string: &ByteString<F>) -> RlcTrace<F> {
+        let (ctx_gate, ctx_rlc) = self.builder.rlc_ctx_pair();
+        let bytes = string.bytes.iter().copied();
+        self.eth_chip.rlc().compute_rlc(
+            (ctx_gate, ctx_rlc),
+            self.eth_chip.gate(),
+            bytes,
+            string.len,
+        )
+    }
+
+    /// Whether `a` and `b` are the same string, i.e. have the same length and the same RLC.
+    pub fn is_equal(&mut self, a: &ByteString<F>, b: &ByteString<F>) -> AssignedValue<F> {
+        let (a, b) = (self.rlc(a), self.rlc(b));
+        let gate = self.eth_chip.gate();
+        let (ctx_gate, _) = self.builder.rlc_ctx_pair();
+        let same_len = gate.is_equal(ctx_gate, a.len, b.len);
+        let same_rlc = gate.is_equal(ctx_gate, a.rlc_val, b.rlc_val);
+        gate.and(ctx_gate, same_len, same_rlc)
+    }
+
+    /// Constrains `a` and `b` to be the same string.
+    pub fn constrain_equal(&mut self, a: &ByteString<F>, b: &ByteString<F>) {
+        let (a, b) = (self.rlc(a), self.rlc(b));
+        let (ctx_gate, _) = self.builder.rlc_ctx_pair();
+        ctx_gate.constrain_equal(&a.len, &b.len);
+        ctx_gate.constrain_equal(&a.rlc_val, &b.rlc_val);
+    }
+
+    /// Constrains `whole` to be the concatenation of `parts`.
+    pub fn constrain_concat(&mut self, parts: &[ByteString<F>], whole: &ByteString<F>) {
+        let parts: Vec<_> =
+            parts.iter().map(|part| self.rlc(part)).map(|rlc| (rlc.rlc_val, rlc.len)).collect();
+        let whole = self.rlc(whole);
+        let gate = self.eth_chip.gate();
+        let rlc = self.eth_chip.rlc();
+        let (ctx_gate, ctx_rlc) = self.builder.rlc_ctx_pair();
+        rlc.load_rlc_cache((ctx_gate, ctx_rlc), gate, bit_length(whole.max_len as u64));
+        rlc.constrain_rlc_concat(ctx_gate, gate, parts, (whole.rlc_val, whole.len), None);
+    }
+}
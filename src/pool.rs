@@ -0,0 +1,130 @@
+use std::{
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use halo2_base::halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::ProvingKey,
+    poly::kzg::commitment::ParamsKZG,
+};
+use parking_lot::Mutex;
+
+use crate::{
+    error::{AxiomError, Result},
+    proof::ProofArtifacts,
+    scaffold::AxiomChip,
+    srs::SrsManager,
+};
+
+/// A circuit whose queries have all been assigned, ready to be proven by a [`ProverPool`].
+pub enum ProofJob {
+    /// Generates keys for the circuit, then proves it.
+    Keygen(AxiomChip<Fr>),
+    /// Proves the circuit with existing keys. The chip must have been created with
+    /// [`AxiomChip::prover`] from the pinning `pk` was generated with.
+    WithKey { chip: AxiomChip<Fr>, params: Arc<ParamsKZG<Bn256>>, pk: Arc<ProvingKey<G1Affine>> },
+}
+
+/// A stage of a [`ProofJob`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofStage {
+    Keygen,
+    Proving,
+}
+
+/// How long each stage of a [`ProofJob`] took. Stages that did not run, or did not finish, are `None`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StageTimings {
+    pub keygen: Option<Duration>,
+    pub proving: Option<Duration>,
+}
+
+/// Callbacks for the progress of the jobs run by a [`ProverPool`]. Jobs are identified by their index in the list
+/// passed to [`ProverPool::run`]. The callbacks are called from the worker threads, so they must be quick.
+pub trait ProofObserver: Sync {
+    /// Called when job `job` starts `stage`.
+    fn on_stage(&self, _job: usize, _stage: ProofStage) {}
+    /// Called when job `job` has produced a valid proof.
+    fn on_complete(&self, _job: usize, _artifacts: &ProofArtifacts, _timings: &StageTimings) {}
+    /// Called when job `job` has failed.
+    fn on_failure(&self, _job: usize, _error: &AxiomError, _timings: &StageTimings) {}
+}
+
+/// Ignores all progress.
+impl ProofObserver for () {}
+
+/// Proves many circuits, at most `max_concurrency` at a time.
+///
+/// Each proof already uses all cores for its MSMs and FFTs, so running more than a few at a time mostly trades memory
+/// for keeping the cores busy during the single threaded parts of proving.
+///
+/// Circuits are configured from a process-global environment variable, so keygen and proving hold
+/// [`lock_config_params`](crate::keys::lock_config_params): the keygen and proving of different jobs never overlap,
+/// whatever `max_concurrency` is, until the configuration is passed to circuits explicitly.
+#[derive(Clone, Debug)]
+pub struct ProverPool {
+    pub max_concurrency: usize,
+    /// Reads the KZG parameters of [`ProofJob::Keygen`] jobs.
+    pub srs: SrsManager,
+}
+
+impl ProverPool {
+    pub fn new(max_concurrency: usize, srs: SrsManager) -> Self {
+        assert!(max_concurrency > 0, "max_concurrency must be positive");
+        Self { max_concurrency, srs }
+    }
+
+    /// Runs all `jobs`, reporting their progress to `observer`, and returns their results in the same order.
+    pub fn run(
+        &self,
+        jobs: Vec<ProofJob>,
+        observer: &impl ProofObserver,
+    ) -> Vec<Result<ProofArtifacts>> {
+        let num_jobs = jobs.len();
+        let queue = Mutex::new(jobs.into_iter().enumerate());
+        let results = Mutex::new((0..num_jobs).map(|_| None).collect::<Vec<_>>());
+        thread::scope(|s| {
+            for _ in 0..self.max_concurrency.min(num_jobs) {
+                s.spawn(|| loop {
+                    let Some((index, job)) = queue.lock().next() else { break };
+                    let mut timings = StageTimings::default();
+                    let result = self.run_job(index, job, observer, &mut timings);
+                    match &result {
+                        Ok(artifacts) => observer.on_complete(index, artifacts, &timings),
+                        Err(err) => observer.on_failure(index, err, &timings),
+                    }
+                    results.lock()[index] = Some(result);
+                });
+            }
+        });
+        results.into_inner().into_iter().map(|result| result.unwrap()).collect()
+    }
+
+    fn run_job(
+        &self,
+        index: usize,
+        job: ProofJob,
+        observer: &impl ProofObserver,
+        timings: &mut StageTimings,
+    ) -> Result<ProofArtifacts> {
+        let (chip, params, pk) = match job {
+            ProofJob::Keygen(mut chip) => {
+                observer.on_stage(index, ProofStage::Keygen);
+                let start = Instant::now();
+                let (pk, pinning) = chip.clone().gen_keys(&self.srs)?;
+                let params = self.srs.params(pinning.params.degree)?;
+                chip.set_pinning(pinning);
+                timings.keygen = Some(start.elapsed());
+                (chip, Arc::new(params), Arc::new(pk))
+            }
+            ProofJob::WithKey { chip, params, pk } => (chip, params, pk),
+        };
+        observer.on_stage(index, ProofStage::Proving);
+        let start = Instant::now();
+        let artifacts = chip.prove_with_pk(&params, &pk)?;
+        timings.proving = Some(start.elapsed());
+        Ok(artifacts)
+    }
+}
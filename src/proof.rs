@@ -0,0 +1,263 @@
+use ark_std::{end_timer, start_timer};
+use halo2_base::halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::{create_proof, verify_proof, Circuit, ProvingKey, VerifyingKey},
+    poly::{
+        commitment::{Prover, Verifier},
+        kzg::{
+            commitment::{KZGCommitmentScheme, ParamsKZG},
+            multiopen::{ProverGWC, ProverSHPLONK, VerifierGWC, VerifierSHPLONK},
+            strategy::SingleStrategy,
+        },
+    },
+    transcript::{
+        Blake2bRead, Blake2bWrite, Challenge255, EncodedChallenge, TranscriptReadBuffer,
+        TranscriptWriterBuffer,
+    },
+};
+use rand_chacha::ChaCha20Rng;
+use rand_core::{OsRng, RngCore, SeedableRng};
+use snark_verifier_sdk::{
+    halo2::PoseidonTranscript,
+    snark_verifier::{loader::native::NativeLoader, system::halo2::transcript::evm::EvmTranscript},
+};
+
+use crate::{
+    aggregation::check_accumulator,
+    error::{AxiomError, Result},
+};
+
+/// The hash used for the Fiat-Shamir transcript of a proof.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TranscriptKind {
+    /// Fastest to prove and verify natively.
+    #[default]
+    Blake2b,
+    /// Cheap to verify inside another circuit. Use this if the proof will be aggregated or recursively verified.
+    Poseidon,
+    /// Cheap to verify in the EVM. Use this if the proof will be verified on-chain by the verifier from
+    /// [`AxiomChip::gen_evm_verifier`](crate::scaffold::AxiomChip::gen_evm_verifier).
+    Keccak,
+}
+
+/// The KZG multi-open scheme used to batch the polynomial openings of a proof.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MultiOpenScheme {
+    /// The scheme of [BDFG21](https://eprint.iacr.org/2020/081), which has the smaller proofs.
+    #[default]
+    Shplonk,
+    /// The scheme of [GWC19](https://eprint.iacr.org/2019/953), i.e., the one from the original PLONK paper.
+    Gwc,
+}
+
+/// Options for how [`AxiomChip::prove`](crate::scaffold::AxiomChip::prove) creates proofs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProvingOptions {
+    pub transcript: TranscriptKind,
+    pub multi_open: MultiOpenScheme,
+    /// If set, proving fails before generating keys when the
+    /// [estimated peak memory](crate::scaffold::AxiomChip::estimated_peak_memory) in bytes exceeds this,
+    /// instead of running out of memory partway through.
+    pub max_memory: Option<usize>,
+    /// If set, the blinding factors of proofs are drawn from a ChaCha20 RNG seeded with this instead of the OS RNG,
+    /// so the same circuit and keys always produce byte-identical proofs, e.g. to snapshot them in tests.
+    ///
+    /// A seeded proof is not zero-knowledge to anyone who knows the seed, so never set this in production.
+    pub rng_seed: Option<u64>,
+}
+
+/// Everything needed to ship a proof produced by [`AxiomChip::prove`](crate::scaffold::AxiomChip::prove)
+/// to a verifier.
+#[derive(Clone, Debug)]
+pub struct ProofArtifacts {
+    /// The serialized proof, i.e., the finalized transcript bytes.
+    pub proof: Vec<u8>,
+    /// The public instance values, in the order they were exposed with `expose_public`.
+    pub instances: Vec<Fr>,
+    /// The verifying key of the circuit the proof was created for.
+    pub vk: VerifyingKey<G1Affine>,
+    /// The transcript the proof was created with.
+    pub transcript: TranscriptKind,
+    /// The multi-open scheme the proof was created with.
+    pub multi_open: MultiOpenScheme,
+    /// Whether the first 12 public instances are the KZG accumulator of verified snarks, as in an
+    /// [aggregate proof](crate::aggregation::prove_aggregation) or a circuit calling
+    /// [`AxiomChip::verify_snarks`](crate::scaffold::AxiomChip::verify_snarks).
+    pub has_accumulator: bool,
+}
+
+impl ProofArtifacts {
+    /// Verifies the proof against its own verifying key and public instances. If the proof has an accumulator, also
+    /// checks it, so that a valid proof means the snarks it verifies are valid too.
+    pub fn verify(&self, params: &ParamsKZG<Bn256>) -> bool {
+        verify(params, &self.vk, &self.proof, &self.instances, self.transcript, self.multi_open)
+            && (!self.has_accumulator || check_accumulator(params, &self.instances))
+    }
+}
+
+/// Verifies a proof produced by [`AxiomChip::prove`](crate::scaffold::AxiomChip::prove) without needing the circuit.
+///
+/// `params` must be the same KZG parameters the proof was created with (only the verifier part is used),
+/// and `transcript` and `multi_open` the options it was created with.
+/// Returns `true` if the proof is valid for the given public `instances`.
+pub fn verify(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    proof: &[u8],
+    instances: &[Fr],
+    transcript: TranscriptKind,
+    multi_open: MultiOpenScheme,
+) -> bool {
+    type Blake2b<'a> = Blake2bRead<&'a [u8], G1Affine, Challenge255<G1Affine>>;
+    type Poseidon<'a> = PoseidonTranscript<NativeLoader, &'a [u8]>;
+    type Keccak<'a> = EvmTranscript<G1Affine, NativeLoader, &'a [u8], Vec<u8>>;
+    use MultiOpenScheme::*;
+    use TranscriptKind as T;
+    match (transcript, multi_open) {
+        (T::Blake2b, Shplonk) => {
+            verify_with::<VerifierSHPLONK<'_, Bn256>, _, Blake2b>(params, vk, proof, instances)
+        }
+        (T::Blake2b, Gwc) => {
+            verify_with::<VerifierGWC<'_, Bn256>, _, Blake2b>(params, vk, proof, instances)
+        }
+        (T::Poseidon, Shplonk) => {
+            verify_with::<VerifierSHPLONK<'_, Bn256>, _, Poseidon>(params, vk, proof, instances)
+        }
+        (T::Poseidon, Gwc) => {
+            verify_with::<VerifierGWC<'_, Bn256>, _, Poseidon>(params, vk, proof, instances)
+        }
+        (T::Keccak, Shplonk) => {
+            verify_with::<VerifierSHPLONK<'_, Bn256>, _, Keccak>(params, vk, proof, instances)
+        }
+        (T::Keccak, Gwc) => {
+            verify_with::<VerifierGWC<'_, Bn256>, _, Keccak>(params, vk, proof, instances)
+        }
+    }
+}
+
+fn verify_with<'params, 'proof, V, E, T>(
+    params: &'params ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    proof: &'proof [u8],
+    instances: &[Fr],
+) -> bool
+where
+    V: Verifier<'params, KZGCommitmentScheme<Bn256>>,
+    E: EncodedChallenge<G1Affine>,
+    T: TranscriptReadBuffer<&'proof [u8], G1Affine, E>,
+{
+    let strategy = SingleStrategy::new(params);
+    let mut transcript = T::init(proof);
+    verify_proof::<KZGCommitmentScheme<Bn256>, V, E, T, SingleStrategy<'_, Bn256>>(
+        params,
+        vk,
+        strategy,
+        &[&[instances]],
+        &mut transcript,
+    )
+    .is_ok()
+}
+
+/// Creates a proof for `circuit` with public `instance` according to `options` and verifies it, including its
+/// accumulator if `has_accumulator`.
+pub(crate) fn prove_circuit(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: impl Circuit<Fr>,
+    instance: Vec<Fr>,
+    options: ProvingOptions,
+    has_accumulator: bool,
+) -> Result<ProofArtifacts> {
+    let pf_time = start_timer!(|| format!(
+        "Creating KZG proof using {:?} multi-open scheme",
+        options.multi_open
+    ));
+    let proof = match options.rng_seed {
+        Some(seed) => create_proof_for(
+            params,
+            pk,
+            circuit,
+            &instance,
+            options,
+            ChaCha20Rng::seed_from_u64(seed),
+        ),
+        None => create_proof_for(params, pk, circuit, &instance, options, OsRng),
+    }?;
+    end_timer!(pf_time);
+
+    let artifacts = ProofArtifacts {
+        proof,
+        instances: instance,
+        vk: pk.get_vk().clone(),
+        transcript: options.transcript,
+        multi_open: options.multi_open,
+        has_accumulator,
+    };
+    let verify_time = start_timer!(|| "verify");
+    if !artifacts.verify(params) {
+        return Err(AxiomError::InvalidProof);
+    }
+    end_timer!(verify_time);
+    Ok(artifacts)
+}
+
+/// Creates a proof with the transcript and multi-open scheme of `options`, drawing blinding factors from `rng`.
+fn create_proof_for(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: impl Circuit<Fr>,
+    instance: &[Fr],
+    options: ProvingOptions,
+    rng: impl RngCore,
+) -> Result<Vec<u8>> {
+    type Blake2b = Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>;
+    type Poseidon = PoseidonTranscript<NativeLoader, Vec<u8>>;
+    type Keccak = EvmTranscript<G1Affine, NativeLoader, Vec<u8>, Vec<u8>>;
+    use MultiOpenScheme::*;
+    use TranscriptKind as T;
+    match (options.transcript, options.multi_open) {
+        (T::Blake2b, Shplonk) => create_proof_with::<ProverSHPLONK<'_, Bn256>, _, Blake2b, _>(
+            params, pk, circuit, instance, rng,
+        ),
+        (T::Blake2b, Gwc) => create_proof_with::<ProverGWC<'_, Bn256>, _, Blake2b, _>(
+            params, pk, circuit, instance, rng,
+        ),
+        (T::Poseidon, Shplonk) => create_proof_with::<ProverSHPLONK<'_, Bn256>, _, Poseidon, _>(
+            params, pk, circuit, instance, rng,
+        ),
+        (T::Poseidon, Gwc) => create_proof_with::<ProverGWC<'_, Bn256>, _, Poseidon, _>(
+            params, pk, circuit, instance, rng,
+        ),
+        (T::Keccak, Shplonk) => create_proof_with::<ProverSHPLONK<'_, Bn256>, _, Keccak, _>(
+            params, pk, circuit, instance, rng,
+        ),
+        (T::Keccak, Gwc) => create_proof_with::<ProverGWC<'_, Bn256>, _, Keccak, _>(
+            params, pk, circuit, instance, rng,
+        ),
+    }
+}
+
+fn create_proof_with<'params, P, E, T, R>(
+    params: &'params ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: impl Circuit<Fr>,
+    instance: &[Fr],
+    rng: R,
+) -> Result<Vec<u8>>
+where
+    P: Prover<'params, KZGCommitmentScheme<Bn256>>,
+    E: EncodedChallenge<G1Affine>,
+    T: TranscriptWriterBuffer<Vec<u8>, G1Affine, E>,
+    R: RngCore,
+{
+    let mut transcript = T::init(vec![]);
+    create_proof::<KZGCommitmentScheme<Bn256>, P, E, _, T, _>(
+        params,
+        pk,
+        &[circuit],
+        &[&[instance]],
+        rng,
+        &mut transcript,
+    )?;
+    Ok(transcript.finalize())
+}
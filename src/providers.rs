@@ -0,0 +1,257 @@
+use axiom_eth::{
+    mpt::MPTFixedKeyInput,
+    providers::{get_acct_list, get_acct_rlp, is_assigned_slot},
+    storage::{EthBlockStorageInput, EthStorageInput},
+    Network,
+};
+use ethers_core::{
+    types::{Address, Block, EIP1186ProofResponse, H256, U256},
+    utils::{keccak256, rlp},
+};
+use std::time::Duration;
+#[cfg(feature = "providers")]
+use {
+    ethers_providers::{Middleware, MiddlewareError, ProviderError},
+    std::{
+        future::Future,
+        sync::{Arc, Mutex},
+    },
+    tokio::time::{sleep, Instant},
+};
+
+use crate::{
+    error::{AxiomError, Result},
+    schema::{ACCOUNT_RLP_MAX_BYTES, STORAGE_VALUE_RLP_MAX_BYTES},
+};
+
+/// How the calls made to a provider are retried and rate limited.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RpcPolicy {
+    /// The number of times a failed call is retried before giving up.
+    pub max_retries: u32,
+    /// The wait before the first retry, doubled on each following retry.
+    pub initial_backoff: Duration,
+    /// The maximum wait between retries.
+    pub max_backoff: Duration,
+    /// If set, at most this many calls are made per second. Zero is the same as no limit.
+    pub requests_per_second: Option<u32>,
+    /// The maximum number of calls in flight at once when fetching the inputs of many queries together.
+    pub max_concurrency: usize,
+}
+
+impl Default for RpcPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            requests_per_second: None,
+            max_concurrency: 8,
+        }
+    }
+}
+
+/// Makes provider calls according to an [`RpcPolicy`]. Clones share the same rate limit.
+#[cfg(feature = "providers")]
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RpcClient {
+    pub(crate) policy: RpcPolicy,
+    /// The earliest time the next call may be made under the rate limit.
+    next_call: Arc<Mutex<Option<Instant>>>,
+}
+
+#[cfg(feature = "providers")]
+impl RpcClient {
+    /// Makes the call `f`, retrying with exponential backoff if it fails with a transient error, see [`is_transient`].
+    pub(crate) async fn call<T, E, Fut>(&self, mut f: impl FnMut() -> Fut) -> Result<T>
+    where
+        E: MiddlewareError,
+        Fut: Future<Output = std::result::Result<T, E>>,
+    {
+        let mut backoff = self.policy.initial_backoff;
+        let mut retries = 0;
+        loop {
+            self.throttle().await;
+            match f().await {
+                Ok(value) => return Ok(value),
+                Err(err) if retries < self.policy.max_retries && is_transient(&err) => {
+                    log::warn!("RPC call failed, retrying in {backoff:?}: {err}");
+                    sleep(backoff).await;
+                    backoff = (backoff * 2).min(self.policy.max_backoff);
+                    retries += 1;
+                }
+                Err(err) => return Err(middleware_error(err)),
+            }
+        }
+    }
+
+    /// Waits until the rate limit allows another call.
+    async fn throttle(&self) {
+        let Some(requests_per_second) = self.policy.requests_per_second.filter(|&rps| rps > 0)
+        else {
+            return;
+        };
+        let now = Instant::now();
+        // reserve the next slot before waiting, so that concurrent calls are spaced out
+        let slot = {
+            let mut next_call = self.next_call.lock().unwrap();
+            let slot = next_call.map_or(now, |next_call| next_call.max(now));
+            *next_call = Some(slot + Duration::from_secs(1) / requests_per_second);
+            slot
+        };
+        if slot > now {
+            sleep(slot - now).await;
+        }
+    }
+}
+
+/// The JSON-RPC error codes of rate limiting: HTTP's 429 and the `limit exceeded` code of EIP-1474.
+#[cfg(feature = "providers")]
+const RATE_LIMIT_ERROR_CODES: [i64; 2] = [429, -32005];
+
+/// Whether a failed call may succeed when retried. A JSON-RPC error response is an answer to the request, e.g. invalid
+/// params or a pruned block, and fails the same way again, unless it is rate limiting or a 5xx server error. Any other
+/// failure is a transport error, or an HTTP error page such as a 429 or 5xx that is not a JSON-RPC response at all.
+#[cfg(feature = "providers")]
+fn is_transient(err: &impl MiddlewareError) -> bool {
+    err.as_error_response().map_or(true, |response| {
+        RATE_LIMIT_ERROR_CODES.contains(&response.code) || (500..600).contains(&response.code)
+    })
+}
+
+/// Returns the network with chain ID `chain_id`, if it is supported.
+pub(crate) fn network_from_chain_id(chain_id: U256) -> Result<Network> {
+    match chain_id {
+        U256([1, 0, 0, 0]) => Ok(Network::Mainnet),
+        U256([5, 0, 0, 0]) => Ok(Network::Goerli),
+        _ => Err(AxiomError::UnsupportedNetwork(chain_id)),
+    }
+}
+
+pub(crate) fn chain_id(network: Network) -> u64 {
+    match network {
+        Network::Mainnet => 1,
+        Network::Goerli => 5,
+    }
+}
+
+/// Converts an error of any [`Middleware`] into an [`AxiomError`].
+#[cfg(feature = "providers")]
+pub(crate) fn middleware_error(err: impl std::error::Error) -> AxiomError {
+    AxiomError::Provider(ProviderError::CustomError(err.to_string()))
+}
+
+/// Fetches the chain ID of `provider`.
+#[cfg(feature = "providers")]
+pub(crate) async fn get_chain_id<M: Middleware>(rpc: &RpcClient, provider: &M) -> Result<U256> {
+    rpc.call(|| provider.get_chainid()).await
+}
+
+/// Fetches the number of the latest block.
+#[cfg(feature = "providers")]
+pub(crate) async fn get_block_number<M: Middleware>(rpc: &RpcClient, provider: &M) -> Result<u64> {
+    Ok(rpc.call(|| provider.get_block_number()).await?.as_u64())
+}
+
+/// Fetches block `block_number`.
+#[cfg(feature = "providers")]
+pub(crate) async fn get_block<M: Middleware>(
+    rpc: &RpcClient,
+    provider: &M,
+    block_number: u64,
+) -> Result<Block<H256>> {
+    rpc.call(|| provider.get_block(block_number))
+        .await?
+        .ok_or(AxiomError::BlockNotFound(block_number))
+}
+
+/// Fetches uncle `index` of block `block_number`.
+#[cfg(feature = "providers")]
+pub(crate) async fn get_uncle<M: Middleware>(
+    rpc: &RpcClient,
+    provider: &M,
+    block_number: u64,
+    index: usize,
+) -> Result<Block<H256>> {
+    rpc.call(|| provider.get_uncle(block_number, (index as u64).into()))
+        .await?
+        .ok_or(AxiomError::BlockNotFound(block_number))
+}
+
+/// Fetches the value of `slot` of the contract at `address` at block `block_number`.
+#[cfg(feature = "providers")]
+pub(crate) async fn get_storage_at<M: Middleware>(
+    rpc: &RpcClient,
+    provider: &M,
+    block_number: u64,
+    address: Address,
+    slot: H256,
+) -> Result<H256> {
+    rpc.call(|| provider.get_storage_at(address, slot, Some(block_number.into()))).await
+}
+
+/// Fetches the account proof of `address` and the storage proofs of `slots` at block `block_number`.
+#[cfg(feature = "providers")]
+pub(crate) async fn get_proof<M: Middleware>(
+    rpc: &RpcClient,
+    provider: &M,
+    block_number: u64,
+    address: Address,
+    slots: &[H256],
+) -> Result<EIP1186ProofResponse> {
+    rpc.call(|| provider.get_proof(address, slots.to_vec(), Some(block_number.into()))).await
+}
+
+/// Converts an `eth_getProof` response against `block`, whose RLP encoded header is `block_header`, into the input of an
+/// account and storage proof.
+///
+/// Returns [`AxiomError::InvalidInput`] if `block` has no hash or no number, or if its number does not fit in 32 bits.
+pub(crate) fn block_storage_input(
+    block: Block<H256>,
+    block_header: Vec<u8>,
+    proof: EIP1186ProofResponse,
+    acct_pf_max_depth: usize,
+    storage_pf_max_depth: usize,
+) -> Result<EthBlockStorageInput> {
+    let block_hash =
+        block.hash.ok_or_else(|| AxiomError::InvalidInput("block has no hash".to_string()))?;
+    let block_number =
+        block.number.ok_or_else(|| AxiomError::InvalidInput("block has no number".to_string()))?;
+    let block_number = u32::try_from(block_number.as_u64()).map_err(|_| {
+        AxiomError::InvalidInput(format!("block number {block_number} does not fit in 32 bits"))
+    })?;
+    let acct_key = H256(keccak256(proof.address));
+    let acct_pf = MPTFixedKeyInput {
+        path: acct_key,
+        value: get_acct_rlp(&proof),
+        root_hash: block.state_root,
+        proof: proof.account_proof.iter().map(|node| node.to_vec()).collect(),
+        value_max_byte_len: ACCOUNT_RLP_MAX_BYTES,
+        max_depth: acct_pf_max_depth,
+        slot_is_empty: !is_assigned_slot(&acct_key, &proof.account_proof),
+    };
+    let storage_pfs = proof
+        .storage_proof
+        .iter()
+        .map(|storage_pf| {
+            let path = H256(keccak256(storage_pf.key));
+            let pf = MPTFixedKeyInput {
+                path,
+                value: rlp::encode(&storage_pf.value).to_vec(),
+                root_hash: proof.storage_hash,
+                proof: storage_pf.proof.iter().map(|node| node.to_vec()).collect(),
+                value_max_byte_len: STORAGE_VALUE_RLP_MAX_BYTES,
+                max_depth: storage_pf_max_depth,
+                slot_is_empty: !is_assigned_slot(&path, &storage_pf.proof),
+            };
+            (storage_pf.key, storage_pf.value, pf)
+        })
+        .collect();
+    let storage = EthStorageInput {
+        addr: proof.address,
+        acct_state: get_acct_list(&proof),
+        acct_pf,
+        storage_pfs,
+    };
+    Ok(EthBlockStorageInput { block_number, block_hash, block_header, block, storage })
+}
@@ -0,0 +1,116 @@
+//! Import of snarkjs `.ptau` files, e.g., from the Perpetual Powers of Tau ceremony.
+//!
+//! The file starts with the magic `ptau`, a version and the number of sections, followed by sections of the form
+//! `(section_type: u32, section_len: u64, data)`. All integers are little endian and field elements are stored
+//! in little endian Montgomery form, which is also the raw in-memory representation used by `halo2curves`.
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use ethers_core::utils::hex;
+use halo2_base::halo2_proofs::{
+    arithmetic::g_to_lagrange,
+    halo2curves::{
+        bn256::{Bn256, Fq, G1Affine, G2Affine},
+        group::{ff::PrimeField, prime::PrimeCurveAffine},
+        serde::SerdeObject,
+    },
+    helpers::SerdeCurveAffine,
+    poly::kzg::commitment::ParamsKZG,
+    SerdeFormat,
+};
+
+const SECTION_HEADER: u32 = 1;
+const SECTION_TAU_G1: u32 = 2;
+const SECTION_TAU_G2: u32 = 3;
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_point<C: SerdeObject>(reader: &mut impl Read, len: usize) -> io::Result<C> {
+    let mut buf = vec![0u8; len];
+    reader.read_exact(&mut buf)?;
+    C::from_raw_bytes(&buf).ok_or_else(|| invalid_data("invalid curve point in ptau file"))
+}
+
+/// Returns the byte offset of the data of each section, by section type.
+fn read_sections<R: Read + Seek>(reader: &mut R) -> io::Result<HashMap<u32, u64>> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != b"ptau" {
+        return Err(invalid_data("not a ptau file"));
+    }
+    let _version = read_u32(reader)?;
+    let num_sections = read_u32(reader)?;
+    let mut sections = HashMap::new();
+    for _ in 0..num_sections {
+        let section_type = read_u32(reader)?;
+        let section_len = read_u64(reader)?;
+        let offset = reader.stream_position()?;
+        sections.insert(section_type, offset);
+        reader.seek(SeekFrom::Start(offset + section_len))?;
+    }
+    Ok(sections)
+}
+
+/// Reads KZG parameters for degree `k` from a snarkjs `.ptau` file of power at least `k`.
+pub fn read_ptau(path: impl AsRef<Path>, k: u32) -> io::Result<ParamsKZG<Bn256>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let sections = read_sections(&mut reader)?;
+    let seek_section = |reader: &mut BufReader<File>, section_type: u32| {
+        let offset = sections
+            .get(&section_type)
+            .ok_or_else(|| invalid_data(format!("ptau file is missing section {section_type}")))?;
+        reader.seek(SeekFrom::Start(*offset))
+    };
+
+    seek_section(&mut reader, SECTION_HEADER)?;
+    let n8 = read_u32(&mut reader)? as usize;
+    let mut q = vec![0u8; n8];
+    reader.read_exact(&mut q)?;
+    q.reverse();
+    if format!("0x{}", hex::encode(&q)) != Fq::MODULUS {
+        return Err(invalid_data("ptau file is not over the BN254 curve"));
+    }
+    let power = read_u32(&mut reader)?;
+    if k > power {
+        return Err(invalid_data(format!(
+            "ptau file has power {power}, which is less than k = {k}"
+        )));
+    }
+
+    seek_section(&mut reader, SECTION_TAU_G1)?;
+    let g = (0..1usize << k)
+        .map(|_| read_point::<G1Affine>(&mut reader, 2 * n8))
+        .collect::<io::Result<Vec<_>>>()?;
+    seek_section(&mut reader, SECTION_TAU_G2)?;
+    let g2 = read_point::<G2Affine>(&mut reader, 4 * n8)?;
+    let s_g2 = read_point::<G2Affine>(&mut reader, 4 * n8)?;
+    let g_lagrange = g_to_lagrange(g.iter().map(|p| p.to_curve()).collect(), k);
+
+    // `ParamsKZG` cannot be constructed from its parts, so we go through its serialization format
+    let mut buf = Vec::with_capacity(4 + (2 << k) * 2 * n8 + 8 * n8);
+    buf.extend_from_slice(&k.to_le_bytes());
+    for p in g.iter().chain(g_lagrange.iter()) {
+        p.write(&mut buf, SerdeFormat::RawBytes)?;
+    }
+    g2.write(&mut buf, SerdeFormat::RawBytes)?;
+    s_g2.write(&mut buf, SerdeFormat::RawBytes)?;
+    ParamsKZG::read_custom(&mut &buf[..], SerdeFormat::RawBytes)
+}
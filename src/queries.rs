@@ -0,0 +1,694 @@
+//! Queries composed of several basic queries, together with the constraints that tie them together.
+
+use axiom_eth::{storage::EIP1186ResponseDigest, Field};
+use halo2_base::{
+    gates::{GateInstructions, RangeInstructions},
+    utils::ScalarField,
+    AssignedValue, Context,
+    QuantumCell::Constant,
+};
+#[cfg(feature = "providers")]
+use {
+    crate::{
+        containers::{ByteString, EthAccountStorage},
+        error::{AxiomError, Result},
+    },
+    axiom_eth::Network,
+    ethers_core::types::{Address, H256},
+    ethers_providers::Middleware,
+    tokio::runtime::Runtime,
+};
+
+use crate::{
+    containers::{EthAccount, EthBlock, H256Assigned},
+    scaffold::AxiomChip,
+};
+
+/// The number of bits of the `timestamp` field of a block header.
+const TIMESTAMP_BITS: usize = 32;
+/// The number of bits of the `gas_used` field of a block header.
+const GAS_USED_BITS: usize = 32;
+/// The number of bytes of the `basefee` field of a block header.
+const BASEFEE_BYTES: usize = 6;
+/// The number of bits of a block number.
+const BLOCK_NUMBER_BITS: usize = 32;
+
+/// The values of one storage slot of one contract at several blocks, see [`AxiomChip::eth_getStorageSeries`].
+#[derive(Clone, Debug)]
+pub struct StorageSeries<F: Field> {
+    pub address: AssignedValue<F>,
+    /// The slot as hi-lo 128 bit halves.
+    pub slot: [AssignedValue<F>; 2],
+    /// The block number of each point of the series, in the order of the queried blocks.
+    pub block_numbers: Vec<AssignedValue<F>>,
+    /// The block hash of each point of the series as hi-lo 128 bit halves.
+    pub block_hashes: Vec<(AssignedValue<F>, AssignedValue<F>)>,
+    /// The value of the slot at each block as hi-lo 128 bit halves, zero where the slot does not exist.
+    pub values: Vec<[AssignedValue<F>; 2]>,
+}
+
+/// The block at a timestamp, see [`AxiomChip::eth_getBlockAtTimestamp`].
+#[derive(Clone, Debug)]
+pub struct BlockAtTimestamp<F: Field> {
+    /// The target timestamp, as a private witness.
+    pub timestamp: AssignedValue<F>,
+    /// The last block with a timestamp at most `timestamp`.
+    pub block: EthBlock<F>,
+    /// The block after `block`, whose timestamp is greater than `timestamp`.
+    pub next_block: EthBlock<F>,
+    /// The number of `block`.
+    pub block_number: AssignedValue<F>,
+}
+
+/// The gas used by a range of consecutive blocks, see [`AxiomChip::eth_getGasStats`].
+#[derive(Clone, Debug)]
+pub struct GasStats<F: Field> {
+    /// The headers of the blocks, in order.
+    pub blocks: Vec<EthBlock<F>>,
+    /// The total gas used by all blocks.
+    pub sum: AssignedValue<F>,
+    /// The most gas used by any block.
+    pub max: AssignedValue<F>,
+}
+
+/// How [`AxiomChip::base_fee_history`] makes the base fees public.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HistoryOutput {
+    /// Exposes each base fee.
+    #[default]
+    Values,
+    /// Exposes the keccak hash of the base fees, each as a 32 byte big endian word, as hi-lo 128 bit halves, i.e.,
+    /// `keccak256(abi.encodePacked(baseFees))` with `uint256[] baseFees`. This keeps the number of public instances
+    /// constant for long windows, and a contract can recompute it from the base fees.
+    ///
+    /// halo2-base has no in-circuit Poseidon chip at the version used here, so keccak, which the circuit already
+    /// proves for block hashes, is used for the commitment.
+    Keccak,
+}
+
+/// The base fees of a window of consecutive blocks, see [`AxiomChip::eth_getBaseFeeHistory`].
+#[derive(Clone, Debug)]
+pub struct BaseFeeHistory<F: Field> {
+    /// The headers of the blocks, in order.
+    pub blocks: Vec<EthBlock<F>>,
+    /// The base fee of each block in wei.
+    pub base_fees: Vec<AssignedValue<F>>,
+    /// The keccak commitment to the base fees as hi-lo 128 bit halves, with [`HistoryOutput::Keccak`].
+    pub commitment: Option<[AssignedValue<F>; 2]>,
+}
+
+#[cfg(feature = "providers")]
+impl<F: Field> AxiomChip<F> {
+    /// Proves the value of `slot` of the contract at `address` at each of `blocks`, e.g., for a time series.
+    ///
+    /// The storage proofs are fetched concurrently with [`AxiomChip::eth_getProofs`], and their addresses and slots are
+    /// constrained to be equal, so the series is of a single slot. Each block still has its own account proof, since the
+    /// account can change between blocks. As with [`AxiomChip::eth_getProof`], the address and slot are private.
+    /// Returns [`AxiomError::InvalidInput`] if `blocks` is empty.
+    pub fn eth_getStorageSeries<M: Middleware>(
+        &mut self,
+        provider: &M,
+        address: Address,
+        slot: H256,
+        blocks: &[u32],
+    ) -> Result<StorageSeries<F>> {
+        Runtime::new()?.block_on(self.eth_getStorageSeries_async(provider, address, slot, blocks))
+    }
+
+    /// Same as [`AxiomChip::eth_getStorageSeries`], for use from within an async context.
+    pub async fn eth_getStorageSeries_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        address: Address,
+        slot: H256,
+        blocks: &[u32],
+    ) -> Result<StorageSeries<F>> {
+        if blocks.is_empty() {
+            return Err(AxiomError::InvalidInput(
+                "a storage series needs at least one block".to_string(),
+            ));
+        }
+        let queries =
+            blocks.iter().map(|&block_number| (address, vec![slot], block_number)).collect();
+        let digests = self.eth_getProofs_async(provider, queries).await?;
+        Ok(self.storage_series(&digests))
+    }
+
+    /// Proves which block was the latest at `timestamp`: the block `N` with `timestamp(N) <= timestamp <
+    /// timestamp(N + 1)`.
+    ///
+    /// `N` is found by a binary search over the block timestamps from the provider, then the headers of `N` and `N + 1`
+    /// are proven with [`AxiomChip::eth_getBlocksByNumber`] and [`AxiomChip::constrain_block_at_timestamp`] checks the
+    /// result in the circuit, so the provider does not need to be trusted. Returns [`AxiomError::MissingInput`] if no
+    /// block after `timestamp` exists yet.
+    pub fn eth_getBlockAtTimestamp<M: Middleware>(
+        &mut self,
+        provider: &M,
+        timestamp: u64,
+    ) -> Result<BlockAtTimestamp<F>> {
+        Runtime::new()?.block_on(self.eth_getBlockAtTimestamp_async(provider, timestamp))
+    }
+
+    /// Same as [`AxiomChip::eth_getBlockAtTimestamp`], for use from within an async context.
+    pub async fn eth_getBlockAtTimestamp_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        timestamp: u64,
+    ) -> Result<BlockAtTimestamp<F>> {
+        let block_number = self.find_block_at_timestamp(provider, timestamp).await?;
+        let blocks =
+            self.eth_getBlocksByNumber_async(provider, &[block_number, block_number + 1]).await?;
+        let [block, next_block]: [EthBlock<F>; 2] = blocks.try_into().unwrap();
+        Ok(self.constrain_block_at_timestamp(block, next_block, timestamp))
+    }
+
+    /// Proves the headers of blocks `from..=to` and computes the total and the maximum of their `gas_used`, see
+    /// [`AxiomChip::gas_stats`]. Returns [`AxiomError::InvalidInput`] if `from > to`.
+    pub fn eth_getGasStats<M: Middleware>(
+        &mut self,
+        provider: &M,
+        from: u32,
+        to: u32,
+    ) -> Result<GasStats<F>> {
+        Runtime::new()?.block_on(self.eth_getGasStats_async(provider, from, to))
+    }
+
+    /// Same as [`AxiomChip::eth_getGasStats`], for use from within an async context.
+    pub async fn eth_getGasStats_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        from: u32,
+        to: u32,
+    ) -> Result<GasStats<F>> {
+        if from > to {
+            return Err(AxiomError::InvalidInput(format!(
+                "the block range {from}..={to} is empty"
+            )));
+        }
+        let block_numbers: Vec<u32> = (from..=to).collect();
+        let blocks = self.eth_getBlocksByNumber_async(provider, &block_numbers).await?;
+        Ok(self.gas_stats(blocks))
+    }
+
+    /// Proves the headers of blocks `from..=to` and makes their base fees public as chosen by `output`, see
+    /// [`AxiomChip::base_fee_history`]. The blocks must be after the London upgrade.
+    /// Returns [`AxiomError::InvalidInput`] if `from > to`.
+    pub fn eth_getBaseFeeHistory<M: Middleware>(
+        &mut self,
+        provider: &M,
+        from: u32,
+        to: u32,
+        output: HistoryOutput,
+    ) -> Result<BaseFeeHistory<F>> {
+        Runtime::new()?.block_on(self.eth_getBaseFeeHistory_async(provider, from, to, output))
+    }
+
+    /// Same as [`AxiomChip::eth_getBaseFeeHistory`], for use from within an async context.
+    pub async fn eth_getBaseFeeHistory_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        from: u32,
+        to: u32,
+        output: HistoryOutput,
+    ) -> Result<BaseFeeHistory<F>> {
+        if from > to {
+            return Err(AxiomError::InvalidInput(format!(
+                "the block range {from}..={to} is empty"
+            )));
+        }
+        let block_numbers: Vec<u32> = (from..=to).collect();
+        let blocks = self.eth_getBlocksByNumber_async(provider, &block_numbers).await?;
+        Ok(self.base_fee_history(blocks, output))
+    }
+
+    /// Proves the nonce and balance of the account at `address` at block `block_number`, with an `eth_getProof` query
+    /// for no storage slots. As with [`AxiomChip::eth_getProof`], the address and block number are private.
+    pub fn eth_getAccount<M: Middleware>(
+        &mut self,
+        provider: &M,
+        address: Address,
+        block_number: u32,
+    ) -> Result<EthAccount<F>> {
+        Runtime::new()?.block_on(self.eth_getAccount_async(provider, address, block_number))
+    }
+
+    /// Same as [`AxiomChip::eth_getAccount`], for use from within an async context.
+    pub async fn eth_getAccount_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        address: Address,
+        block_number: u32,
+    ) -> Result<EthAccount<F>> {
+        let digest = self.eth_getProof_async(provider, address, vec![], block_number).await?;
+        Ok(self.last_account(&digest))
+    }
+
+    /// Proves the value of `slot` of the contract at `address` at block `block_number`, with an `eth_getProof` query for
+    /// that slot. Returns the value as its minimal big endian bytes, which are empty if the slot or account does not
+    /// exist, and the hash of the block. As with [`AxiomChip::eth_getProof`], the address and slot are private.
+    pub fn eth_getStorageAt<M: Middleware>(
+        &mut self,
+        provider: &M,
+        address: Address,
+        slot: H256,
+        block_number: u32,
+    ) -> Result<(ByteString<F>, H256Assigned<F>)> {
+        Runtime::new()?.block_on(self.eth_getStorageAt_async(provider, address, slot, block_number))
+    }
+
+    /// Same as [`AxiomChip::eth_getStorageAt`], for use from within an async context.
+    pub async fn eth_getStorageAt_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        address: Address,
+        slot: H256,
+        block_number: u32,
+    ) -> Result<(ByteString<F>, H256Assigned<F>)> {
+        let digest = self.eth_getProof_async(provider, address, vec![slot], block_number).await?;
+        Ok(self.last_storage_value(&digest))
+    }
+
+    /// Proves the account at `address` and the values of `slots` of its storage at block `block_number`, with an
+    /// `eth_getProof` query, as a container with a named field for each part of the proof. As with
+    /// [`AxiomChip::eth_getProof`], the address and slots are private.
+    pub fn eth_getAccountStorage<M: Middleware>(
+        &mut self,
+        provider: &M,
+        address: Address,
+        slots: Vec<H256>,
+        block_number: u32,
+    ) -> Result<EthAccountStorage<F>> {
+        Runtime::new()?.block_on(self.eth_getAccountStorage_async(
+            provider,
+            address,
+            slots,
+            block_number,
+        ))
+    }
+
+    /// Same as [`AxiomChip::eth_getAccountStorage`], for use from within an async context.
+    pub async fn eth_getAccountStorage_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        address: Address,
+        slots: Vec<H256>,
+        block_number: u32,
+    ) -> Result<EthAccountStorage<F>> {
+        let digest = self.eth_getProof_async(provider, address, slots, block_number).await?;
+        Ok(self.last_account_storage(&digest))
+    }
+
+    /// Proves that the account at `address` became active between blocks `block_a < block_b`: it had not sent a
+    /// transaction or deployed a contract by block `block_a`, i.e., its nonce was zero, but had by block `block_b`. See
+    /// [`AxiomChip::constrain_activation`].
+    pub fn eth_getAccountActivation<M: Middleware>(
+        &mut self,
+        provider: &M,
+        address: Address,
+        block_a: u32,
+        block_b: u32,
+    ) -> Result<[EthAccount<F>; 2]> {
+        Runtime::new()?
+            .block_on(self.eth_getAccountActivation_async(provider, address, block_a, block_b))
+    }
+
+    /// Same as [`AxiomChip::eth_getAccountActivation`], for use from within an async context.
+    pub async fn eth_getAccountActivation_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        address: Address,
+        block_a: u32,
+        block_b: u32,
+    ) -> Result<[EthAccount<F>; 2]> {
+        let before = self.eth_getAccount_async(provider, address, block_a).await?;
+        let after = self.eth_getAccount_async(provider, address, block_b).await?;
+        self.constrain_activation(&before, &after);
+        Ok([before, after])
+    }
+
+    /// Proves that the externally owned account at `address` sent no transactions between blocks `block_a < block_b`:
+    /// its nonce is the same at both, see [`AxiomChip::constrain_same_nonce`].
+    pub fn eth_getNoOutgoingTransactions<M: Middleware>(
+        &mut self,
+        provider: &M,
+        address: Address,
+        block_a: u32,
+        block_b: u32,
+    ) -> Result<[EthAccount<F>; 2]> {
+        Runtime::new()?
+            .block_on(self.eth_getNoOutgoingTransactions_async(provider, address, block_a, block_b))
+    }
+
+    /// Same as [`AxiomChip::eth_getNoOutgoingTransactions`], for use from within an async context.
+    pub async fn eth_getNoOutgoingTransactions_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        address: Address,
+        block_a: u32,
+        block_b: u32,
+    ) -> Result<[EthAccount<F>; 2]> {
+        let before = self.eth_getAccount_async(provider, address, block_a).await?;
+        let after = self.eth_getAccount_async(provider, address, block_b).await?;
+        self.constrain_same_nonce(&before, &after);
+        Ok([before, after])
+    }
+
+    /// Binary searches for the last block with a timestamp at most `timestamp`.
+    async fn find_block_at_timestamp<M: Middleware>(
+        &mut self,
+        provider: &M,
+        timestamp: u64,
+    ) -> Result<u32> {
+        let network = self.resolve_network(provider).await?;
+        let latest = self.fetch_latest_block_number(provider).await?;
+        if self.block_timestamp(provider, network, latest).await? <= timestamp {
+            return Err(AxiomError::MissingInput(format!(
+                "no block after timestamp {timestamp} yet"
+            )));
+        }
+        if self.block_timestamp(provider, network, 0).await? > timestamp {
+            return Err(AxiomError::MissingInput(format!(
+                "timestamp {timestamp} is before the genesis block"
+            )));
+        }
+        // invariant: timestamp(low) <= timestamp < timestamp(high)
+        let (mut low, mut high) = (0, latest);
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+            if self.block_timestamp(provider, network, mid).await? <= timestamp {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        Ok(low as u32)
+    }
+
+    /// Fetches block `block_number` for a search, recording it for [`AxiomChip::export_inputs`], and returns its
+    /// timestamp.
+    async fn block_timestamp<M: Middleware>(
+        &mut self,
+        provider: &M,
+        network: Network,
+        block_number: u64,
+    ) -> Result<u64> {
+        let block = self.fetch_block(provider, network, block_number).await?;
+        self.record_block(&block);
+        Ok(block.timestamp.as_u64())
+    }
+}
+
+impl<F: Field> AxiomChip<F> {
+    /// Constrains the `digests` of single slot storage proofs to be of the same address and slot, and collects them
+    /// into a series.
+    pub fn storage_series(&self, digests: &[EIP1186ResponseDigest<F>]) -> StorageSeries<F> {
+        assert!(
+            digests.iter().all(|digest| digest.slots_values.len() == 1),
+            "each proof of a series must have one slot"
+        );
+        let first = &digests[0];
+        let (slot, _) = first.slots_values[0];
+        {
+            let mut ctx = self.ctx();
+            for digest in &digests[1..] {
+                ctx.constrain_equal(&first.address, &digest.address);
+                for (a, b) in slot.iter().zip(digest.slots_values[0].0.iter()) {
+                    ctx.constrain_equal(a, b);
+                }
+            }
+        }
+        StorageSeries {
+            address: first.address,
+            slot,
+            block_numbers: digests.iter().map(|digest| digest.block_number).collect(),
+            block_hashes: digests.iter().map(|digest| digest.block_hash).collect(),
+            values: digests.iter().map(|digest| self.storage_value(digest, 0)).collect(),
+        }
+    }
+
+    /// Constrains each of `blocks` to be the parent of the next, i.e., the next block number is one more and the next
+    /// parent hash is its block hash. Then checking the hash of the last block against the chain authenticates them all.
+    ///
+    /// Returns the block number of each block.
+    pub fn constrain_consecutive(&self, blocks: &[EthBlock<F>]) -> Vec<AssignedValue<F>> {
+        let gate = self.gate();
+        let mut ctx = self.ctx();
+        let numbers: Vec<_> =
+            blocks.iter().map(|block| block.number.evaluate(&mut ctx, gate)).collect();
+        for (i, pair) in blocks.windows(2).enumerate() {
+            let expected_next = gate.add(&mut ctx, numbers[i], Constant(F::one()));
+            ctx.constrain_equal(&expected_next, &numbers[i + 1]);
+            for (parent_byte, hash_byte) in
+                pair[1].parent_hash.bytes.iter().zip(&pair[0].block_hash.bytes)
+            {
+                ctx.constrain_equal(parent_byte, hash_byte);
+            }
+        }
+        numbers
+    }
+
+    /// Constrains `next_block` to be the child of `block`, see [`AxiomChip::constrain_consecutive`], and
+    /// `timestamp(block) <= timestamp < timestamp(next_block)`.
+    pub fn constrain_block_at_timestamp(
+        &self,
+        block: EthBlock<F>,
+        next_block: EthBlock<F>,
+        timestamp: u64,
+    ) -> BlockAtTimestamp<F> {
+        let block_number = self.constrain_consecutive(&[block.clone(), next_block.clone()])[0];
+        let range = self.range();
+        let gate = range.gate();
+        let mut ctx = self.ctx();
+        let block_time = block.timestamp.evaluate(&mut ctx, gate);
+        let next_time = next_block.timestamp.evaluate(&mut ctx, gate);
+        let target = ctx.load_witness(F::from(timestamp));
+        range.range_check(&mut ctx, target, TIMESTAMP_BITS);
+        // timestamp(block) <= timestamp, i.e., timestamp(block) < timestamp + 1
+        let target_plus_one = gate.add(&mut ctx, target, Constant(F::one()));
+        range.check_less_than(&mut ctx, block_time, target_plus_one, TIMESTAMP_BITS + 1);
+        range.check_less_than(&mut ctx, target, next_time, TIMESTAMP_BITS);
+        drop(ctx);
+
+        BlockAtTimestamp { timestamp: target, block, next_block, block_number }
+    }
+}
+
+impl<F: Field> AxiomChip<F> {
+    /// Constrains `blocks` to be consecutive, see [`AxiomChip::constrain_consecutive`], computes the total and the
+    /// maximum of their `gas_used`, and exposes both, in this order.
+    ///
+    /// Only the aggregates are public: to attest to a specific range, also expose the number of the first block and the
+    /// hash of the last one, or constrain them to known values.
+    pub fn gas_stats(&mut self, blocks: Vec<EthBlock<F>>) -> GasStats<F> {
+        assert!(!blocks.is_empty(), "gas statistics need at least one block");
+        self.constrain_consecutive(&blocks);
+        let (sum, max) = {
+            let range = self.range();
+            let gate = range.gate();
+            let mut ctx = self.ctx();
+            let gas_used: Vec<_> =
+                blocks.iter().map(|block| block.gas_used.evaluate(&mut ctx, gate)).collect();
+            let sum = gate.sum(&mut ctx, gas_used.iter().copied());
+            let max = gas_used[1..].iter().fold(gas_used[0], |max, &gas| {
+                let is_larger = range.is_less_than(&mut ctx, max, gas, GAS_USED_BITS);
+                gate.select(&mut ctx, gas, max, is_larger)
+            });
+            (sum, max)
+        };
+        self.expose_public(sum);
+        self.expose_public(max);
+        GasStats { blocks, sum, max }
+    }
+}
+
+impl<F: Field> AxiomChip<F> {
+    /// Constrains `blocks` to be consecutive, see [`AxiomChip::constrain_consecutive`], and makes their base fees public
+    /// as chosen by `output`.
+    pub fn base_fee_history(
+        &mut self,
+        blocks: Vec<EthBlock<F>>,
+        output: HistoryOutput,
+    ) -> BaseFeeHistory<F> {
+        assert!(!blocks.is_empty(), "a base fee history needs at least one block");
+        self.constrain_consecutive(&blocks);
+        let gate = self.gate();
+        let base_fees: Vec<_> =
+            blocks.iter().map(|block| block.basefee.evaluate(&mut self.ctx(), gate)).collect();
+        let commitment = match output {
+            HistoryOutput::Values => {
+                base_fees.iter().for_each(|fee| self.expose_public(*fee));
+                None
+            }
+            HistoryOutput::Keccak => {
+                let commitment = {
+                    let range = self.range();
+                    let mut ctx = self.ctx();
+                    let words: Vec<_> = base_fees
+                        .iter()
+                        .flat_map(|fee| uint256_bytes(&mut ctx, range, *fee, BASEFEE_BYTES))
+                        .collect();
+                    let bytes =
+                        words.iter().map(|byte| byte.value().get_lower_32() as u8).collect();
+                    let mut keccak = self.keccak.lock();
+                    let index = keccak.keccak_fixed_len(&mut ctx, range.gate(), words, Some(bytes));
+                    let hash = keccak.fixed_len_queries[index].output_assigned.clone();
+                    H256Assigned::from_bytes(&mut ctx, range.gate(), hash.try_into().unwrap())
+                        .hi_lo()
+                };
+                commitment.iter().for_each(|half| self.expose_public(*half));
+                Some(commitment)
+            }
+        };
+        BaseFeeHistory { blocks, base_fees, commitment }
+    }
+}
+
+/// Replaces `value` with the empty string unless `exists`, for the fields of a proof of a missing key, which are not
+/// meaningful.
+#[cfg(feature = "providers")]
+fn or_empty<F: Field>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    value: ByteString<F>,
+    exists: AssignedValue<F>,
+) -> ByteString<F> {
+    ByteString {
+        len: gate.mul(ctx, value.len, exists),
+        bytes: value.bytes.into_iter().map(|byte| gate.mul(ctx, byte, exists)).collect(),
+    }
+}
+
+/// Decomposes `value`, which must fit in `num_bytes <= 16` bytes, into the 32 bytes of its big endian `uint256` encoding.
+fn uint256_bytes<F: Field>(
+    ctx: &mut Context<F>,
+    range: &impl RangeInstructions<F>,
+    value: AssignedValue<F>,
+    num_bytes: usize,
+) -> Vec<AssignedValue<F>> {
+    let le_bytes = value.value().get_lower_128().to_le_bytes();
+    let bytes_le =
+        ctx.assign_witnesses(le_bytes[..num_bytes].iter().map(|byte| F::from(*byte as u64)));
+    for byte in &bytes_le {
+        range.range_check(ctx, *byte, 8);
+    }
+    let powers = (0..num_bytes).map(|i| Constant(range.gate().pow_of_two()[8 * i]));
+    let recomposed = range.gate().inner_product(ctx, bytes_le.iter().copied(), powers);
+    ctx.constrain_equal(&recomposed, &value);
+    let mut bytes = vec![ctx.load_zero(); 32 - num_bytes];
+    bytes.extend(bytes_le.into_iter().rev());
+    bytes
+}
+
+impl<F: Field> AxiomChip<F> {
+    /// Reads the account of the last storage query, whose digest is `digest`.
+    #[cfg(feature = "providers")]
+    pub(crate) fn last_account(&self, digest: &EIP1186ResponseDigest<F>) -> EthAccount<F> {
+        let witness = &self.storage_witness().last().expect("no storage query").acct_witness;
+        let gate = self.gate();
+        let mut ctx = self.ctx();
+        let is_empty = digest.address_is_empty;
+        // The fields of an account missing from the state trie are not meaningful, so they are replaced with zero.
+        let [nonce, balance] = ["nonce", "balance"].map(|field| {
+            let value = ByteString::from(witness.get(field)).evaluate(&mut ctx, gate);
+            gate.select(&mut ctx, Constant(F::zero()), value, is_empty)
+        });
+        EthAccount {
+            address: digest.address,
+            block_number: digest.block_number,
+            block_hash: digest.block_hash,
+            is_empty,
+            nonce,
+            balance,
+        }
+    }
+
+    /// Reads the value of the first slot of the last storage query, whose digest is `digest`, and the hash of its block.
+    #[cfg(feature = "providers")]
+    pub(crate) fn last_storage_value(
+        &self,
+        digest: &EIP1186ResponseDigest<F>,
+    ) -> (ByteString<F>, H256Assigned<F>) {
+        let exists = self.slot_exists(digest, 0);
+        let witness = self.storage_witness().last().expect("no storage query");
+        let gate = self.gate();
+        let mut ctx = self.ctx();
+        let value = ByteString::from(&witness.storage_witness[0].value_witness);
+        let value = or_empty(&mut ctx, gate, value, exists);
+        let block_hash = witness.block_witness.block_hash.clone().try_into().unwrap();
+        (value, H256Assigned::from_bytes(&mut ctx, gate, block_hash))
+    }
+
+    /// Reads the account and slots of the last storage query, whose digest is `digest`.
+    #[cfg(feature = "providers")]
+    pub(crate) fn last_account_storage(
+        &self,
+        digest: &EIP1186ResponseDigest<F>,
+    ) -> EthAccountStorage<F> {
+        let slots_exist: Vec<_> =
+            (0..digest.slots_values.len()).map(|i| self.slot_exists(digest, i)).collect();
+        let witness = self.storage_witness().last().expect("no storage query");
+        let gate = self.gate();
+        let mut ctx = self.ctx();
+        let account_exists = gate.not(&mut ctx, digest.address_is_empty);
+        let [nonce, balance, storage_root, code_hash] =
+            ["nonce", "balance", "storage_root", "code_hash"].map(|field| {
+                let value = ByteString::from(witness.acct_witness.get(field));
+                or_empty(&mut ctx, gate, value, account_exists)
+            });
+        let slots = witness
+            .storage_witness
+            .iter()
+            .zip(slots_exist)
+            .map(|(storage, exists)| {
+                let slot = H256Assigned::from_bytes(
+                    &mut ctx,
+                    gate,
+                    storage.slot.clone().try_into().unwrap(),
+                );
+                let value = ByteString::from(&storage.value_witness);
+                (slot, or_empty(&mut ctx, gate, value, exists))
+            })
+            .collect();
+        let block_hash = witness.block_witness.block_hash.clone().try_into().unwrap();
+        EthAccountStorage {
+            address: digest.address,
+            block_number: digest.block_number,
+            block_hash: H256Assigned::from_bytes(&mut ctx, gate, block_hash),
+            is_empty: digest.address_is_empty,
+            nonce,
+            balance,
+            storage_root,
+            code_hash,
+            slots,
+        }
+    }
+
+    /// Constrains `before` and `after` to be the same account at blocks in increasing order, with nonce zero at the first
+    /// and a positive nonce at the second.
+    pub fn constrain_activation(&self, before: &EthAccount<F>, after: &EthAccount<F>) {
+        self.constrain_same_account(before, after);
+        let gate = self.gate();
+        let mut ctx = self.ctx();
+        gate.assert_is_const(&mut ctx, &before.nonce, &F::zero());
+        let inactive_after = gate.is_zero(&mut ctx, after.nonce);
+        gate.assert_is_const(&mut ctx, &inactive_after, &F::zero());
+    }
+
+    /// Constrains `before` and `after` to be the same account at blocks in increasing order, with the same nonce.
+    ///
+    /// Every transaction sent by an externally owned account increments its nonce, and the state at a block includes the
+    /// transactions of that block, so this shows it sent none in the blocks after the first up to and including the
+    /// second. For a contract account the nonce only counts the contracts it created.
+    pub fn constrain_same_nonce(&self, before: &EthAccount<F>, after: &EthAccount<F>) {
+        self.constrain_same_account(before, after);
+        self.ctx().constrain_equal(&before.nonce, &after.nonce);
+    }
+
+    /// Constrains `a` and `b` to be of the same address, and the block of `a` to be before the block of `b`.
+    fn constrain_same_account(&self, a: &EthAccount<F>, b: &EthAccount<F>) {
+        let range = self.range();
+        let mut ctx = self.ctx();
+        ctx.constrain_equal(&a.address, &b.address);
+        range.check_less_than(&mut ctx, a.block_number, b.block_number, BLOCK_NUMBER_BITS);
+    }
+}
@@ -0,0 +1,148 @@
+//! Proofs of the configuration of a Safe (formerly Gnosis Safe) multisig at a block: its threshold and owners.
+//!
+//! The owners are stored as a linked list in `mapping(address => address) owners`, from the sentinel address `0x1`
+//! through each owner back to the sentinel. Every entry of the list is proven, so the owner set is complete.
+
+use axiom_eth::{storage::EIP1186ResponseDigest, Field};
+use halo2_base::{
+    gates::{GateInstructions, RangeInstructions},
+    AssignedValue, Context,
+    QuantumCell::Constant,
+};
+#[cfg(feature = "providers")]
+use {
+    crate::{error::Result, slots::address_mapping_slot_value},
+    ethers_core::types::{Address, H256, U256},
+    ethers_providers::Middleware,
+    tokio::runtime::Runtime,
+};
+
+use crate::scaffold::AxiomChip;
+
+/// The slot of `mapping(address => address) owners` in a Safe.
+pub const SAFE_OWNERS_SLOT: u64 = 2;
+/// The slot of `uint256 ownerCount` in a Safe.
+pub const SAFE_OWNER_COUNT_SLOT: u64 = 3;
+/// The slot of `uint256 threshold` in a Safe.
+pub const SAFE_THRESHOLD_SLOT: u64 = 4;
+/// The address that starts and ends the linked list of owners.
+const SENTINEL_OWNERS: u64 = 1;
+
+/// The configuration of a Safe at a block, see [`AxiomChip::safe_config`].
+#[derive(Clone, Debug)]
+pub struct SafeConfig<F: Field> {
+    pub safe: AssignedValue<F>,
+    pub block_number: AssignedValue<F>,
+    /// The block hash as hi-lo 128 bit halves.
+    pub block_hash: (AssignedValue<F>, AssignedValue<F>),
+    /// The number of owner signatures needed to execute a transaction.
+    pub threshold: AssignedValue<F>,
+    /// The owners, in the order of the linked list.
+    pub owners: Vec<AssignedValue<F>>,
+}
+
+impl<F: Field> SafeConfig<F> {
+    /// Returns whether `address` is one of the owners.
+    pub fn is_owner(
+        &self,
+        ctx: &mut Context<F>,
+        gate: &impl GateInstructions<F>,
+        address: AssignedValue<F>,
+    ) -> AssignedValue<F> {
+        let mut is_owner = ctx.load_zero();
+        for owner in &self.owners {
+            let is_equal = gate.is_equal(ctx, *owner, address);
+            is_owner = gate.or(ctx, is_owner, is_equal);
+        }
+        is_owner
+    }
+}
+
+#[cfg(feature = "providers")]
+impl<F: Field> AxiomChip<F> {
+    /// Proves the threshold and owners of the Safe at `safe` at block `block_number`.
+    ///
+    /// The linked list of owners is walked with the provider first, then the threshold, the owner count and every
+    /// entry of the list are proven with a single storage proof, see [`AxiomChip::constrain_safe_config`]. The circuit
+    /// layout depends on the number of owners.
+    pub fn safe_config<M: Middleware>(
+        &mut self,
+        provider: &M,
+        safe: Address,
+        block_number: u32,
+    ) -> Result<SafeConfig<F>> {
+        Runtime::new()?.block_on(self.safe_config_async(provider, safe, block_number))
+    }
+
+    /// Same as [`AxiomChip::safe_config`], for use from within an async context.
+    pub async fn safe_config_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        safe: Address,
+        block_number: u32,
+    ) -> Result<SafeConfig<F>> {
+        let count_slot = H256::from_low_u64_be(SAFE_OWNER_COUNT_SLOT);
+        let count = self.fetch_storage_at(provider, block_number as u64, safe, count_slot).await?;
+        let count = U256::from_big_endian(&count.0).low_u64();
+
+        let owners_slot = H256::from_low_u64_be(SAFE_OWNERS_SLOT);
+        let mut slots = vec![H256::from_low_u64_be(SAFE_THRESHOLD_SLOT), count_slot];
+        let mut owner = Address::from_low_u64_be(SENTINEL_OWNERS);
+        for _ in 0..=count {
+            let slot = address_mapping_slot_value(owners_slot, &[owner]);
+            slots.push(slot);
+            let next = self.fetch_storage_at(provider, block_number as u64, safe, slot).await?;
+            owner = Address::from(next);
+        }
+        let digest = self.eth_getProof_async(provider, safe, slots, block_number).await?;
+        Ok(self.constrain_safe_config(&digest))
+    }
+}
+
+impl<F: Field> AxiomChip<F> {
+    /// Constrains `digest` to be the storage proof of the threshold, the owner count and the linked list of owners of a
+    /// Safe, in this order, where the list starts at the entry of the sentinel and ends with the entry of the last owner,
+    /// which points back to the sentinel. The number of owners in the list must be the owner count.
+    pub fn constrain_safe_config(&self, digest: &EIP1186ResponseDigest<F>) -> SafeConfig<F> {
+        assert!(digest.slots_values.len() >= 3, "the owners list has at least the sentinel entry");
+        let num_owners = digest.slots_values.len() - 3;
+        self.constrain_slot(digest, 0, self.constant_slot(SAFE_THRESHOLD_SLOT).hi_lo());
+        self.constrain_slot(digest, 1, self.constant_slot(SAFE_OWNER_COUNT_SLOT).hi_lo());
+        let owners_slot = self.constant_slot(SAFE_OWNERS_SLOT);
+
+        let sentinel = self.ctx().load_constant(F::from(SENTINEL_OWNERS));
+        let mut owner = sentinel;
+        let mut owners = vec![];
+        for i in 0..=num_owners {
+            let slot = self.address_mapping_slot(owners_slot.clone(), &[owner]);
+            self.constrain_slot(digest, 2 + i, slot.hi_lo());
+            let [hi, lo] = self.storage_value(digest, 2 + i);
+            let range = self.range();
+            let mut ctx = self.ctx();
+            // an address is stored in the lowest 20 bytes of the slot
+            range.range_check(&mut ctx, hi, 32);
+            owner =
+                range.gate().mul_add(&mut ctx, hi, Constant(range.gate().pow_of_two()[128]), lo);
+            if i < num_owners {
+                owners.push(owner);
+            }
+        }
+
+        let [[threshold_hi, threshold], [count_hi, count_lo]] =
+            [0, 1].map(|i| self.storage_value(digest, i));
+        let gate = self.gate();
+        let mut ctx = self.ctx();
+        ctx.constrain_equal(&owner, &sentinel);
+        gate.assert_is_const(&mut ctx, &count_hi, &F::zero());
+        gate.assert_is_const(&mut ctx, &count_lo, &F::from(num_owners as u64));
+        gate.assert_is_const(&mut ctx, &threshold_hi, &F::zero());
+
+        SafeConfig {
+            safe: digest.address,
+            block_number: digest.block_number,
+            block_hash: digest.block_hash,
+            threshold,
+            owners,
+        }
+    }
+}
@@ -6,59 +6,161 @@ use axiom_eth::{
         MAINNET_BLOCK_HEADER_RLP_MAX_BYTES,
     },
     keccak::{FnSynthesize, KeccakChip},
-    providers::{get_block_rlp, get_block_storage_input},
+    mpt::MPTFixedKeyProofWitness,
+    providers::get_block_rlp,
     rlp::{
         builder::{RlcThreadBreakPoints, RlcThreadBuilder},
         rlc::{RlcFixedTrace, RlcTrace},
         RlpChip,
     },
-    storage::{EIP1186ResponseDigest, EthBlockAccountStorageTraceWitness, EthStorageChip},
-    EthChip, EthCircuitBuilder, Field, Network,
+    storage::{
+        EIP1186ResponseDigest, EthBlockAccountStorageTraceWitness, EthBlockStorageInput,
+        EthStorageChip,
+    },
+    EthChip, EthCircuitBuilder, EthConfigParams, Field, Network,
 };
-use ethers_core::types::{Address, H256, U256};
-use ethers_providers::{Http, Middleware, Provider};
+use ethers_core::types::{Block, EIP1186ProofResponse, H256};
 use halo2_base::{
     gates::{GateChip, RangeChip, RangeInstructions},
     halo2_proofs::{
         dev::MockProver,
         halo2curves::bn256::{Bn256, Fr, G1Affine},
-        plonk::{create_proof, keygen_pk, keygen_vk, verify_proof},
-        poly::kzg::{
-            commitment::KZGCommitmentScheme,
-            multiopen::{ProverSHPLONK, VerifierSHPLONK},
-            strategy::SingleStrategy,
-        },
-        transcript::{
-            Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
-        },
+        plonk::{keygen_pk, keygen_vk, ProvingKey},
+        poly::kzg::commitment::ParamsKZG,
     },
-    utils::fs::gen_srs,
     AssignedValue, Context,
 };
-use rand_core::OsRng;
-use std::{
-    cell::{RefCell, RefMut},
-    env::var,
-};
-use tokio::runtime::Runtime;
+use parking_lot::{MappedMutexGuard, Mutex, MutexGuard};
+use std::{cell::RefCell, collections::HashMap, sync::Arc};
 
-use super::containers::EthBlock;
+use super::{
+    cancel::CancellationToken,
+    config::{AutoDegree, AxiomChipConfig},
+    containers::{EthBlock, H256Assigned},
+    debug::{explain_failures, QuerySource, QuerySpan},
+    error::{AxiomError, Result},
+    keys::{lock_config_params, set_config_params, AxiomPinning},
+    offline::{ProofInput, StorageInput, UncleInput},
+    ommers::OmmersTraceWitness,
+    phase1::{AxiomQuery, Phase1Hook},
+    proof::{prove_circuit, ProofArtifacts, ProvingOptions},
+    providers::chain_id,
+    srs::SrsManager,
+};
+// Only the queries that fetch their inputs from a provider need the networking stack.
+#[cfg(feature = "providers")]
+use {
+    super::{
+        cache::ChainDataCache,
+        providers::{
+            get_block, get_block_number, get_chain_id, get_proof, get_storage_at,
+            network_from_chain_id, RpcClient, RpcPolicy,
+        },
+    },
+    axiom_eth::keccak::parallelize_keccak_phase0,
+    ethers_core::types::Address,
+    ethers_providers::Middleware,
+    futures::{stream, StreamExt, TryStreamExt},
+    tokio::runtime::Runtime,
+};
 
-type KeccakRlcs<F> =
+pub(crate) type KeccakRlcs<F> =
     (Vec<(RlcFixedTrace<F>, RlcFixedTrace<F>)>, Vec<(RlcTrace<F>, RlcFixedTrace<F>)>);
 
+/// Where the witness of a proven block header is.
+#[derive(Clone, Copy, Debug)]
+enum ProvenHeader {
+    /// In `header_witness`, from a query by number.
+    Header(usize),
+    /// In `storage_witness`, from an `eth_getProof` query.
+    Storage(usize),
+}
+
 pub const ACCOUNT_PROOF_MAX_DEPTH: usize = 10;
 pub const STORAGE_PROOF_MAX_DEPTH: usize = 10;
 
+/// The most nodes of the account proof and of each storage proof of an `eth_getProof` query, which fix the circuit
+/// layout of the query. Smaller depths make a smaller circuit for shallow tries, and deeper tries need larger ones.
+///
+/// Keys generated from a [`QuerySchema`](crate::schema::QuerySchema) use the default depths.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProofDepths {
+    pub account: usize,
+    pub storage: usize,
+}
+
+impl Default for ProofDepths {
+    fn default() -> Self {
+        Self { account: ACCOUNT_PROOF_MAX_DEPTH, storage: STORAGE_PROOF_MAX_DEPTH }
+    }
+}
+
+impl ProofDepths {
+    /// Returns [`AxiomError::ProofTooDeep`] if a proof of `response` has more nodes than these depths allow.
+    pub fn check(&self, response: &EIP1186ProofResponse) -> Result<()> {
+        let proofs = std::iter::once((response.account_proof.len(), self.account)).chain(
+            response.storage_proof.iter().map(|storage| (storage.proof.len(), self.storage)),
+        );
+        for (depth, max_depth) in proofs {
+            if depth > max_depth {
+                return Err(AxiomError::ProofTooDeep { depth, max_depth });
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub struct AxiomChip<F: Field> {
     pub range: RangeChip<F>,
-    pub keccak: RefCell<KeccakChip<F>>,
-    pub builder: RefCell<RlcThreadBuilder<F>>,
+    pub keccak: Mutex<KeccakChip<F>>,
+    pub builder: Mutex<RlcThreadBuilder<F>>,
 
-    instances: Vec<AssignedValue<F>>,
+    pub(crate) instances: Vec<AssignedValue<F>>,
+    /// Whether the first public instances are a KZG accumulator from verifying other snarks in this circuit.
+    pub(crate) has_accumulator: bool,
     header_witness: Vec<EthBlockHeaderTraceWitness<F>>,
+    /// The block hash of each of `header_witness`, packed into hi-lo halves when the header was assigned.
+    header_hashes: Vec<H256Assigned<F>>,
+    /// The witness of each block header proven so far, keyed by chain ID and block number, so that querying the same
+    /// block again reuses it.
+    proven_blocks: HashMap<(u64, u64), ProvenHeader>,
     storage_witness: Vec<EthBlockAccountStorageTraceWitness<F>>,
+    /// The results of the storage queries, in the same order as `storage_witness`.
+    pub(crate) storage_digests: Vec<EIP1186ResponseDigest<F>>,
+    /// The ommers lists proven so far, whose RLCs are constrained in phase 1.
+    pub(crate) ommers_witness: Vec<OmmersTraceWitness<F>>,
+    /// The MPT proofs of custom tries proven so far, whose RLCs are constrained in phase 1.
+    pub(crate) mpt_witness: Vec<MPTFixedKeyProofWitness<F>>,
+    /// The queries added with [`AxiomChip::add_query`], whose second phase follows the built-in queries.
+    custom_queries: Vec<Arc<dyn AxiomQuery<F>>>,
+    /// User constraints of the second phase, added after those of all queries.
+    phase1_hook: Option<Phase1Hook<F>>,
+    config: AxiomChipConfig,
+    /// The network of the provider, detected on the first query unless set with [`AxiomChip::set_network`].
+    network: Option<Network>,
+    /// Makes the provider calls of queries, with retries and rate limiting.
+    #[cfg(feature = "providers")]
+    pub(crate) rpc: RpcClient,
+    /// If set, fetched chain data is cached on disk.
+    #[cfg(feature = "providers")]
+    cache: Option<ChainDataCache>,
+    /// The blocks and proofs used by queries so far, see [`AxiomChip::export_inputs`].
+    pub(crate) recorded_blocks: Vec<Block<H256>>,
+    pub(crate) recorded_proofs: Vec<ProofInput>,
+    pub(crate) recorded_uncles: Vec<UncleInput>,
+    pub(crate) recorded_storage: Vec<StorageInput>,
+    pub(crate) recorded_latest_block: Option<u64>,
+    pinning: Option<AxiomPinning>,
+    proving_options: ProvingOptions,
+    /// If set, queries and proving stop with [`AxiomError::Cancelled`] once it is cancelled.
+    cancellation: Option<CancellationToken>,
+    /// The cells assigned by each query, to explain mock prover failures.
+    pub(crate) query_spans: Vec<QuerySpan>,
+    /// Whether each block header and storage proof query starts a new first phase thread.
+    thread_per_query: bool,
+    /// Whether querying an already proven block header reuses its witness, see [`AxiomChip::set_reuse_headers`].
+    reuse_headers: bool,
 }
 
 impl<F: Field> Default for AxiomChip<F> {
@@ -72,30 +174,219 @@ impl<F: Field> Clone for AxiomChip<F> {
     fn clone(&self) -> Self {
         Self {
             range: self.range.clone(),
-            keccak: RefCell::new(self.keccak.borrow().clone()),
-            builder: RefCell::new(self.builder.borrow().clone()),
+            keccak: Mutex::new(self.keccak.lock().clone()),
+            builder: Mutex::new(self.builder.lock().clone()),
             instances: self.instances.clone(),
+            has_accumulator: self.has_accumulator,
             header_witness: self.header_witness.clone(),
+            header_hashes: self.header_hashes.clone(),
+            proven_blocks: self.proven_blocks.clone(),
             storage_witness: self.storage_witness.clone(),
+            storage_digests: self.storage_digests.clone(),
+            ommers_witness: self.ommers_witness.clone(),
+            mpt_witness: self.mpt_witness.clone(),
+            custom_queries: self.custom_queries.clone(),
+            phase1_hook: self.phase1_hook.clone(),
+            config: self.config,
+            network: self.network,
+            #[cfg(feature = "providers")]
+            rpc: self.rpc.clone(),
+            #[cfg(feature = "providers")]
+            cache: self.cache.clone(),
+            recorded_blocks: self.recorded_blocks.clone(),
+            recorded_proofs: self.recorded_proofs.clone(),
+            recorded_uncles: self.recorded_uncles.clone(),
+            recorded_storage: self.recorded_storage.clone(),
+            recorded_latest_block: self.recorded_latest_block,
+            pinning: self.pinning.clone(),
+            proving_options: self.proving_options,
+            cancellation: self.cancellation.clone(),
+            query_spans: self.query_spans.clone(),
+            thread_per_query: self.thread_per_query,
+            reuse_headers: self.reuse_headers,
         }
     }
 }
 
 impl<F: Field> AxiomChip<F> {
+    /// Creates a chip configured from environment variables, see [`AxiomChipConfig::from_env`].
     pub fn new(builder: RlcThreadBuilder<F>) -> Self {
-        let lookup_bits = var("LOOKUP_BITS").map(|l| l.parse().unwrap_or(8)).unwrap_or(8);
+        Self::new_with_config(builder, AxiomChipConfig::from_env())
+    }
+
+    /// Creates a chip for mock proving and testing with the given circuit configuration.
+    pub fn with_config(config: AxiomChipConfig) -> Self {
+        Self::new_with_config(RlcThreadBuilder::mock(), config)
+    }
+
+    pub fn new_with_config(builder: RlcThreadBuilder<F>, config: AxiomChipConfig) -> Self {
         Self {
-            range: RangeChip::default(lookup_bits),
-            keccak: RefCell::new(KeccakChip::default()),
-            builder: RefCell::new(builder),
+            range: RangeChip::default(config.lookup_bits),
+            keccak: Mutex::new(KeccakChip::default()),
+            builder: Mutex::new(builder),
             instances: Default::default(),
+            has_accumulator: false,
             header_witness: Default::default(),
+            header_hashes: Default::default(),
+            proven_blocks: Default::default(),
             storage_witness: Default::default(),
+            storage_digests: Default::default(),
+            ommers_witness: Default::default(),
+            mpt_witness: Default::default(),
+            custom_queries: Default::default(),
+            phase1_hook: None,
+            config,
+            network: None,
+            #[cfg(feature = "providers")]
+            rpc: Default::default(),
+            #[cfg(feature = "providers")]
+            cache: None,
+            recorded_blocks: Default::default(),
+            recorded_proofs: Default::default(),
+            recorded_uncles: Default::default(),
+            recorded_storage: Default::default(),
+            recorded_latest_block: None,
+            pinning: None,
+            proving_options: Default::default(),
+            cancellation: None,
+            query_spans: Default::default(),
+            thread_per_query: false,
+            reuse_headers: false,
         }
     }
 
-    pub fn ctx(&self) -> RefMut<Context<F>> {
-        RefMut::map(self.builder.borrow_mut(), |b| b.gate_builder.main(0))
+    /// Creates a witness generation only chip for proving with keys generated for the circuit described by `pinning`.
+    ///
+    /// The break points and circuit configuration are taken from the pinning instead of being recomputed, so the circuit
+    /// built by [`AxiomChip::create`] is identical to the one that was keyed.
+    pub fn prover(pinning: AxiomPinning) -> Self {
+        let config = AxiomChipConfig {
+            k: pinning.params.degree as usize,
+            lookup_bits: pinning.params.lookup_bits.unwrap_or(8),
+            unusable_rows: pinning.params.unusable_rows,
+            auto_degree: None,
+            keccak_rows_per_round: Some(pinning.params.keccak_rows_per_round),
+        };
+        Self { pinning: Some(pinning), ..Self::new_with_config(RlcThreadBuilder::prover(), config) }
+    }
+
+    pub fn config(&self) -> &AxiomChipConfig {
+        &self.config
+    }
+
+    pub fn pinning(&self) -> Option<&AxiomPinning> {
+        self.pinning.as_ref()
+    }
+
+    pub fn proving_options(&self) -> &ProvingOptions {
+        &self.proving_options
+    }
+
+    /// The network queries are made against, if it is known yet.
+    pub fn network(&self) -> Option<Network> {
+        self.network
+    }
+
+    /// Sets the network queries are made against, so that it is not detected from the provider's chain ID.
+    pub fn set_network(&mut self, network: Network) {
+        self.network = Some(network);
+    }
+
+    /// Sets how failed provider calls are retried and how many calls are made per second.
+    #[cfg(feature = "providers")]
+    pub fn set_rpc_policy(&mut self, policy: RpcPolicy) {
+        self.rpc.policy = policy;
+    }
+
+    /// Caches the chain data fetched by queries on disk, and reuses previously cached data instead of fetching it again.
+    #[cfg(feature = "providers")]
+    pub fn set_cache(&mut self, cache: ChainDataCache) {
+        self.cache = Some(cache);
+    }
+
+    /// Makes queries and proving check `token` before each provider call and between the stages of proving, and
+    /// return [`AxiomError::Cancelled`] once it is cancelled.
+    ///
+    /// A stage that has started runs to completion: halo2 offers no way to interrupt key generation or `create_proof`,
+    /// so cancelling a proof takes effect within one stage rather than immediately.
+    pub fn set_cancellation(&mut self, token: CancellationToken) {
+        self.cancellation = Some(token);
+    }
+
+    /// Returns [`AxiomError::Cancelled`] if the cancellation token of this chip was cancelled.
+    pub(crate) fn check_cancelled(&self) -> Result<()> {
+        self.cancellation.as_ref().map_or(Ok(()), CancellationToken::check)
+    }
+
+    /// Sets the pinning of keys generated for this circuit, so it can be proven with [`AxiomChip::prove_with_pk`].
+    pub(crate) fn set_pinning(&mut self, pinning: AxiomPinning) {
+        self.pinning = Some(pinning);
+    }
+
+    /// Sets how proofs of this circuit are created, e.g., which transcript is used.
+    pub fn set_proving_options(&mut self, options: ProvingOptions) {
+        self.proving_options = options;
+    }
+
+    /// Adds a query of a kind that is not built in: runs its first phase now, and its second phase in the circuit
+    /// created by [`AxiomChip::create`], after the built-in queries and the queries added before it.
+    pub fn add_query(&mut self, mut query: Box<dyn AxiomQuery<F>>) {
+        query.phase0(self);
+        self.custom_queries.push(Arc::from(query));
+    }
+
+    /// Sets a callback that adds constraints in the second phase of the circuit created by [`AxiomChip::create`], after
+    /// those of all queries, e.g. over the random linear combinations of values assigned in the first phase,
+    /// which it captures. It replaces any callback set before.
+    ///
+    /// The callback runs each time the circuit is synthesized, and must assign the same cells each time. Create an
+    /// [`RlcContext`](crate::phase1::RlcContext) from its arguments to compare byte strings by their RLCs.
+    pub fn set_phase1_hook(
+        &mut self,
+        hook: impl Fn(&mut RlcThreadBuilder<F>, &EthChip<F>) + Send + Sync + 'static,
+    ) {
+        self.phase1_hook = Some(Phase1Hook::new(hook));
+    }
+
+    /// Locks the main context of the first phase, which is the thread started last, see [`AxiomChip::new_thread`].
+    /// Other threads using the chip wait until the returned guard is dropped.
+    pub fn ctx(&self) -> MappedMutexGuard<Context<F>> {
+        MutexGuard::map(self.builder.lock(), |b| b.gate_builder.main(0))
+    }
+
+    /// Starts a new first phase thread and returns its index. It becomes the main context returned by
+    /// [`AxiomChip::ctx`], so everything assigned afterwards goes into it, until the next thread is started.
+    ///
+    /// Cells of different threads can be constrained equal, so values assigned in earlier threads stay usable.
+    pub fn new_thread(&self) -> usize {
+        let mut builder = self.builder.lock();
+        builder.gate_builder.new_thread(0);
+        builder.gate_builder.threads[0].len() - 1
+    }
+
+    /// Locks the `thread`-th context of the first phase, e.g. a thread returned by [`AxiomChip::new_thread`], to
+    /// assign more cells to it after other threads were started.
+    pub fn thread_ctx(&self, thread: usize) -> MappedMutexGuard<Context<F>> {
+        MutexGuard::map(self.builder.lock(), |b| &mut b.gate_builder.threads[0][thread])
+    }
+
+    /// Sets whether each block header and storage proof query is assigned in a new first phase thread, instead of
+    /// appending to the main context. The circuit is then made of many short threads rather than one long one, which
+    /// halo2-lib lays out and assigns thread by thread.
+    ///
+    /// This changes the circuit layout, so keys must be generated with the same setting as the proofs.
+    pub fn set_thread_per_query(&mut self, enabled: bool) {
+        self.thread_per_query = enabled;
+    }
+
+    /// Sets whether querying a block header that an earlier query already proved, including the block of an
+    /// `eth_getProof` query, returns the existing header witness instead of proving the header again. Off by default.
+    ///
+    /// Reuse makes the circuit layout depend on which queried blocks repeat, which a
+    /// [`QuerySchema`](crate::schema::QuerySchema) does not describe, so keys must be generated by running the same
+    /// queries, with the same repetitions, as the proofs.
+    pub fn set_reuse_headers(&mut self, enabled: bool) {
+        self.reuse_headers = enabled;
     }
 
     pub fn gate(&self) -> &GateChip<F> {
@@ -130,84 +421,534 @@ impl<F: Field> AxiomChip<F> {
         &self.storage_witness
     }
 
-    /// Get block header from provider by number. The provider provides the chain ID. Currently Ethereum mainnet and Goerli are supported.
+    /// Returns the header of block `block_number` if a query has already proven it, either by number or as the block
+    /// of an `eth_getProof` query, without assigning anything.
+    pub fn proven_header(&self, block_number: u32) -> Option<EthBlock<F>> {
+        self.proven_block(self.network?, block_number as u64)
+    }
+}
+
+/// Queries that fetch their inputs from a provider. Without the `providers` feature, use the variants of
+/// [`offline`](crate::offline) that take pre-fetched inputs instead.
+///
+/// Every provider query, here and in the other modules, comes with an `_async` variant for use from within an async
+/// context. The synchronous one blocks on its own runtime, so it panics if called from within an async context.
+#[cfg(feature = "providers")]
+impl<F: Field> AxiomChip<F> {
+    /// Get block header from provider by number. The provider provides the chain ID, unless the network was set with
+    /// [`AxiomChip::set_network`]. Currently Ethereum mainnet and Goerli are supported.
     /// Returns the parsed block header where each field is a variable-length bytestring.
-    pub fn eth_getBlockByNumber(
+    /// If enabled with [`AxiomChip::set_reuse_headers`], querying a block already proven by an earlier query, including
+    /// `eth_getProof`, returns its header witness instead of proving it again.
+    ///
+    /// Any ethers [`Middleware`] can be used as the provider.
+    pub fn eth_getBlockByNumber<M: Middleware>(
         &mut self,
-        provider: &Provider<Http>,
+        provider: &M,
         block_number: u32,
-    ) -> EthBlock<F> {
-        let rt = Runtime::new().unwrap();
-        let chain_id = rt.block_on(provider.get_chainid()).unwrap();
-        let network = match chain_id {
-            U256([1, 0, 0, 0]) => Network::Mainnet,
-            U256([5, 0, 0, 0]) => Network::Goerli,
-            _ => panic!("Unsupported chain id"),
-        };
-        let block = rt.block_on(provider.get_block(block_number as u64)).unwrap().unwrap();
-        let mut block_header = get_block_rlp(&block);
-        let max_len = match network {
-            Network::Mainnet => MAINNET_BLOCK_HEADER_RLP_MAX_BYTES,
-            Network::Goerli => GOERLI_BLOCK_HEADER_RLP_MAX_BYTES,
-        };
-        block_header.resize(max_len, 0u8);
-
-        let witness = self.eth_chip().decompose_block_header_phase0(
-            &mut self.ctx(),
-            &mut self.keccak.borrow_mut(),
-            &block_header,
-            network,
-        );
-        let block = (&witness).into();
-        self.header_witness.push(witness);
-        block
+    ) -> Result<EthBlock<F>> {
+        Runtime::new()?.block_on(self.eth_getBlockByNumber_async(provider, block_number))
     }
 
     /// Matches the `eth_getProof` JSON-RPC call. Note that this will return a result matching the JSON-RPC call, even if account or storage slot is empty.
     ///
     /// This function assigns `address`, `slots`, and `block_number` as *private* witnesses. You must separately constrain them or make them public as needed.
-    pub fn eth_getProof(
+    ///
+    /// The block header is proven as part of the query. Get it with [`AxiomChip::proven_header`], or with
+    /// [`AxiomChip::eth_getBlockByNumber`], which reuses it instead of proving it again if enabled with
+    /// [`AxiomChip::set_reuse_headers`]. The storage proof of `axiom-eth`
+    /// always decomposes its own header, so the reverse does not hold: a header queried by number is proven again here.
+    ///
+    /// Any ethers [`Middleware`] can be used as the provider.
+    pub fn eth_getProof<M: Middleware>(
         &mut self,
-        provider: &Provider<Http>,
+        provider: &M,
         address: Address,
         slots: Vec<H256>,
         block_number: u32,
-    ) -> EIP1186ResponseDigest<F> {
-        let rt = Runtime::new().unwrap();
-        let chain_id = rt.block_on(provider.get_chainid()).unwrap();
-        let network = match chain_id {
-            U256([1, 0, 0, 0]) => Network::Mainnet,
-            U256([5, 0, 0, 0]) => Network::Goerli,
-            _ => panic!("Unsupported chain id"),
-        };
-        let input = get_block_storage_input(
+    ) -> Result<EIP1186ResponseDigest<F>> {
+        Runtime::new()?.block_on(self.eth_getProof_async(provider, address, slots, block_number))
+    }
+
+    /// Same as [`AxiomChip::eth_getBlockByNumber`], for use from within an async context.
+    pub async fn eth_getBlockByNumber_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        block_number: u32,
+    ) -> Result<EthBlock<F>> {
+        let network = self.resolve_network(provider).await?;
+        if let Some(block) = self.reusable_block(network, block_number as u64) {
+            return Ok(block);
+        }
+        let block = self.fetch_block(provider, network, block_number as u64).await?;
+        self.record_block(&block);
+        Ok(self.assign_block(&block, network))
+    }
+
+    /// Same as [`AxiomChip::eth_getProof`], for use from within an async context.
+    pub async fn eth_getProof_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        address: Address,
+        slots: Vec<H256>,
+        block_number: u32,
+    ) -> Result<EIP1186ResponseDigest<F>> {
+        let depths = ProofDepths::default();
+        self.eth_getProof_with_depths_async(provider, address, slots, block_number, depths).await
+    }
+
+    /// Same as [`AxiomChip::eth_getProof`], with the proofs padded to `depths` nodes instead of the default depths.
+    /// Returns [`AxiomError::ProofTooDeep`] if a proof has more nodes.
+    pub fn eth_getProof_with_depths<M: Middleware>(
+        &mut self,
+        provider: &M,
+        address: Address,
+        slots: Vec<H256>,
+        block_number: u32,
+        depths: ProofDepths,
+    ) -> Result<EIP1186ResponseDigest<F>> {
+        Runtime::new()?.block_on(self.eth_getProof_with_depths_async(
             provider,
-            block_number,
             address,
             slots,
-            ACCOUNT_PROOF_MAX_DEPTH,
-            STORAGE_PROOF_MAX_DEPTH,
-        );
-        let input = input.assign(&mut self.ctx());
-        let (witness, digest) = self.eth_chip().parse_eip1186_proofs_from_block_phase0(
-            &mut self.builder.borrow_mut().gate_builder,
-            &mut self.keccak.borrow_mut(),
-            input,
-            network,
-        );
+            block_number,
+            depths,
+        ))
+    }
+
+    /// Same as [`AxiomChip::eth_getProof_with_depths`], for use from within an async context.
+    pub async fn eth_getProof_with_depths_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        address: Address,
+        slots: Vec<H256>,
+        block_number: u32,
+        depths: ProofDepths,
+    ) -> Result<EIP1186ResponseDigest<F>> {
+        let network = self.resolve_network(provider).await?;
+        let block = self.fetch_block(provider, network, block_number as u64).await?;
+        let proof =
+            self.fetch_proof(provider, network, block_number as u64, address, slots).await?;
+        self.eth_getProof_from_response_with_depths(&block, proof, network, depths)
+    }
+
+    /// Same as calling [`AxiomChip::eth_getBlockByNumber`] for each of `block_numbers`, but the blocks are fetched
+    /// concurrently, up to [`RpcPolicy::max_concurrency`] at a time, and their witnesses are generated in parallel.
+    ///
+    /// Each header is assigned in its own thread of the circuit builder, so the circuit layout differs from assigning
+    /// the headers one at a time: generate keys and proofs with the same calls.
+    pub fn eth_getBlocksByNumber<M: Middleware>(
+        &mut self,
+        provider: &M,
+        block_numbers: &[u32],
+    ) -> Result<Vec<EthBlock<F>>> {
+        Runtime::new()?.block_on(self.eth_getBlocksByNumber_async(provider, block_numbers))
+    }
+
+    /// Same as calling [`AxiomChip::eth_getProof`] for each `(address, slots, block_number)` in `queries`, but the
+    /// blocks and proofs are fetched concurrently, up to [`RpcPolicy::max_concurrency`] at a time.
+    /// The storage proofs within each query are assigned in parallel.
+    pub fn eth_getProofs<M: Middleware>(
+        &mut self,
+        provider: &M,
+        queries: Vec<(Address, Vec<H256>, u32)>,
+    ) -> Result<Vec<EIP1186ResponseDigest<F>>> {
+        Runtime::new()?.block_on(self.eth_getProofs_async(provider, queries))
+    }
+
+    /// Same as [`AxiomChip::eth_getBlocksByNumber`], for use from within an async context.
+    pub async fn eth_getBlocksByNumber_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        block_numbers: &[u32],
+    ) -> Result<Vec<EthBlock<F>>> {
+        let network = self.resolve_network(provider).await?;
+        let mut new_numbers: Vec<u64> = vec![];
+        for &block_number in block_numbers {
+            let block_number = block_number as u64;
+            if !self.reuse_headers
+                || (self.proven_block(network, block_number).is_none()
+                    && !new_numbers.contains(&block_number))
+            {
+                new_numbers.push(block_number);
+            }
+        }
+        let this = &*self;
+        let blocks: Vec<_> = stream::iter(new_numbers)
+            .map(|block_number| this.fetch_block(provider, network, block_number))
+            .buffered(this.rpc.policy.max_concurrency)
+            .try_collect()
+            .await?;
+        for block in &blocks {
+            self.record_block(block);
+        }
+        Ok(self.assign_blocks(&blocks, network, block_numbers))
+    }
+
+    /// Same as [`AxiomChip::eth_getProofs`], for use from within an async context.
+    pub async fn eth_getProofs_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        queries: Vec<(Address, Vec<H256>, u32)>,
+    ) -> Result<Vec<EIP1186ResponseDigest<F>>> {
+        let network = self.resolve_network(provider).await?;
+        let this = &*self;
+        let inputs: Vec<_> = stream::iter(queries)
+            .map(|(address, slots, block_number)| async move {
+                let block_number = block_number as u64;
+                let block = this.fetch_block(provider, network, block_number).await?;
+                let proof =
+                    this.fetch_proof(provider, network, block_number, address, slots).await?;
+                Ok::<_, AxiomError>((block, proof))
+            })
+            .buffered(this.rpc.policy.max_concurrency)
+            .try_collect()
+            .await?;
+        inputs
+            .into_iter()
+            .map(|(block, proof)| self.eth_getProof_from_response(&block, proof, network))
+            .collect()
+    }
+
+    /// Fetches block `block_number` from the cache, or from `provider` if it is not cached.
+    pub(crate) async fn fetch_block<M: Middleware>(
+        &self,
+        provider: &M,
+        network: Network,
+        block_number: u64,
+    ) -> Result<Block<H256>> {
+        if let Some(block) = self.cache.as_ref().and_then(|c| c.block(network, block_number)) {
+            return Ok(block);
+        }
+        self.check_cancelled()?;
+        let block = get_block(&self.rpc, provider, block_number).await?;
+        if let Some(cache) = &self.cache {
+            cache.put_block(network, &block)?;
+        }
+        Ok(block)
+    }
+
+    /// Fetches the `eth_getProof` response for `slots` of `address` from the cache, or from `provider` if it is not cached.
+    async fn fetch_proof<M: Middleware>(
+        &self,
+        provider: &M,
+        network: Network,
+        block_number: u64,
+        address: Address,
+        slots: Vec<H256>,
+    ) -> Result<EIP1186ProofResponse> {
+        let cached =
+            self.cache.as_ref().and_then(|c| c.proof(network, block_number, address, &slots));
+        if let Some(proof) = cached {
+            return Ok(proof);
+        }
+        self.check_cancelled()?;
+        let proof = get_proof(&self.rpc, provider, block_number, address, &slots).await?;
+        if let Some(cache) = &self.cache {
+            cache.put_proof(network, block_number, &proof)?;
+        }
+        Ok(proof)
+    }
+
+    /// Fetches the value of `slot` of the contract at `address` at block `block_number` with `eth_getStorageAt`, and
+    /// records it for [`AxiomChip::export_inputs`]. Only used to find which slots to prove, the value is not proven.
+    pub(crate) async fn fetch_storage_at<M: Middleware>(
+        &mut self,
+        provider: &M,
+        block_number: u64,
+        address: Address,
+        slot: H256,
+    ) -> Result<H256> {
+        self.check_cancelled()?;
+        let value = get_storage_at(&self.rpc, provider, block_number, address, slot).await?;
+        self.recorded_storage.push(StorageInput { block_number, address, slot, value });
+        Ok(value)
+    }
+
+    /// Fetches the number of the latest block, and records it for [`AxiomChip::export_inputs`].
+    pub(crate) async fn fetch_latest_block_number<M: Middleware>(
+        &mut self,
+        provider: &M,
+    ) -> Result<u64> {
+        let latest = get_block_number(&self.rpc, provider).await?;
+        self.recorded_latest_block = Some(latest);
+        Ok(latest)
+    }
+
+    /// Returns the network of this chip, detecting it from the chain ID of `provider` on first use.
+    pub(crate) async fn resolve_network<M: Middleware>(&mut self, provider: &M) -> Result<Network> {
+        if let Some(network) = self.network {
+            return Ok(network);
+        }
+        let network = network_from_chain_id(get_chain_id(&self.rpc, provider).await?)?;
+        self.network = Some(network);
+        Ok(network)
+    }
+}
+
+impl<F: Field> AxiomChip<F> {
+    /// Assigns the phase 0 witness of an RLP encoded block header, padding it to the maximum header length of `network`.
+    pub(crate) fn assign_block_header(
+        &mut self,
+        mut block_header: Vec<u8>,
+        network: Network,
+    ) -> EthBlock<F> {
+        block_header.resize(header_rlp_max_bytes(network), 0u8);
+
+        let source = QuerySource::BlockHeader { index: self.header_witness.len() };
+        let (witness, block_hash) = self.track_query(source, |axiom| {
+            if axiom.thread_per_query {
+                axiom.new_thread();
+            }
+            let witness = axiom.eth_chip().decompose_block_header_phase0(
+                &mut axiom.ctx(),
+                &mut axiom.keccak.lock(),
+                &block_header,
+                network,
+            );
+            let block_hash = pack_block_hash(&mut axiom.ctx(), axiom.gate(), &witness);
+            (witness, block_hash)
+        });
+        let block = EthBlock::new(&witness, block_hash.clone());
+        self.header_witness.push(witness);
+        self.header_hashes.push(block_hash);
+        block
+    }
+
+    /// Assigns the phase 0 witnesses of RLP encoded block headers in parallel, each in a new thread.
+    #[cfg(feature = "providers")]
+    pub(crate) fn assign_block_headers(&mut self, block_headers: Vec<Vec<u8>>, network: Network) {
+        let first_index = self.header_witness.len();
+        let first_thread = self.phase0_thread_lens().len();
+        let keccak_before = self.keccak.lock().capacity();
+        let (witnesses, block_hashes): (Vec<_>, Vec<_>) = {
+            let eth_chip = self.eth_chip();
+            let gate = self.gate();
+            parallelize_keccak_phase0(
+                &mut self.builder.lock().gate_builder,
+                &mut self.keccak.lock(),
+                block_headers,
+                |ctx, keccak, mut block_header| {
+                    block_header.resize(header_rlp_max_bytes(network), 0u8);
+                    let witness =
+                        eth_chip.decompose_block_header_phase0(ctx, keccak, &block_header, network);
+                    let block_hash = pack_block_hash(ctx, gate, &witness);
+                    (witness, block_hash)
+                },
+            )
+            .into_iter()
+            .unzip()
+        };
+        let sources = (0..witnesses.len())
+            .map(|i| QuerySource::BlockHeader { index: first_index + i })
+            .collect();
+        self.track_threads(sources, first_thread, keccak_before);
+        self.header_witness.extend(witnesses);
+        self.header_hashes.extend(block_hashes);
+    }
+
+    /// Returns the header of block `block_number` if it was already proven by a query and headers are reused, see
+    /// [`AxiomChip::set_reuse_headers`].
+    pub(crate) fn reusable_block(
+        &self,
+        network: Network,
+        block_number: u64,
+    ) -> Option<EthBlock<F>> {
+        if !self.reuse_headers {
+            return None;
+        }
+        self.proven_block(network, block_number)
+    }
+
+    /// Returns the header of block `block_number` if it was already proven by a query.
+    pub(crate) fn proven_block(&self, network: Network, block_number: u64) -> Option<EthBlock<F>> {
+        Some(match *self.proven_blocks.get(&(chain_id(network), block_number))? {
+            ProvenHeader::Header(index) => self.header_block(index),
+            ProvenHeader::Storage(index) => {
+                // the storage query already packed the hash of its block into its digest
+                let witness = &self.storage_witness[index].block_witness;
+                let (hi, lo) = self.storage_digests[index].block_hash;
+                let bytes = witness.block_hash.clone().try_into().unwrap();
+                EthBlock::new(witness, H256Assigned { bytes, hi, lo })
+            }
+        })
+    }
+
+    /// The header of the `index`-th header query.
+    pub(crate) fn header_block(&self, index: usize) -> EthBlock<F> {
+        EthBlock::new(&self.header_witness[index], self.header_hashes[index].clone())
+    }
+
+    /// Assigns the header of `block`, or returns the witness of an earlier query of the same block, including the header
+    /// proven by an `eth_getProof` query, if headers are reused, see [`AxiomChip::set_reuse_headers`].
+    pub(crate) fn assign_block(&mut self, block: &Block<H256>, network: Network) -> EthBlock<F> {
+        let block_number = block.number.expect("block has no number").as_u64();
+        self.assign_block_rlp(block_number, get_block_rlp(block), network)
+    }
+
+    /// Same as [`AxiomChip::assign_block`], for the RLP encoded header `block_header` of block `block_number`.
+    pub(crate) fn assign_block_rlp(
+        &mut self,
+        block_number: u64,
+        block_header: Vec<u8>,
+        network: Network,
+    ) -> EthBlock<F> {
+        if let Some(proven) = self.reusable_block(network, block_number) {
+            return proven;
+        }
+        let header = self.assign_block_header(block_header, network);
+        let index = ProvenHeader::Header(self.header_witness.len() - 1);
+        self.proven_blocks.insert((chain_id(network), block_number), index);
+        header
+    }
+
+    /// Same as [`AxiomChip::assign_block`] for each of `block_numbers`, where `blocks` are the ones not proven yet,
+    /// which are assigned in parallel. If headers are not reused, `blocks` are all of `block_numbers`, in order.
+    #[cfg(feature = "providers")]
+    pub(crate) fn assign_blocks(
+        &mut self,
+        blocks: &[Block<H256>],
+        network: Network,
+        block_numbers: &[u32],
+    ) -> Vec<EthBlock<F>> {
+        let first_index = self.header_witness.len();
+        self.assign_block_headers(blocks.iter().map(get_block_rlp).collect(), network);
+        for (i, block) in blocks.iter().enumerate() {
+            let block_number = block.number.expect("block has no number").as_u64();
+            let index = ProvenHeader::Header(first_index + i);
+            self.proven_blocks.insert((chain_id(network), block_number), index);
+        }
+        if !self.reuse_headers {
+            return (first_index..self.header_witness.len())
+                .map(|index| self.header_block(index))
+                .collect();
+        }
+        block_numbers
+            .iter()
+            .map(|&block_number| self.proven_block(network, block_number as u64).unwrap())
+            .collect()
+    }
+
+    /// Assigns the phase 0 witness of an account and storage proof against a block header.
+    pub(crate) fn assign_storage_input(
+        &mut self,
+        input: EthBlockStorageInput,
+        network: Network,
+    ) -> EIP1186ResponseDigest<F> {
+        let block_number = input.block_number as u64;
+        let index = self.storage_witness.len();
+        let source = QuerySource::Storage {
+            index,
+            address: input.storage.addr,
+            slots: input.storage.storage_pfs.iter().map(|(slot, _, _)| *slot).collect(),
+        };
+        let (witness, digest) = self.track_query(source, |axiom| {
+            if axiom.thread_per_query {
+                axiom.new_thread();
+            }
+            let input = input.assign(&mut axiom.ctx());
+            axiom.eth_chip().parse_eip1186_proofs_from_block_phase0(
+                &mut axiom.builder.lock().gate_builder,
+                &mut axiom.keccak.lock(),
+                input,
+                network,
+            )
+        });
         self.storage_witness.push(witness);
+        self.storage_digests.push(digest.clone());
+        self.proven_blocks
+            .entry((chain_id(network), block_number))
+            .or_insert(ProvenHeader::Storage(index));
         digest
     }
 
+    /// Creates the circuit from everything assigned so far.
+    ///
+    /// A chip created with [`AxiomChip::prover`] configures the circuit with the exact column configuration of its
+    /// pinning, so the circuit it proves is laid out like the one that was keyed, whatever `ETH_CONFIG_PARAMS` was set
+    /// to before.
+    ///
+    /// The circuit is configured from a process-global environment variable, so when circuits are created on several
+    /// threads, hold [`lock_config_params`] from creating the circuit until it has been keyed or proven.
+    ///
+    /// Panics if the degree is chosen automatically and the circuit does not fit, see [`AxiomChip::select_degree`].
     pub fn create(
+        mut self,
+        break_points: Option<RlcThreadBreakPoints>,
+    ) -> EthCircuitBuilder<F, impl FnSynthesize<F>> {
+        self.resolve_degree().expect("failed to select circuit degree");
+        let prover = self.builder.lock().witness_gen_only();
+        let config = self.config;
+        let pinned_params = self.pinning.as_ref().map(|pinning| pinning.params.clone());
+        let circuit = self.build(break_points);
+        if prover {
+            if let Some(params) = &pinned_params {
+                set_config_params(params);
+            }
+        }
+        #[cfg(not(feature = "production"))]
+        if !prover {
+            configure(&circuit, config.k, &config);
+        }
+        circuit
+    }
+
+    /// Same as [`AxiomChip::create`], with `phase1` adding constraints in the second phase after those of all queries,
+    /// see [`AxiomChip::set_phase1_hook`]. This mixes queries with custom logic over RLCs of the values that `phase1`
+    /// captures.
+    pub fn create_with_phase1(
+        mut self,
+        break_points: Option<RlcThreadBreakPoints>,
+        phase1: impl Fn(&mut RlcThreadBuilder<F>, &EthChip<F>) + Send + Sync + 'static,
+    ) -> EthCircuitBuilder<F, impl FnSynthesize<F>> {
+        self.set_phase1_hook(phase1);
+        self.create(break_points)
+    }
+
+    /// Measures the circuit built so far and returns the smallest `k` for which it fits: the lookup table and the keccak
+    /// rows must fit in 2<sup>k</sup> rows, and the circuit may use at most `auto.max_advice_columns` advice columns.
+    ///
+    /// Returns [`AxiomError::CapacityExceeded`] if the circuit does not fit for any `k <= auto.max_k`.
+    pub fn select_degree(&self, auto: AutoDegree) -> Result<usize> {
+        let AxiomChipConfig { lookup_bits, unusable_rows, .. } = self.config;
+        let circuit = self.clone().build(None);
+        (lookup_bits + 1..=auto.max_k)
+            .find(|&k| {
+                if (1 << k) <= (1 << lookup_bits) + unusable_rows {
+                    return false;
+                }
+                if self.check_keccak_capacity(k).is_err() {
+                    return false;
+                }
+                let params = configure(&circuit, k, &self.config);
+                let advice_columns =
+                    params.num_range_advice.iter().sum::<usize>() + params.num_rlc_columns;
+                advice_columns <= auto.max_advice_columns
+            })
+            .ok_or(AxiomError::CapacityExceeded { max_k: auto.max_k })
+    }
+
+    /// If the config asks for automatic degree selection, selects `k` and fixes it in the config.
+    pub(crate) fn resolve_degree(&mut self) -> Result<()> {
+        if let Some(auto) = self.config.auto_degree {
+            self.config.k = self.select_degree(auto)?;
+            self.config.auto_degree = None;
+            log::info!("Selected circuit degree k = {}", self.config.k);
+        }
+        Ok(())
+    }
+
+    /// Builds the circuit without configuring its columns.
+    pub(crate) fn build(
         self,
         break_points: Option<RlcThreadBreakPoints>,
     ) -> EthCircuitBuilder<F, impl FnSynthesize<F>> {
-        let prover = self.builder.borrow().witness_gen_only();
-        let circuit = EthCircuitBuilder::new(
+        let break_points =
+            break_points.or_else(|| self.pinning.as_ref().map(|p| p.break_points.clone()));
+        EthCircuitBuilder::new(
             self.instances,
-            self.builder.take(),
-            self.keccak,
+            self.builder.into_inner(),
+            RefCell::new(self.keccak.into_inner()),
             self.range,
             break_points,
             move |builder: &mut RlcThreadBuilder<F>,
@@ -223,84 +964,182 @@ impl<F: Field> AxiomChip<F> {
                 for witness in self.storage_witness.into_iter() {
                     eth_chip.parse_eip1186_proofs_from_block_phase1(builder, witness);
                 }
+
+                for witness in self.ommers_witness.into_iter() {
+                    witness.phase1(builder, &eth_chip);
+                }
+
+                for witness in self.mpt_witness.into_iter() {
+                    let (ctx_gate, ctx_rlc) = builder.rlc_ctx_pair();
+                    eth_chip.parse_mpt_inclusion_fixed_key_phase1((ctx_gate, ctx_rlc), witness);
+                }
+
+                for query in self.custom_queries.iter() {
+                    query.phase1(builder, &eth_chip);
+                }
+
+                if let Some(hook) = self.phase1_hook {
+                    hook.call(builder, &eth_chip);
+                }
             },
-        );
-        #[cfg(not(feature = "production"))]
-        if !prover {
-            let k = var("DEGREE").unwrap_or_else(|_| "18".to_string()).parse().unwrap();
-            let minimum_rows =
-                var("UNUSABLE_ROWS").unwrap_or_else(|_| "109".to_string()).parse().unwrap();
-            circuit.config(k, Some(minimum_rows));
-        }
-        circuit
+        )
+    }
+}
+
+/// Configures the columns of `circuit` for 2<sup>k</sup> rows, with the keccak rows per round of `config` if it sets them.
+pub(crate) fn configure<F: Field, FnPhase1: FnSynthesize<F>>(
+    circuit: &EthCircuitBuilder<F, FnPhase1>,
+    k: usize,
+    config: &AxiomChipConfig,
+) -> EthConfigParams {
+    let mut params = circuit.config(k, Some(config.unusable_rows));
+    if let Some(rows) = config.keccak_rows_per_round {
+        params.keccak_rows_per_round = rows;
+        set_config_params(&params);
     }
+    params
 }
 
 impl AxiomChip<Fr> {
-    /// Creates a circuit and runs the Halo2 `MockProver` on it. Will print out errors if the circuit does not pass.
+    /// Creates a circuit and runs the Halo2 `MockProver` on it. If the circuit does not pass, returns the failures and
+    /// prints the queries that assigned the cells where they happened.
     ///
-    /// This requires an environment variable `DEGREE` to be set, which limits the number of rows of the circuit to 2<sup>DEGREE</sup>.
-    pub fn mock(self) {
-        assert!(!self.builder.borrow().witness_gen_only());
+    /// The circuit has 2<sup>k</sup> rows, where `k` is from the [`AxiomChipConfig`] of this chip.
+    pub fn mock(mut self) -> Result<()> {
+        assert!(!self.builder.lock().witness_gen_only());
+        let _config_lock = lock_config_params();
+        self.resolve_degree()?;
+        self.check_keccak_capacity(self.config.k)?;
+        let k = self.config.k as u32;
+        for usage in self.query_usage() {
+            log::info!("{usage}");
+        }
+        let query_spans = self.query_spans.clone();
+        let thread_lens = self.phase0_thread_lens();
         let circuit = self.create(None);
-        let k = var("DEGREE").unwrap_or_else(|_| "18".to_string()).parse().unwrap();
         let time = start_timer!(|| "Mock prover");
-        MockProver::run(k, &circuit, vec![circuit.instance()]).unwrap().assert_satisfied();
+        let prover = MockProver::run(k, &circuit, vec![circuit.instance()])?;
+        if let Err(failures) = prover.verify() {
+            let break_points = circuit.break_points();
+            for line in
+                explain_failures(&query_spans, &thread_lens, &break_points.gate[0], &failures)
+            {
+                println!("{line}");
+            }
+            return Err(AxiomError::Constraints(failures));
+        }
         end_timer!(time);
         println!("Mock prover passed!");
+        Ok(())
     }
 
-    /// Creates a circuit and runs the full Halo2 proving process on it.
+    /// Creates a circuit and generates its proving key, which contains the verifying key.
+    /// The KZG parameters for the circuit's degree are read with `srs`.
+    ///
+    /// Returns the proving key together with the pinning of the circuit, which must be reused when proving with this key.
+    pub fn gen_keys(mut self, srs: &SrsManager) -> Result<(ProvingKey<G1Affine>, AxiomPinning)> {
+        assert!(!self.builder.lock().witness_gen_only());
+        let _config_lock = lock_config_params();
+        self.resolve_degree()?;
+        let cancellation = self.cancellation.clone().unwrap_or_default();
+        let config = self.config;
+        let k = config.k;
+        self.check_keccak_capacity(k)?;
+        let circuit = self.create(None);
+        let eth_config = configure(&circuit, k, &config);
+
+        let params = srs.params(k as u32)?;
+        cancellation.check()?;
+        let vk_time = start_timer!(|| "Generating verifying key");
+        let vk = keygen_vk(&params, &circuit)?;
+        end_timer!(vk_time);
+        cancellation.check()?;
+        let pk_time = start_timer!(|| "Generating proving key");
+        let pk = keygen_pk(&params, vk, &circuit)?;
+        end_timer!(pk_time);
+
+        Ok((pk, AxiomPinning { params: eth_config, break_points: circuit.break_points() }))
+    }
+
+    /// Creates a circuit and runs the full Halo2 proving process on it, with the KZG parameters read with `srs`.
     /// Will time the generation of verify key & proving key. It will then run the prover on the given circuit.
-    /// Finally the verifier will verify the proof. The verifier will panic if the proof is invalid.
+    /// Finally the verifier will verify the proof, returning [`AxiomError::InvalidProof`] if it is invalid.
+    ///
+    /// Returns the proof bytes, public instances, and verifying key so the proof can be persisted.
     ///
     /// Warning: This may be memory and compute intensive.
-    pub fn prove(self) {
-        assert!(!self.builder.borrow().witness_gen_only());
+    pub fn prove(mut self, srs: &SrsManager) -> Result<ProofArtifacts> {
+        assert!(!self.builder.lock().witness_gen_only());
+        let _config_lock = lock_config_params();
+        self.resolve_degree()?;
+        let options = self.proving_options;
+        let cancellation = self.cancellation.clone().unwrap_or_default();
+        let config = self.config;
+        let k = config.k;
+        self.check_keccak_capacity(k)?;
+        if let Some(limit) = options.max_memory {
+            let estimated = self.estimated_peak_memory(k);
+            if estimated > limit {
+                return Err(AxiomError::MemoryExceeded { estimated, limit });
+            }
+        }
+        let has_accumulator = self.has_accumulator;
         let circuit = self.create(None);
-        let k = var("DEGREE").unwrap_or_else(|_| "18".to_string()).parse().unwrap();
-        let minimum_rows =
-            var("UNUSABLE_ROWS").unwrap_or_else(|_| "109".to_string()).parse().unwrap();
-        circuit.config(k, Some(minimum_rows));
+        configure(&circuit, k, &config);
 
-        let params = gen_srs(k as u32);
+        let params = srs.params(k as u32)?;
+        cancellation.check()?;
         let vk_time = start_timer!(|| "Generating verifying key");
-        let vk = keygen_vk(&params, &circuit).expect("vk generation failed");
+        let vk = keygen_vk(&params, &circuit)?;
         end_timer!(vk_time);
+        cancellation.check()?;
         let pk_time = start_timer!(|| "Generating proving key");
-        let pk = keygen_pk(&params, vk, &circuit).expect("pk generation failed");
+        let pk = keygen_pk(&params, vk, &circuit)?;
         end_timer!(pk_time);
+        cancellation.check()?;
 
         // For those learning: you should technically create a separate circuit for the
         // proof (vs keygen) but for memory efficiency we just use the same one
-        let pf_time = start_timer!(|| "Creating KZG proof using SHPLONK multi-open scheme");
         let instance = circuit.instance();
-        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
-        create_proof::<
-            KZGCommitmentScheme<Bn256>,
-            ProverSHPLONK<'_, Bn256>,
-            Challenge255<G1Affine>,
-            _,
-            Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
-            _,
-        >(&params, &pk, &[circuit], &[&[&instance]], OsRng, &mut transcript)
-        .expect("proof generation failed");
-        let proof = transcript.finalize();
-        end_timer!(pf_time);
-
-        let strategy = SingleStrategy::new(&params);
-        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
-        let verify_time = start_timer!(|| "verify");
-        verify_proof::<
-            KZGCommitmentScheme<Bn256>,
-            VerifierSHPLONK<'_, Bn256>,
-            Challenge255<G1Affine>,
-            Blake2bRead<&[u8], G1Affine, Challenge255<G1Affine>>,
-            SingleStrategy<'_, Bn256>,
-        >(&params, pk.get_vk(), strategy, &[&[&instance]], &mut transcript)
-        .unwrap();
-        end_timer!(verify_time);
-
+        let artifacts = prove_circuit(&params, &pk, circuit, instance, options, has_accumulator)?;
         println!("Congratulations! Your ZK proof is valid!");
+        Ok(artifacts)
+    }
+
+    /// Creates a circuit and proves it with an existing proving key, skipping key generation.
+    ///
+    /// The chip must have been created with [`AxiomChip::prover`] from the pinning returned by [`AxiomChip::gen_keys`].
+    /// Returns [`AxiomError::InvalidProof`] if the proof is invalid.
+    pub fn prove_with_pk(
+        self,
+        params: &ParamsKZG<Bn256>,
+        pk: &ProvingKey<G1Affine>,
+    ) -> Result<ProofArtifacts> {
+        assert!(self.pinning.is_some(), "proving with an existing key requires a pinning");
+        self.check_cancelled()?;
+        let _config_lock = lock_config_params();
+        let options = self.proving_options;
+        let has_accumulator = self.has_accumulator;
+        let circuit = self.create(None);
+        let instance = circuit.instance();
+        prove_circuit(params, pk, circuit, instance, options, has_accumulator)
+    }
+}
+
+/// Packs the block hash of the header `witness` into hi-lo halves. The hash bytes are keccak outputs, which are already
+/// constrained to be bytes.
+fn pack_block_hash<F: Field>(
+    ctx: &mut Context<F>,
+    gate: &GateChip<F>,
+    witness: &EthBlockHeaderTraceWitness<F>,
+) -> H256Assigned<F> {
+    H256Assigned::from_bytes(ctx, gate, witness.block_hash.clone().try_into().unwrap())
+}
+
+/// The maximum byte length of an RLP encoded block header on `network`.
+pub(crate) fn header_rlp_max_bytes(network: Network) -> usize {
+    match network {
+        Network::Mainnet => MAINNET_BLOCK_HEADER_RLP_MAX_BYTES,
+        Network::Goerli => GOERLI_BLOCK_HEADER_RLP_MAX_BYTES,
     }
 }
@@ -0,0 +1,134 @@
+use axiom_eth::{
+    mpt::MPTFixedKeyInput,
+    providers::get_block_rlp,
+    rlp::builder::RlcThreadBuilder,
+    storage::{EIP1186ResponseDigest, EthBlockStorageInput, EthStorageInput},
+    Field, Network,
+};
+use ethers_core::{
+    types::{Address, Block, H256, H64, U256},
+    utils::rlp::RlpStream,
+};
+
+use crate::{
+    containers::EthBlock,
+    scaffold::{AxiomChip, ACCOUNT_PROOF_MAX_DEPTH, STORAGE_PROOF_MAX_DEPTH},
+};
+
+/// The maximum byte length of an RLP encoded account `[nonce, balance, storage_root, code_hash]`.
+pub(crate) const ACCOUNT_RLP_MAX_BYTES: usize = 114;
+/// The maximum byte length of an RLP encoded storage value.
+pub(crate) const STORAGE_VALUE_RLP_MAX_BYTES: usize = 33;
+
+/// The shape of the queries made by a circuit.
+///
+/// The layout of an `AxiomChip` circuit only depends on the number and kind of queries, not on the
+/// chain data returned by them, so this is all that is needed to generate proving and verifying keys.
+#[derive(Clone, Debug)]
+pub struct QuerySchema {
+    pub network: Network,
+    /// The number of `eth_getBlockByNumber` queries.
+    pub num_headers: usize,
+    /// The number of storage slots in each `eth_getProof` query.
+    pub storage_slots: Vec<usize>,
+}
+
+/// The results of the queries declared in a [`QuerySchema`], in declaration order.
+#[derive(Clone, Debug)]
+pub struct QueryResults<F: Field> {
+    pub blocks: Vec<EthBlock<F>>,
+    pub storage: Vec<EIP1186ResponseDigest<F>>,
+}
+
+impl<F: Field> AxiomChip<F> {
+    /// Creates a chip for key generation where every query declared in `schema` is assigned with
+    /// dummy inputs of the correct shape. No RPC provider is needed.
+    ///
+    /// Build the rest of your circuit on top of the returned query results exactly as you would with live data,
+    /// then call [`AxiomChip::gen_keys`]. Every header is assigned separately, so the keys only fit circuits that do not
+    /// reuse headers, see [`AxiomChip::set_reuse_headers`].
+    pub fn keygen(schema: &QuerySchema) -> (Self, QueryResults<F>) {
+        let mut axiom = Self::new(RlcThreadBuilder::keygen());
+        let blocks = (0..schema.num_headers)
+            .map(|_| axiom.assign_block_header(get_block_rlp(&dummy_block()), schema.network))
+            .collect();
+        let storage = schema
+            .storage_slots
+            .iter()
+            .map(|&num_slots| {
+                axiom.assign_storage_input(dummy_storage_input(num_slots), schema.network)
+            })
+            .collect();
+        (axiom, QueryResults { blocks, storage })
+    }
+}
+
+/// A block with every field needed for RLP encoding the header filled in.
+pub(crate) fn dummy_block() -> Block<H256> {
+    Block {
+        hash: Some(H256::zero()),
+        number: Some(0u64.into()),
+        mix_hash: Some(H256::zero()),
+        nonce: Some(H64::zero()),
+        base_fee_per_gas: Some(U256::zero()),
+        withdrawals_root: Some(H256::zero()),
+        ..Default::default()
+    }
+}
+
+/// A single leaf MPT proof of `value` at `path`. The proof is not valid against any root,
+/// it only has the correct shape.
+fn dummy_mpt_input(
+    value: Vec<u8>,
+    value_max_byte_len: usize,
+    max_depth: usize,
+) -> MPTFixedKeyInput {
+    let path = H256::zero();
+    let mut encoded_path = vec![0x20];
+    encoded_path.extend_from_slice(path.as_bytes());
+    let mut leaf = RlpStream::new_list(2);
+    leaf.append(&encoded_path).append(&value);
+    MPTFixedKeyInput {
+        path,
+        value,
+        root_hash: H256::zero(),
+        proof: vec![leaf.out().to_vec()],
+        value_max_byte_len,
+        max_depth,
+        slot_is_empty: false,
+    }
+}
+
+fn dummy_storage_input(num_slots: usize) -> EthBlockStorageInput {
+    let block = dummy_block();
+    let block_header = get_block_rlp(&block);
+
+    let mut acct_state = RlpStream::new_list(4);
+    acct_state
+        .append(&U256::zero())
+        .append(&U256::zero())
+        .append(&H256::zero())
+        .append(&H256::zero());
+    let acct_pf =
+        dummy_mpt_input(acct_state.out().to_vec(), ACCOUNT_RLP_MAX_BYTES, ACCOUNT_PROOF_MAX_DEPTH);
+    let storage_pfs = (0..num_slots)
+        .map(|_| {
+            let value = ethers_core::utils::rlp::encode(&U256::zero()).to_vec();
+            let pf = dummy_mpt_input(value, STORAGE_VALUE_RLP_MAX_BYTES, STORAGE_PROOF_MAX_DEPTH);
+            (H256::zero(), U256::zero(), pf)
+        })
+        .collect();
+
+    EthBlockStorageInput {
+        block,
+        block_number: 0,
+        block_hash: H256::zero(),
+        block_header,
+        storage: EthStorageInput {
+            addr: Address::zero(),
+            acct_state: vec![],
+            acct_pf,
+            storage_pfs,
+        },
+    }
+}
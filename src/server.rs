@@ -0,0 +1,314 @@
+//! An HTTP proving service, enabled by the `server` feature.
+//!
+//! - `POST /jobs` with a JSON [`QuerySpec`] body starts a proof job and returns its `id`.
+//! - `GET /jobs/{id}` returns the [stage](JobStatus) of the job, and once it is done, its proof and public instances
+//!   in hex.
+//! - `DELETE /jobs/{id}` cancels the job, which then fails at its next cancellation check, see
+//!   [`AxiomChip::set_cancellation`].
+//!
+//! Keys are generated the first time a spec of a given shape is submitted and reused for all later specs of the same
+//! shape, i.e., with the same number of queries, blocks, and slots and the same outputs. Jobs of a shape whose keys
+//! are being generated wait for them instead of generating them again.
+//!
+//! At most `max_concurrency` jobs generate keys or prove at a time, see [`ProverServer::new`]; the others wait in
+//! their previous stage. Finished jobs are forgotten [`JOB_TTL`] after they finish.
+
+use std::{
+    collections::HashMap,
+    io,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use ethers_providers::{Http, Provider};
+use halo2_base::halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::ProvingKey,
+    poly::kzg::commitment::ParamsKZG,
+};
+use parking_lot::Mutex;
+use serde::{Serialize, Serializer};
+use tokio::sync::{watch, OnceCell, Semaphore, SemaphorePermit};
+
+use crate::{
+    cancel::CancellationToken,
+    config::AxiomChipConfig,
+    error::{AxiomError, Result},
+    keys::AxiomPinning,
+    proof::{ProofArtifacts, ProvingOptions},
+    scaffold::AxiomChip,
+    spec::{QuerySpec, SpecOutput},
+    srs::SrsManager,
+};
+
+/// The state shared by all requests to the server.
+#[derive(Clone)]
+pub struct ProverServer {
+    provider: Provider<Http>,
+    config: AxiomChipConfig,
+    options: ProvingOptions,
+    srs: SrsManager,
+    keys: Arc<Mutex<HashMap<String, Arc<OnceCell<Arc<CircuitKeys>>>>>>,
+    jobs: Arc<Mutex<HashMap<u64, Job>>>,
+    next_id: Arc<AtomicU64>,
+    /// Limits the number of jobs generating keys or proving at the same time.
+    permits: Arc<Semaphore>,
+}
+
+/// How long a finished job can still be queried before it is removed.
+pub const JOB_TTL: Duration = Duration::from_secs(60 * 60);
+
+struct Job {
+    status: watch::Receiver<JobStatus>,
+    cancellation: CancellationToken,
+    finished_at: Option<Instant>,
+}
+
+/// The keys of one circuit shape.
+struct CircuitKeys {
+    params: ParamsKZG<Bn256>,
+    pk: ProvingKey<G1Affine>,
+    pinning: AxiomPinning,
+}
+
+/// The status of a proof job, as returned by `GET /jobs/{id}`.
+///
+/// The first phase of witness generation runs while the chain data is fetched, and the second phase runs inside the
+/// halo2 prover, so it is part of `Proving`.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum JobStatus {
+    /// Fetching chain data and assigning the witnesses of the queries.
+    WitnessGen,
+    /// Generating the keys of a spec shape seen for the first time.
+    Keygen,
+    Proving,
+    Done {
+        #[serde(serialize_with = "serialize_hex")]
+        proof: Vec<u8>,
+        /// The public instances, each as its 32 byte little endian representation.
+        #[serde(serialize_with = "serialize_hex_list")]
+        instances: Vec<[u8; 32]>,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+impl JobStatus {
+    /// Whether the job has stopped, successfully or not.
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Done { .. } | Self::Failed { .. })
+    }
+}
+
+#[derive(Serialize)]
+struct JobId {
+    id: u64,
+}
+
+impl ProverServer {
+    /// Creates a server that fetches chain data from `provider` and proves with circuits of the given configuration.
+    /// KZG parameters are read with `srs`. At most `max_concurrency` jobs generate keys or prove at a time; each proof
+    /// already uses all cores, so a small value, e.g. 1, is usually best. Keygen and proving also hold
+    /// [`lock_config_params`](crate::keys::lock_config_params), since circuits are configured through a process-global
+    /// environment variable, so for now they never overlap whatever `max_concurrency` is.
+    pub fn new(
+        provider: Provider<Http>,
+        config: AxiomChipConfig,
+        options: ProvingOptions,
+        srs: SrsManager,
+        max_concurrency: usize,
+    ) -> Self {
+        assert!(max_concurrency > 0, "max_concurrency must be positive");
+        Self {
+            provider,
+            config,
+            options,
+            srs,
+            keys: Default::default(),
+            jobs: Default::default(),
+            next_id: Default::default(),
+            permits: Arc::new(Semaphore::new(max_concurrency)),
+        }
+    }
+
+    pub fn router(self) -> Router {
+        Router::new()
+            .route("/jobs", post(submit))
+            .route("/jobs/:id", get(status).delete(cancel))
+            .with_state(self)
+    }
+
+    /// Serves the HTTP API on `addr` until the server fails.
+    pub async fn serve(self, addr: SocketAddr) -> io::Result<()> {
+        log::info!("Serving proof jobs on {addr}");
+        axum::Server::bind(&addr)
+            .serve(self.router().into_make_service())
+            .await
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+    }
+
+    /// Starts a proof job for `spec` in the background and returns its id.
+    ///
+    /// Returns [`AxiomError::InvalidInput`] without starting a job if the spec is too large, see [`QuerySpec::check`].
+    pub fn submit(&self, spec: QuerySpec) -> Result<u64> {
+        spec.check()?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = watch::channel(JobStatus::WitnessGen);
+        let cancellation = CancellationToken::new();
+        let mut jobs = self.jobs.lock();
+        jobs.retain(|_, job| {
+            job.finished_at.map_or(true, |finished_at| finished_at.elapsed() < JOB_TTL)
+        });
+        jobs.insert(id, Job { status: rx, cancellation: cancellation.clone(), finished_at: None });
+        drop(jobs);
+        let server = self.clone();
+        tokio::spawn(async move {
+            let status = match server.run(spec, &tx, cancellation).await {
+                Ok(artifacts) => JobStatus::Done {
+                    proof: artifacts.proof,
+                    instances: artifacts.instances.iter().map(|x| x.to_bytes()).collect(),
+                },
+                Err(err) => {
+                    log::error!("Proof job {id} failed: {err}");
+                    JobStatus::Failed { error: err.to_string() }
+                }
+            };
+            tx.send_replace(status);
+            if let Some(job) = server.jobs.lock().get_mut(&id) {
+                job.finished_at = Some(Instant::now());
+            }
+        });
+        Ok(id)
+    }
+
+    /// Returns a receiver of the status of job `id`, which is updated whenever the job moves to the next stage.
+    pub fn job(&self, id: u64) -> Option<watch::Receiver<JobStatus>> {
+        self.jobs.lock().get(&id).map(|job| job.status.clone())
+    }
+
+    /// Cancels job `id`. Returns `false` if there is no such job.
+    pub fn cancel(&self, id: u64) -> bool {
+        self.jobs.lock().get(&id).map(|job| job.cancellation.cancel()).is_some()
+    }
+
+    /// Generates keys for the shape of `spec` if there are none yet, then proves it.
+    async fn run(
+        &self,
+        spec: QuerySpec,
+        status: &watch::Sender<JobStatus>,
+        cancellation: CancellationToken,
+    ) -> Result<ProofArtifacts> {
+        let shape = spec_shape(&spec);
+        // If another job is generating the keys of this shape, wait for it. If it fails, the next waiting job tries.
+        let cell = self.keys.lock().entry(shape).or_default().clone();
+        let keys = cell
+            .get_or_try_init(|| async {
+                let mut axiom = AxiomChip::<Fr>::with_config(self.config);
+                axiom.set_cancellation(cancellation.clone());
+                axiom.load_spec_async(&self.provider, &spec).await?;
+                let _permit = self.acquire().await?;
+                status.send_replace(JobStatus::Keygen);
+                let srs = self.srs.clone();
+                let keys = spawn_blocking(move || {
+                    let (pk, pinning) = axiom.gen_keys(&srs)?;
+                    let params = srs.params(pinning.params.degree)?;
+                    Ok(CircuitKeys { params, pk, pinning })
+                })
+                .await?;
+                Ok::<_, AxiomError>(Arc::new(keys))
+            })
+            .await?
+            .clone();
+
+        let mut axiom = AxiomChip::<Fr>::prover(keys.pinning.clone());
+        axiom.set_proving_options(self.options);
+        axiom.set_cancellation(cancellation);
+        status.send_replace(JobStatus::WitnessGen);
+        axiom.load_spec_async(&self.provider, &spec).await?;
+        let _permit = self.acquire().await?;
+        status.send_replace(JobStatus::Proving);
+        spawn_blocking(move || axiom.prove_with_pk(&keys.params, &keys.pk)).await
+    }
+
+    /// Waits until fewer than `max_concurrency` jobs are generating keys or proving.
+    async fn acquire(&self) -> Result<SemaphorePermit<'_>> {
+        self.permits
+            .acquire()
+            .await
+            .map_err(|err| AxiomError::Io(io::Error::new(io::ErrorKind::Other, err)))
+    }
+}
+
+/// Runs the compute heavy `f` on the blocking thread pool.
+async fn spawn_blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|err| AxiomError::Io(io::Error::new(io::ErrorKind::Other, err)))?
+}
+
+/// Identifies the circuits keyed by the same proving key: everything in the spec except the addresses, slots, and
+/// block numbers, which are only witnesses.
+fn spec_shape(spec: &QuerySpec) -> String {
+    let shape: Vec<(usize, usize, &[SpecOutput])> = spec
+        .storage
+        .iter()
+        .map(|query| (query.blocks.num_blocks(), query.slots.len(), &query.expose[..]))
+        .collect();
+    serde_json::to_string(&shape).unwrap()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn serialize_hex<S: Serializer>(
+    bytes: &[u8],
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str(&hex(bytes))
+}
+
+fn serialize_hex_list<S: Serializer>(
+    words: &[[u8; 32]],
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_seq(words.iter().map(|word| hex(word)))
+}
+
+async fn submit(
+    State(server): State<ProverServer>,
+    Json(spec): Json<QuerySpec>,
+) -> std::result::Result<Json<JobId>, (StatusCode, String)> {
+    let id = server.submit(spec).map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+    Ok(Json(JobId { id }))
+}
+
+async fn cancel(State(server): State<ProverServer>, Path(id): Path<u64>) -> StatusCode {
+    if server.cancel(id) {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+async fn status(
+    State(server): State<ProverServer>,
+    Path(id): Path<u64>,
+) -> std::result::Result<Json<JobStatus>, StatusCode> {
+    let status = server.job(id).ok_or(StatusCode::NOT_FOUND)?.borrow().clone();
+    Ok(Json(status))
+}
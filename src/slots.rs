@@ -0,0 +1,341 @@
+//! The Solidity storage layout in the circuit: the slots of mapping entries and struct fields, and the fields packed into
+//! a slot.
+//!
+//! A storage proof only authenticates the value of the slot it was queried for, so helpers that read a contract's
+//! variables derive the slot here and constrain it to be the slot of the proof.
+
+use axiom_eth::{storage::EIP1186ResponseDigest, Field};
+use ethers_core::{
+    types::{Address, H256, U256},
+    utils::keccak256,
+};
+use halo2_base::{
+    gates::{GateInstructions, RangeInstructions},
+    AssignedValue, Context,
+    QuantumCell::Constant,
+};
+
+use crate::{
+    abi::{abi_encode, AbiValue},
+    containers::H256Assigned,
+    scaffold::AxiomChip,
+};
+
+/// The slot of the entry for `key` of a mapping at `slot`, outside of the circuit, see [`AxiomChip::mapping_slot`].
+pub fn mapping_slot_value(key: H256, slot: H256) -> H256 {
+    H256(keccak256([key.0, slot.0].concat()))
+}
+
+/// The slot of the entry of a nested mapping at `slot` keyed by the addresses `keys` in order, outside of the circuit,
+/// see [`AxiomChip::address_mapping_slot`].
+pub fn address_mapping_slot_value(slot: H256, keys: &[Address]) -> H256 {
+    keys.iter().fold(slot, |slot, key| mapping_slot_value((*key).into(), slot))
+}
+
+/// The slot `offset` slots after `slot`, outside of the circuit, see [`AxiomChip::slot_offset`].
+pub fn slot_offset_value(slot: H256, offset: u64) -> H256 {
+    let mut bytes = [0u8; 32];
+    (U256::from_big_endian(&slot.0) + offset).to_big_endian(&mut bytes);
+    H256(bytes)
+}
+
+/// Assigns the constant 32 byte word `word`, e.g. the slot of a state variable.
+pub fn constant_word<F: Field>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    word: H256,
+) -> H256Assigned<F> {
+    let bytes = word.0.map(|byte| ctx.load_constant(F::from(byte as u64)));
+    H256Assigned::from_bytes(ctx, gate, bytes)
+}
+
+/// Splits `value`, which must fit in `num_bits <= 128` bits, into its top `num_bits - low_bits` bits and its lowest
+/// `low_bits` bits, e.g. to read the fields packed into one half of a slot.
+pub fn split_bits<F: Field>(
+    ctx: &mut Context<F>,
+    range: &impl RangeInstructions<F>,
+    value: AssignedValue<F>,
+    low_bits: usize,
+    num_bits: usize,
+) -> (AssignedValue<F>, AssignedValue<F>) {
+    assert!(low_bits < num_bits && num_bits <= 128);
+    let raw = value.value().get_lower_128();
+    let [high, low] = [raw >> low_bits, raw & ((1u128 << low_bits) - 1)];
+    let [high, low] = [high, low].map(|x| ctx.load_witness(F::from_u128(x)));
+    range.range_check(ctx, high, num_bits - low_bits);
+    range.range_check(ctx, low, low_bits);
+    let shift = Constant(range.gate().pow_of_two()[low_bits]);
+    let recomposed = range.gate().mul_add(ctx, high, shift, low);
+    ctx.constrain_equal(&recomposed, &value);
+    (high, low)
+}
+
+/// Reads the field of `num_bits <= 128` bits starting `offset` bits from the least significant end of the 32 byte
+/// word `word`, given as hi-lo 128 bit halves, e.g. a member of a packed struct. Solidity packs the members of a slot
+/// from its least significant end.
+pub fn packed_field<F: Field>(
+    ctx: &mut Context<F>,
+    range: &impl RangeInstructions<F>,
+    word: [AssignedValue<F>; 2],
+    offset: usize,
+    num_bits: usize,
+) -> AssignedValue<F> {
+    assert!(0 < num_bits && num_bits <= 128 && offset + num_bits <= 256);
+    let [hi, lo] = word;
+    let end = offset + num_bits;
+    if end <= 128 {
+        half_bits(ctx, range, lo, offset, end)
+    } else if offset >= 128 {
+        half_bits(ctx, range, hi, offset - 128, end - 128)
+    } else {
+        let low = half_bits(ctx, range, lo, offset, 128);
+        let high = half_bits(ctx, range, hi, 0, end - 128);
+        let shift = Constant(range.gate().pow_of_two()[128 - offset]);
+        range.gate().mul_add(ctx, high, shift, low)
+    }
+}
+
+/// The value of a storage slot as hi-lo 128 bit halves, see [`AxiomChip::slot_value`]. Solidity stores a value type
+/// that fills a slot by itself right aligned, i.e. in the least significant bits, so each of the methods decoding it as
+/// a type constrains the bits above the type to be zero.
+#[derive(Clone, Copy, Debug)]
+pub struct SlotValue<F: Field> {
+    pub hi: AssignedValue<F>,
+    pub lo: AssignedValue<F>,
+}
+
+impl<F: Field> From<[AssignedValue<F>; 2]> for SlotValue<F> {
+    fn from([hi, lo]: [AssignedValue<F>; 2]) -> Self {
+        Self { hi, lo }
+    }
+}
+
+impl<F: Field> SlotValue<F> {
+    /// Decodes the value as an `address`, constraining it to fit in 160 bits.
+    pub fn as_address(
+        &self,
+        ctx: &mut Context<F>,
+        range: &impl RangeInstructions<F>,
+    ) -> AssignedValue<F> {
+        self.as_uint(ctx, range, 160)
+    }
+
+    /// Decodes the value as a `uint<n_bits>`, constraining it to fit in `n_bits` bits. Panics unless
+    /// `0 < n_bits <= 248`, since a `uint256` may not fit in a field element; use [`SlotValue::as_bytes32`] for it.
+    pub fn as_uint(
+        &self,
+        ctx: &mut Context<F>,
+        range: &impl RangeInstructions<F>,
+        n_bits: usize,
+    ) -> AssignedValue<F> {
+        assert!(0 < n_bits && n_bits <= 248);
+        let gate = range.gate();
+        if n_bits <= 128 {
+            gate.assert_is_const(ctx, &self.hi, &F::zero());
+            range.range_check(ctx, self.lo, n_bits);
+            self.lo
+        } else {
+            range.range_check(ctx, self.hi, n_bits - 128);
+            range.range_check(ctx, self.lo, 128);
+            gate.mul_add(ctx, self.hi, Constant(gate.pow_of_two()[128]), self.lo)
+        }
+    }
+
+    /// Decodes the value as a `bool`, constraining it to be 0 or 1.
+    pub fn as_bool(
+        &self,
+        ctx: &mut Context<F>,
+        range: &impl RangeInstructions<F>,
+    ) -> AssignedValue<F> {
+        let gate = range.gate();
+        gate.assert_is_const(ctx, &self.hi, &F::zero());
+        gate.assert_bit(ctx, self.lo);
+        self.lo
+    }
+
+    /// Decodes the value as a `bytes32` or `uint256`, as hi-lo 128 bit halves, constraining each half to 128 bits.
+    pub fn as_bytes32(
+        &self,
+        ctx: &mut Context<F>,
+        range: &impl RangeInstructions<F>,
+    ) -> [AssignedValue<F>; 2] {
+        range.range_check(ctx, self.hi, 128);
+        range.range_check(ctx, self.lo, 128);
+        [self.hi, self.lo]
+    }
+}
+
+/// The bits `start..end` of the 128 bit value `half`.
+fn half_bits<F: Field>(
+    ctx: &mut Context<F>,
+    range: &impl RangeInstructions<F>,
+    half: AssignedValue<F>,
+    start: usize,
+    end: usize,
+) -> AssignedValue<F> {
+    let below_end = if end < 128 { split_bits(ctx, range, half, end, 128).1 } else { half };
+    if start > 0 {
+        split_bits(ctx, range, below_end, start, end).0
+    } else {
+        below_end
+    }
+}
+
+impl<F: Field> AxiomChip<F> {
+    /// The slot of the entry for `key` of a mapping at `slot`, i.e. `keccak256(key . slot)`, where `key` is the 32 byte
+    /// ABI encoding of a value type key, see [`abi_encode`](crate::abi::abi_encode).
+    pub fn mapping_slot(
+        &self,
+        key: &[AssignedValue<F>],
+        slot: &H256Assigned<F>,
+    ) -> H256Assigned<F> {
+        assert_eq!(key.len(), 32, "mapping keys must be ABI encoded to 32 bytes");
+        self.keccak_word(key.iter().chain(&slot.bytes).copied().collect())
+    }
+
+    /// The slot of the entry of a nested mapping at `slot` keyed by the addresses `keys` in order, e.g.
+    /// `allowances[owner][spender]`, or `slot` itself if there are no keys.
+    pub fn address_mapping_slot(
+        &self,
+        slot: H256Assigned<F>,
+        keys: &[AssignedValue<F>],
+    ) -> H256Assigned<F> {
+        keys.iter().fold(slot, |slot, key| {
+            let key = {
+                let range = self.range();
+                abi_encode(&mut self.ctx(), range, &[AbiValue::Address(*key)])
+            };
+            self.mapping_slot(&key, &slot)
+        })
+    }
+
+    /// The slot `slot` of a state variable, as a constant.
+    pub(crate) fn constant_slot(&self, slot: u64) -> H256Assigned<F> {
+        constant_word(&mut self.ctx(), self.gate(), H256::from_low_u64_be(slot))
+    }
+
+    /// The keccak hash of the bytes `input`, whose length is fixed by the circuit.
+    pub(crate) fn keccak_word(&self, input: Vec<AssignedValue<F>>) -> H256Assigned<F> {
+        let gate = self.gate();
+        let mut ctx = self.ctx();
+        let bytes = input.iter().map(|byte| byte.value().get_lower_32() as u8).collect();
+        let mut keccak = self.keccak.lock();
+        let index = keccak.keccak_fixed_len(&mut ctx, gate, input, Some(bytes));
+        let hash = keccak.fixed_len_queries[index].output_assigned.clone();
+        H256Assigned::from_bytes(&mut ctx, gate, hash.try_into().unwrap())
+    }
+
+    /// The slot `offset` slots after `slot`, e.g. of a field of a struct stored at `slot`, as hi-lo 128 bit halves.
+    ///
+    /// The hi half does not wrap around 2<sup>256</sup>, which only matters for slots that are not hashes.
+    pub fn slot_offset(&self, slot: &H256Assigned<F>, offset: u64) -> [AssignedValue<F>; 2] {
+        let offset = self.ctx().load_constant(F::from(offset));
+        self.slot_index(slot, offset)
+    }
+
+    /// The slot `index` slots after `slot`, where `index` is a witness of at most 64 bits, e.g. of an element of a
+    /// dynamic array whose data starts at `slot`, as hi-lo 128 bit halves. Like [`AxiomChip::slot_offset`], the hi
+    /// half does not wrap around.
+    pub fn slot_index(
+        &self,
+        slot: &H256Assigned<F>,
+        index: AssignedValue<F>,
+    ) -> [AssignedValue<F>; 2] {
+        let range = self.range();
+        let gate = range.gate();
+        let mut ctx = self.ctx();
+        range.range_check(&mut ctx, index, 64);
+        let (lo, overflow) =
+            slot.lo.value().get_lower_128().overflowing_add(index.value().get_lower_128());
+        let carry = ctx.load_witness(F::from(overflow as u64));
+        gate.assert_bit(&mut ctx, carry);
+        let lo = ctx.load_witness(F::from_u128(lo));
+        range.range_check(&mut ctx, lo, 128);
+        // slot.lo + index == lo + carry * 2^128
+        let sum = gate.add(&mut ctx, slot.lo, index);
+        let recomposed = gate.mul_add(&mut ctx, carry, Constant(gate.pow_of_two()[128]), lo);
+        ctx.constrain_equal(&sum, &recomposed);
+        let hi = gate.add(&mut ctx, slot.hi, carry);
+        [hi, lo]
+    }
+
+    /// Whether the `index`-th slot of `digest` is in the storage trie of an account in the state trie. The storage proof
+    /// of a missing slot, or of any slot of a missing account, is an exclusion proof that does not constrain its value.
+    pub fn slot_exists(&self, digest: &EIP1186ResponseDigest<F>, index: usize) -> AssignedValue<F> {
+        let gate = self.gate();
+        let mut ctx = self.ctx();
+        let is_empty = gate.or(&mut ctx, digest.address_is_empty, digest.slot_is_empty[index]);
+        gate.not(&mut ctx, is_empty)
+    }
+
+    /// The value of the `index`-th slot of `digest` as hi-lo 128 bit halves, which is zero if the slot or its account
+    /// does not exist, like `SLOAD`. Helpers that read a contract's variables use this instead of the raw value of the
+    /// digest, which is only meaningful if [`AxiomChip::slot_exists`].
+    pub fn storage_value(
+        &self,
+        digest: &EIP1186ResponseDigest<F>,
+        index: usize,
+    ) -> [AssignedValue<F>; 2] {
+        let exists = self.slot_exists(digest, index);
+        let gate = self.gate();
+        let mut ctx = self.ctx();
+        digest.slots_values[index].1.map(|half| gate.mul(&mut ctx, half, exists))
+    }
+
+    /// Same as [`AxiomChip::storage_value`], to decode as a Solidity type, e.g. with [`SlotValue::as_address`].
+    pub fn slot_value(&self, digest: &EIP1186ResponseDigest<F>, index: usize) -> SlotValue<F> {
+        self.storage_value(digest, index).into()
+    }
+
+    /// Constrains the slot of the `index`-th storage proof of `digest` to be `slot`, given as hi-lo 128 bit halves.
+    pub fn constrain_slot(
+        &self,
+        digest: &EIP1186ResponseDigest<F>,
+        index: usize,
+        slot: [AssignedValue<F>; 2],
+    ) {
+        let mut ctx = self.ctx();
+        for (a, b) in digest.slots_values[index].0.iter().zip(slot.iter()) {
+            ctx.constrain_equal(a, b);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mapping_slot_value() {
+        // keccak256 of 64 zero bytes
+        let expected: H256 =
+            "0xad3228b676f7d3cd4284a5443f17f1962b36e491b30a40b2405849e597ba5fb5".parse().unwrap();
+        assert_eq!(mapping_slot_value(H256::zero(), H256::zero()), expected);
+    }
+
+    #[test]
+    fn test_address_mapping_slot_value() {
+        let slot = H256::from_low_u64_be(3);
+        let [owner, spender] = [0xabcd, 0x1234].map(Address::from_low_u64_be);
+        assert_eq!(address_mapping_slot_value(slot, &[]), slot);
+
+        let expected: H256 =
+            "0xa81f4bf17b73b1ca07d4c2cd015611e74f89c21286499da1b80a3b84a2cb7df5".parse().unwrap();
+        assert_eq!(address_mapping_slot_value(slot, &[owner]), expected);
+        assert_eq!(
+            address_mapping_slot_value(slot, &[owner, spender]),
+            mapping_slot_value(spender.into(), expected)
+        );
+    }
+
+    #[test]
+    fn test_slot_offset_value() {
+        let slot = H256::from_low_u64_be(5);
+        assert_eq!(slot_offset_value(slot, 0), slot);
+        assert_eq!(slot_offset_value(slot, 2), H256::from_low_u64_be(7));
+
+        let mut carried = [0u8; 32];
+        carried[23] = 1;
+        assert_eq!(slot_offset_value(H256::from_low_u64_be(u64::MAX), 1), H256(carried));
+    }
+}
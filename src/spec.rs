@@ -0,0 +1,210 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+use axiom_eth::{storage::EIP1186ResponseDigest, Field};
+use ethers_core::types::{Address, H256};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "providers")]
+use {ethers_providers::Middleware, tokio::runtime::Runtime};
+
+use crate::{
+    error::{AxiomError, Result},
+    offline::InputBundle,
+    scaffold::AxiomChip,
+};
+
+/// The most storage queries a [`QuerySpec`] may expand to, over all of its blocks. Each is a separate storage proof,
+/// so this is already far more than fit in one circuit, and it keeps a huge block range from being listed at all.
+pub const MAX_SPEC_QUERIES: usize = 1 << 12;
+
+/// A circuit described as data instead of circuit code, e.g. "prove slot S of contract C at blocks B1..B2 and expose
+/// the value and block hash". Load it into a chip with
+/// [`AxiomChip::load_spec`](crate::scaffold::AxiomChip::load_spec).
+///
+/// In JSON:
+/// ```json
+/// {
+///   "storage": [{
+///     "address": "0xb47e3cd837ddf8e4c57f05d70ab865de6e193bbb",
+///     "slots": ["0x0000000000000000000000000000000000000000000000000000000000000000"],
+///     "blocks": { "from": 16000000, "to": 16000002 },
+///     "expose": ["value", "block_hash"]
+///   }]
+/// }
+/// ```
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QuerySpec {
+    pub storage: Vec<StorageQuerySpec>,
+}
+
+/// Storage proofs of `slots` of the contract at `address`, at each of `blocks`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StorageQuerySpec {
+    pub address: Address,
+    pub slots: Vec<H256>,
+    pub blocks: BlockSpec,
+    /// The outputs made public for each block and slot, in this order. Defaults to the value and the block hash.
+    #[serde(default = "default_outputs")]
+    pub expose: Vec<SpecOutput>,
+}
+
+/// The blocks of a [`StorageQuerySpec`], either an inclusive range or a list of block numbers.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BlockSpec {
+    Range { from: u32, to: u32 },
+    List(Vec<u32>),
+}
+
+/// A value of a storage query that can be made public. 32 byte words are exposed as two hi-lo field elements.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpecOutput {
+    Value,
+    Slot,
+    Address,
+    BlockNumber,
+    BlockHash,
+}
+
+fn default_outputs() -> Vec<SpecOutput> {
+    vec![SpecOutput::Value, SpecOutput::BlockHash]
+}
+
+impl BlockSpec {
+    /// The number of blocks, without listing them.
+    pub fn num_blocks(&self) -> usize {
+        match self {
+            Self::Range { from, to } => to.checked_sub(*from).map_or(0, |len| len as usize + 1),
+            Self::List(blocks) => blocks.len(),
+        }
+    }
+
+    pub fn block_numbers(&self) -> Vec<u32> {
+        match self {
+            Self::Range { from, to } => (*from..=*to).collect(),
+            Self::List(blocks) => blocks.clone(),
+        }
+    }
+}
+
+impl QuerySpec {
+    /// Returns [`AxiomError::InvalidInput`] if the spec expands to more than [`MAX_SPEC_QUERIES`] storage queries.
+    pub fn check(&self) -> Result<()> {
+        let num_queries: usize = self.storage.iter().map(|query| query.blocks.num_blocks()).sum();
+        if num_queries > MAX_SPEC_QUERIES {
+            return Err(AxiomError::InvalidInput(format!(
+                "the spec has {num_queries} storage queries, more than the maximum of {MAX_SPEC_QUERIES}"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns the `(address, slots, block_number)` of every storage query, in the order they are assigned.
+    pub fn storage_queries(&self) -> Vec<(Address, Vec<H256>, u32)> {
+        self.storage
+            .iter()
+            .flat_map(|query| {
+                query
+                    .blocks
+                    .block_numbers()
+                    .into_iter()
+                    .map(move |block_number| (query.address, query.slots.clone(), block_number))
+            })
+            .collect()
+    }
+
+    /// Reads a spec from a TOML file if `path` has the `.toml` extension, and from a JSON file otherwise.
+    pub fn from_path(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+        if path.extension().map_or(false, |ext| ext == "toml") {
+            toml::from_str(&contents).map_err(|err| io::Error::new(ErrorKind::InvalidData, err))
+        } else {
+            Ok(serde_json::from_str(&contents)?)
+        }
+    }
+}
+
+#[cfg(feature = "providers")]
+impl<F: Field> AxiomChip<F> {
+    /// Constructs the circuit described by `spec`: fetches and assigns all of its storage proofs with
+    /// [`AxiomChip::eth_getProofs`], then exposes the outputs of each query, for each block in order, for each slot in
+    /// order, in the order of its `expose` list.
+    ///
+    /// Returns the digests of the storage proofs, one per block of each query, so more computation can be added on top.
+    pub fn load_spec<M: Middleware>(
+        &mut self,
+        provider: &M,
+        spec: &QuerySpec,
+    ) -> Result<Vec<EIP1186ResponseDigest<F>>> {
+        Runtime::new()?.block_on(self.load_spec_async(provider, spec))
+    }
+
+    /// Same as [`AxiomChip::load_spec`], for use from within an async context.
+    pub async fn load_spec_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        spec: &QuerySpec,
+    ) -> Result<Vec<EIP1186ResponseDigest<F>>> {
+        spec.check()?;
+        let digests = self.eth_getProofs_async(provider, spec.storage_queries()).await?;
+        self.expose_spec_outputs(spec, &digests);
+        Ok(digests)
+    }
+}
+
+impl<F: Field> AxiomChip<F> {
+    /// Same as [`AxiomChip::load_spec`](crate::scaffold::AxiomChip::load_spec), but with the blocks and proofs taken
+    /// from `inputs` instead of a provider.
+    pub fn load_spec_offline(
+        &mut self,
+        inputs: &InputBundle,
+        spec: &QuerySpec,
+    ) -> Result<Vec<EIP1186ResponseDigest<F>>> {
+        spec.check()?;
+        let digests = spec
+            .storage_queries()
+            .into_iter()
+            .map(|(address, slots, block_number)| {
+                self.eth_getProof_offline(inputs, address, slots, block_number)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.expose_spec_outputs(spec, &digests);
+        Ok(digests)
+    }
+
+    /// Exposes the outputs of `spec` from the `digests` of its storage queries.
+    fn expose_spec_outputs(&mut self, spec: &QuerySpec, digests: &[EIP1186ResponseDigest<F>]) {
+        let outputs = spec
+            .storage
+            .iter()
+            .flat_map(|query| query.blocks.block_numbers().into_iter().map(move |_| &query.expose));
+        for (digest, expose) in digests.iter().zip(outputs) {
+            for (index, (slot, _)) in digest.slots_values.iter().enumerate() {
+                for output in expose {
+                    match output {
+                        // The value of an empty slot is read as zero, whatever the proof holds.
+                        SpecOutput::Value => {
+                            for value in self.storage_value(digest, index) {
+                                self.expose_public(value);
+                            }
+                        }
+                        SpecOutput::Slot => slot.iter().for_each(|s| self.expose_public(*s)),
+                        SpecOutput::Address => self.expose_public(digest.address),
+                        SpecOutput::BlockNumber => self.expose_public(digest.block_number),
+                        SpecOutput::BlockHash => {
+                            self.expose_public(digest.block_hash.0);
+                            self.expose_public(digest.block_hash.1);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! Splitting a [`QuerySpec`] whose queries do not fit in one circuit into several circuits, each proving a contiguous
+//! run of its storage queries, e.g. to prove them separately and aggregate the proofs later.
+
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter},
+    ops::Range,
+    path::Path,
+};
+
+use axiom_eth::{Field, Network};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "providers")]
+use {ethers_providers::Middleware, tokio::runtime::Runtime};
+
+use crate::{
+    config::{AutoDegree, AxiomChipConfig},
+    error::{AxiomError, Result},
+    offline::InputBundle,
+    scaffold::AxiomChip,
+    schema::QuerySchema,
+    spec::{BlockSpec, QuerySpec, StorageQuerySpec},
+    stats::QueryCosts,
+};
+
+/// One of the circuits a spec is split into, see [`QuerySpec::split`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SplitPart {
+    /// The queries of this circuit, as a spec of its own. Its outputs are exposed in the same order as in the whole spec.
+    pub spec: QuerySpec,
+    /// The indices of the queries of this circuit among the [`QuerySpec::storage_queries`] of the whole spec.
+    pub queries: Range<usize>,
+    /// The smallest degree the queries of this circuit are estimated to fit in, see [`QuerySchema::estimate`].
+    pub estimated_k: usize,
+}
+
+/// How a spec was split into circuits, to match the proofs of the circuits and their public outputs with the queries of
+/// the spec. The concatenated public outputs of the parts, in order, are the outputs of the whole spec.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SplitManifest {
+    pub parts: Vec<SplitPart>,
+}
+
+impl SplitManifest {
+    /// Reads a manifest from a JSON file.
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        serde_json::to_writer_pretty(BufWriter::new(File::create(path)?), self)?;
+        Ok(())
+    }
+}
+
+impl QuerySpec {
+    /// Partitions the storage queries of this spec, in order, into as few circuits as fit with `config`: in
+    /// 2<sup>k</sup> rows, or up to its `max_k` if the degree is chosen automatically.
+    ///
+    /// Each circuit takes as many of the remaining queries as are estimated to fit, see [`QuerySchema::estimate`]. The
+    /// estimate leaves out the second phase, so a circuit that is close to the limit may still need a larger degree.
+    /// Returns [`AxiomError::CapacityExceeded`] if a single query does not fit.
+    pub fn split(&self, network: Network, config: AxiomChipConfig) -> Result<SplitManifest> {
+        let auto = config.auto_degree.unwrap_or_default();
+        let max_k = if config.auto_degree.is_some() { auto.max_k } else { config.k };
+        let auto = AutoDegree { max_k, ..auto };
+        let costs = QueryCosts::measure(network);
+        let queries = self.storage_queries();
+        let mut parts = vec![];
+        let mut start = 0;
+        while start < queries.len() {
+            let mut end = start;
+            let mut estimated_k = None;
+            while end < queries.len() {
+                let schema = QuerySchema {
+                    network,
+                    num_headers: 0,
+                    storage_slots: queries[start..=end].iter().map(|(_, s, _)| s.len()).collect(),
+                };
+                match schema.estimate_with(&costs, config, auto).min_k {
+                    Some(k) => estimated_k = Some(k),
+                    None => break,
+                }
+                end += 1;
+            }
+            let estimated_k = estimated_k.ok_or(AxiomError::CapacityExceeded { max_k })?;
+            parts.push(SplitPart {
+                spec: self.sub_spec(start..end),
+                queries: start..end,
+                estimated_k,
+            });
+            start = end;
+        }
+        Ok(SplitManifest { parts })
+    }
+
+    /// The spec of the storage queries at `range` among [`QuerySpec::storage_queries`], with the blocks of each of
+    /// its storage queries listed.
+    fn sub_spec(&self, range: Range<usize>) -> QuerySpec {
+        let blocks = self.storage.iter().enumerate().flat_map(|(index, query)| {
+            query.blocks.block_numbers().into_iter().map(move |block| (index, block))
+        });
+        let mut groups: Vec<(usize, Vec<u32>)> = vec![];
+        for (index, block_number) in blocks.skip(range.start).take(range.len()) {
+            match groups.last_mut() {
+                Some((last, blocks)) if *last == index => blocks.push(block_number),
+                _ => groups.push((index, vec![block_number])),
+            }
+        }
+        let storage = groups
+            .into_iter()
+            .map(|(index, blocks)| StorageQuerySpec {
+                blocks: BlockSpec::List(blocks),
+                ..self.storage[index].clone()
+            })
+            .collect();
+        QuerySpec { storage }
+    }
+}
+
+#[cfg(feature = "providers")]
+impl<F: Field> AxiomChip<F> {
+    /// Same as [`AxiomChip::load_spec`], but if the queries of `spec` do not fit in one circuit with the config of this
+    /// chip, splits them into several circuits, see [`QuerySpec::split`]. Returns one chip per circuit, each with its
+    /// part of the spec loaded, together with the manifest of the split.
+    ///
+    /// Each chip starts as a clone of this one, which should have no queries yet, so they share its config, provider
+    /// settings, and proving options.
+    pub fn load_spec_split<M: Middleware>(
+        &self,
+        provider: &M,
+        spec: &QuerySpec,
+    ) -> Result<(Vec<Self>, SplitManifest)> {
+        Runtime::new()?.block_on(self.load_spec_split_async(provider, spec))
+    }
+
+    /// Same as [`AxiomChip::load_spec_split`], for use from within an async context.
+    pub async fn load_spec_split_async<M: Middleware>(
+        &self,
+        provider: &M,
+        spec: &QuerySpec,
+    ) -> Result<(Vec<Self>, SplitManifest)> {
+        let mut template = self.clone();
+        let network = template.resolve_network(provider).await?;
+        let manifest = spec.split(network, *self.config())?;
+        let mut chips = vec![];
+        for part in &manifest.parts {
+            let mut chip = template.clone();
+            chip.load_spec_async(provider, &part.spec).await?;
+            chips.push(chip);
+        }
+        Ok((chips, manifest))
+    }
+}
+
+impl<F: Field> AxiomChip<F> {
+    /// Same as [`AxiomChip::load_spec_split`], but with the blocks and proofs taken from `inputs` instead of a provider.
+    pub fn load_spec_split_offline(
+        &self,
+        inputs: &InputBundle,
+        spec: &QuerySpec,
+    ) -> Result<(Vec<Self>, SplitManifest)> {
+        let manifest = spec.split(inputs.network()?, *self.config())?;
+        let chips = manifest
+            .parts
+            .iter()
+            .map(|part| {
+                let mut chip = self.clone();
+                chip.load_spec_offline(inputs, &part.spec)?;
+                Ok(chip)
+            })
+            .collect::<Result<_>>()?;
+        Ok((chips, manifest))
+    }
+}
@@ -0,0 +1,157 @@
+use std::{
+    collections::HashMap,
+    env::var,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use ethers_core::utils::hex;
+use halo2_base::{
+    halo2_proofs::{
+        halo2curves::bn256::Bn256, poly::commitment::Params, poly::kzg::commitment::ParamsKZG,
+    },
+    utils::fs::gen_srs,
+};
+use sha2::{Digest, Sha256};
+
+use crate::ptau::read_ptau;
+
+/// Axiom's conversion of the Perpetual Powers of Tau ceremony (challenge 85) to halo2 format.
+pub const AXIOM_SRS_URL: &str = "https://axiom-crypto.s3.amazonaws.com/challenge_0085";
+
+/// Loads KZG parameters for a given degree from a local directory, downloading them if they are missing.
+///
+/// Files are named `kzg_bn254_{k}.srs`, matching the naming used by `halo2_base::utils::fs`.
+/// A downloaded or local file is checked against the expected SHA-256 checksum for its degree if one is known,
+/// either from [`SrsManager::checksums`] or from a `kzg_bn254_{k}.srs.sha256` file next to it, and is rejected on a
+/// mismatch. When a file without a known checksum is downloaded, its checksum is written to that `.sha256` file, so
+/// a file that changes on disk afterwards is rejected.
+#[derive(Clone, Debug)]
+pub struct SrsManager {
+    /// The directory the parameter files are read from and downloaded to.
+    pub dir: PathBuf,
+    /// The base URL missing files are downloaded from. Downloads are disabled if `None`.
+    pub download_url: Option<String>,
+    /// Expected SHA-256 checksums of the parameter files, by degree.
+    pub checksums: HashMap<u32, [u8; 32]>,
+    /// Reject parameter files without a known checksum instead of only logging a warning.
+    pub require_checksum: bool,
+    /// If no file can be found or downloaded, generate parameters locally from a *known* toxic waste.
+    /// Such parameters are only suitable for testing.
+    pub allow_insecure_setup: bool,
+}
+
+impl Default for SrsManager {
+    /// Reads from the `PARAMS_DIR` environment variable, defaulting to `./params`, and downloads from [`AXIOM_SRS_URL`].
+    fn default() -> Self {
+        Self {
+            dir: var("PARAMS_DIR").unwrap_or_else(|_| "./params".to_string()).into(),
+            download_url: Some(AXIOM_SRS_URL.to_string()),
+            checksums: HashMap::new(),
+            require_checksum: false,
+            allow_insecure_setup: false,
+        }
+    }
+}
+
+impl SrsManager {
+    pub fn path(&self, k: u32) -> PathBuf {
+        self.dir.join(format!("kzg_bn254_{k}.srs"))
+    }
+
+    fn checksum_path(&self, k: u32) -> PathBuf {
+        let mut path = self.path(k).into_os_string();
+        path.push(".sha256");
+        path.into()
+    }
+
+    /// Returns the KZG parameters for degree `k`.
+    pub fn params(&self, k: u32) -> io::Result<ParamsKZG<Bn256>> {
+        let path = self.path(k);
+        if !path.exists() {
+            match &self.download_url {
+                Some(url) => self.download(k, url)?,
+                None if self.allow_insecure_setup => {
+                    log::warn!("Generating insecure KZG parameters for k = {k}, only use these for testing");
+                    return Ok(gen_srs(k));
+                }
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("KZG parameters not found at {path:?} and downloads are disabled"),
+                    ))
+                }
+            }
+        }
+        self.check(k, &fs::read(&path)?)?;
+        let mut reader = BufReader::new(File::open(&path)?);
+        ParamsKZG::<Bn256>::read(&mut reader)
+    }
+
+    /// Converts a snarkjs `.ptau` file to KZG parameters for degree `k` and saves them to [`SrsManager::path`],
+    /// so that subsequent calls to [`SrsManager::params`] use them.
+    pub fn import_ptau(&self, ptau_path: impl AsRef<Path>, k: u32) -> io::Result<()> {
+        let params = read_ptau(ptau_path, k)?;
+        fs::create_dir_all(&self.dir)?;
+        let mut writer = BufWriter::new(File::create(self.path(k))?);
+        params.write(&mut writer)?;
+        writer.flush()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn download(&self, k: u32, _url: &str) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "KZG parameters for k = {k} must be provided, downloads are not supported on wasm"
+            ),
+        ))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn download(&self, k: u32, url: &str) -> io::Result<()> {
+        let url = format!("{url}/kzg_bn254_{k}.srs");
+        log::info!("Downloading KZG parameters from {url}");
+        let bytes = reqwest::blocking::get(&url)
+            .and_then(|res| res.error_for_status())
+            .and_then(|res| res.bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        let pinned = self.check(k, &bytes)?;
+        fs::create_dir_all(&self.dir)?;
+        if !pinned {
+            fs::write(self.checksum_path(k), hex::encode(Sha256::digest(&bytes)))?;
+        }
+        fs::write(self.path(k), bytes)
+    }
+
+    /// Checks `bytes` against the expected checksum for degree `k`, returning whether there was one.
+    fn check(&self, k: u32, bytes: &[u8]) -> io::Result<bool> {
+        let expected = match self.checksums.get(&k) {
+            Some(checksum) => hex::encode(checksum),
+            None => {
+                match fs::read_to_string(self.checksum_path(k)) {
+                    Ok(checksum) => checksum.trim().to_lowercase(),
+                    Err(_) if self.require_checksum => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("no checksum known for KZG parameters with k = {k}"),
+                        ))
+                    }
+                    Err(_) => {
+                        log::warn!("No checksum known for KZG parameters with k = {k}, skipping verification");
+                        return Ok(false);
+                    }
+                }
+            }
+        };
+        let actual = hex::encode(Sha256::digest(bytes));
+        if actual != expected {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("checksum mismatch for KZG parameters with k = {k}: expected {expected}, got {actual}"),
+            ));
+        }
+        Ok(true)
+    }
+}
@@ -0,0 +1,290 @@
+use std::{fmt, mem::size_of};
+
+use axiom_eth::{EthConfigParams, Field, Network};
+use halo2_base::halo2_proofs::{
+    halo2curves::bn256::Fr,
+    plonk::{Circuit, ConstraintSystem},
+};
+use serde::Serialize;
+
+use crate::{
+    config::{AutoDegree, AxiomChipConfig},
+    debug::QuerySource,
+    error::{AxiomError, Result},
+    scaffold::{configure, AxiomChip},
+    schema::QuerySchema,
+};
+
+/// The keccak circuit uses `keccak_rows_per_round` rows for each of the 24 rounds of a keccak-f permutation,
+/// plus one more round for absorbing the input.
+const KECCAK_ROUNDS_PER_PERMUTATION: usize = 25;
+/// The rounds of the keccak circuit besides those of the permutations: one at the start, and one for each word absorbed
+/// by a permutation.
+const KECCAK_EXTRA_ROUNDS: usize = 1 + 17;
+
+/// Resource usage of an [`AxiomChip`] circuit, see [`AxiomChip::stats`].
+///
+/// Cells assigned in the second phase (RLC computations) are only known once the circuit is synthesized,
+/// so for that phase only the resulting number of columns is reported.
+#[derive(Clone, Debug, Serialize)]
+pub struct CircuitStats {
+    /// The circuit has 2<sup>k</sup> rows.
+    pub k: usize,
+    /// The number of advice cells assigned in the first phase.
+    pub advice_cells_phase0: usize,
+    /// The number of cells range checked with the lookup table in the first phase.
+    pub lookup_cells_phase0: usize,
+    /// The number of keccak hashes of fixed length inputs.
+    pub keccak_fixed_len_queries: usize,
+    /// The number of keccak hashes of variable length inputs.
+    pub keccak_var_len_queries: usize,
+    /// The total number of keccak-f permutations needed for all keccak queries.
+    pub keccak_capacity: usize,
+    /// The column configuration of the circuit at `k`, including the keccak rows per round.
+    pub config: EthConfigParams,
+    /// The resources used by each query, in the order they were made.
+    pub queries: Vec<QueryUsage>,
+}
+
+/// The resources used by a single block header or storage proof query, see [`AxiomChip::query_usage`].
+#[derive(Clone, Debug, Serialize)]
+pub struct QueryUsage {
+    pub query: QuerySource,
+    /// The number of advice cells assigned in the first phase.
+    pub advice_cells: usize,
+    /// The number of cells range checked with the lookup table in the first phase.
+    pub lookup_cells: usize,
+    /// The number of keccak-f permutations needed for the keccak hashes of the query.
+    pub keccak_capacity: usize,
+    /// The rows of the keccak circuit used by those permutations, with the keccak rows per round of the chip's config,
+    /// or one per round if it is not set.
+    pub keccak_rows: usize,
+}
+
+impl fmt::Display for QueryUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} advice cells, {} lookup cells, {} keccak rows",
+            self.query, self.advice_cells, self.lookup_cells, self.keccak_rows
+        )
+    }
+}
+
+impl<F: Field> AxiomChip<F> {
+    /// Measures the circuit built so far and computes its column configuration at the `k` of this chip's config.
+    pub fn stats(&self) -> CircuitStats {
+        let k = self.config().k;
+        let (advice_cells_phase0, lookup_cells_phase0) = self.phase0_usage();
+        let (keccak_fixed_len_queries, keccak_var_len_queries, keccak_capacity) = {
+            let keccak = self.keccak.lock();
+            (keccak.fixed_len_queries.len(), keccak.var_len_queries.len(), keccak.capacity())
+        };
+        let config = configure(&self.clone().build(None), k, self.config());
+        let queries = self.query_usage();
+        CircuitStats {
+            k,
+            advice_cells_phase0,
+            lookup_cells_phase0,
+            keccak_fixed_len_queries,
+            keccak_var_len_queries,
+            keccak_capacity,
+            config,
+            queries,
+        }
+    }
+
+    /// Returns the resources used by each block header and storage proof query made so far, to find the queries that
+    /// take up most of the circuit.
+    ///
+    /// Headers assigned in parallel, e.g. by [`AxiomChip::eth_getBlocksByNumber`], share the keccak capacity of the
+    /// batch equally, which is exact since every header is padded to the same length. Block headers reused by later
+    /// queries are only counted once, by the query that assigned them.
+    pub fn query_usage(&self) -> Vec<QueryUsage> {
+        let rows_per_round = self.config().keccak_rows_per_round.unwrap_or(1);
+        self.query_spans
+            .iter()
+            .map(|span| QueryUsage {
+                query: span.source.clone(),
+                advice_cells: span.cells.iter().map(|(_, cells)| cells.len()).sum(),
+                lookup_cells: span.lookup_cells,
+                keccak_capacity: span.keccak_capacity,
+                keccak_rows: span.keccak_capacity * KECCAK_ROUNDS_PER_PERMUTATION * rows_per_round,
+            })
+            .collect()
+    }
+
+    /// Roughly estimates the peak memory in bytes needed to generate keys for and prove this circuit with
+    /// 2<sup>k</sup> rows, from the number of columns, lookups, and the degree of its constraints.
+    ///
+    /// The estimate counts the polynomials held by the proving key and the prover in Lagrange, coefficient, and extended
+    /// coset form, so it is a lower bound: allocator overhead and witness generation come on top.
+    pub fn estimated_peak_memory(&self, k: usize) -> usize {
+        let circuit = self.clone().build(None);
+        configure(&circuit, k, self.config());
+        let cs = constraint_system(&circuit);
+
+        let n = 1usize << k;
+        let extended_n = n << (cs.degree() - 1).next_power_of_two().trailing_zeros();
+        let fixed = cs.num_fixed_columns() + cs.num_selectors();
+        let permutation = cs.permutation().get_columns().len();
+        let field_elements =
+            // fixed and permutation polynomials of the proving key
+            (fixed + permutation) * (2 * n + extended_n)
+            // advice and instance polynomials
+            + (cs.num_advice_columns() + cs.num_instance_columns()) * 2 * n
+            // permuted input, permuted table, and product polynomials of each lookup
+            + cs.lookups().len() * 3 * 2 * n
+            // the quotient polynomial
+            + 2 * extended_n;
+        field_elements * size_of::<F>()
+    }
+
+    /// Checks that the keccak hashes of the circuit built so far fit in 2<sup>k</sup> rows, with the keccak rows per
+    /// round of this chip's config, or at least one.
+    ///
+    /// Otherwise returns [`AxiomError::KeccakCapacityExceeded`], before building the circuit, which would fail to
+    /// configure or fail the mock prover without saying why.
+    pub fn check_keccak_capacity(&self, k: usize) -> Result<()> {
+        let needed_rows =
+            keccak_rows(self.keccak.lock().capacity(), self.config().keccak_rows_per_round);
+        let available_rows = (1usize << k).saturating_sub(self.config().unusable_rows);
+        if needed_rows > available_rows {
+            return Err(AxiomError::KeccakCapacityExceeded { needed_rows, k, available_rows });
+        }
+        Ok(())
+    }
+
+    /// Returns the number of advice cells and lookup cells assigned in the first phase so far.
+    fn phase0_usage(&self) -> (usize, usize) {
+        let builder = self.builder.lock();
+        builder.gate_builder.threads[0].iter().fold((0, 0), |(advice, lookup), ctx| {
+            (advice + ctx.advice.len(), lookup + ctx.cells_to_lookup.len())
+        })
+    }
+}
+
+/// The number of rows the keccak chip needs for `capacity` permutations, with `rows_per_round` rows per round.
+fn keccak_rows(capacity: usize, rows_per_round: Option<usize>) -> usize {
+    (capacity * KECCAK_ROUNDS_PER_PERMUTATION + KECCAK_EXTRA_ROUNDS) * rows_per_round.unwrap_or(1)
+}
+
+/// Configures the columns and constraints of `circuit`.
+fn constraint_system<F: Field, C: Circuit<F>>(_circuit: &C) -> ConstraintSystem<F> {
+    let mut cs = ConstraintSystem::default();
+    C::configure(&mut cs);
+    cs
+}
+
+/// A prediction of the resources needed by the queries of a [`QuerySchema`], see [`QuerySchema::estimate`].
+///
+/// Only the queries themselves are accounted for, so any computation you add on top of their results comes in addition.
+#[derive(Clone, Debug, Serialize)]
+pub struct CostEstimate {
+    /// The number of advice cells assigned in the first phase.
+    pub advice_cells_phase0: usize,
+    /// The number of cells range checked with the lookup table in the first phase.
+    pub lookup_cells_phase0: usize,
+    /// The total number of keccak-f permutations needed for all keccak queries.
+    pub keccak_capacity: usize,
+    /// The smallest `k` for which the queries fit, or `None` if they do not fit in 2<sup>max_k</sup> rows.
+    pub min_k: Option<usize>,
+    /// The number of advice columns in the first phase at `min_k`.
+    pub advice_columns_phase0: usize,
+}
+
+/// The resources used by a single query.
+#[derive(Clone, Copy, Default)]
+struct QueryCost {
+    advice_cells: usize,
+    lookup_cells: usize,
+    keccak_capacity: usize,
+}
+
+/// The resources used by each kind of query on a network, see [`QuerySchema::estimate`].
+pub(crate) struct QueryCosts {
+    header: QueryCost,
+    account: QueryCost,
+    slot: QueryCost,
+}
+
+impl QueryCosts {
+    /// Measures each kind of query once on dummy inputs.
+    pub(crate) fn measure(network: Network) -> Self {
+        let schema =
+            |num_headers, storage_slots| QuerySchema { network, num_headers, storage_slots };
+        let header = QueryCost::measure(&schema(1, vec![]));
+        let account = QueryCost::measure(&schema(0, vec![0]));
+        let slot = QueryCost::measure(&schema(0, vec![1])).sub(account);
+        Self { header, account, slot }
+    }
+}
+
+impl QueryCost {
+    /// Measures the queries of `schema` by assigning them with dummy inputs.
+    fn measure(schema: &QuerySchema) -> Self {
+        let (axiom, _) = AxiomChip::<Fr>::keygen(schema);
+        let (advice_cells, lookup_cells) = axiom.phase0_usage();
+        let keccak_capacity = axiom.keccak.lock().capacity();
+        Self { advice_cells, lookup_cells, keccak_capacity }
+    }
+
+    fn sub(self, other: Self) -> Self {
+        Self {
+            advice_cells: self.advice_cells - other.advice_cells,
+            lookup_cells: self.lookup_cells - other.lookup_cells,
+            keccak_capacity: self.keccak_capacity - other.keccak_capacity,
+        }
+    }
+}
+
+impl QuerySchema {
+    /// Predicts the resources needed by the queries in this schema, and the smallest degree they fit in, without
+    /// fetching any chain data or generating the witnesses of the full circuit.
+    ///
+    /// The cost of a query does not depend on the chain data it returns, so the cost of each kind of query is measured
+    /// once on dummy inputs and scaled by the number of queries. Columns used by the second phase (RLC computations)
+    /// are not accounted for, so the estimated `min_k` is a lower bound.
+    pub fn estimate(&self, config: AxiomChipConfig, auto: AutoDegree) -> CostEstimate {
+        self.estimate_with(&QueryCosts::measure(self.network), config, auto)
+    }
+
+    /// Same as [`QuerySchema::estimate`], with the costs of each kind of query measured before.
+    pub(crate) fn estimate_with(
+        &self,
+        costs: &QueryCosts,
+        config: AxiomChipConfig,
+        auto: AutoDegree,
+    ) -> CostEstimate {
+        let QueryCosts { header, account, slot } = costs;
+        let num_accounts = self.storage_slots.len();
+        let num_slots = self.storage_slots.iter().sum::<usize>();
+        let total = |cost: fn(&QueryCost) -> usize| {
+            self.num_headers * cost(header) + num_accounts * cost(account) + num_slots * cost(slot)
+        };
+        let advice_cells_phase0 = total(|c| c.advice_cells);
+        let lookup_cells_phase0 = total(|c| c.lookup_cells);
+        let keccak_capacity = total(|c| c.keccak_capacity);
+
+        let AxiomChipConfig { lookup_bits, unusable_rows, keccak_rows_per_round, .. } = config;
+        let advice_columns = |k: usize| {
+            let usable_rows = (1 << k) - unusable_rows;
+            (advice_cells_phase0 + usable_rows - 1) / usable_rows
+        };
+        let min_k = (lookup_bits + 1..=auto.max_k).find(|&k| {
+            if (1 << k) <= (1 << lookup_bits) + unusable_rows {
+                return false;
+            }
+            let usable_rows = (1 << k) - unusable_rows;
+            keccak_rows(keccak_capacity, keccak_rows_per_round) <= usable_rows
+                && advice_columns(k) <= auto.max_advice_columns
+        });
+        CostEstimate {
+            advice_cells_phase0,
+            lookup_cells_phase0,
+            keccak_capacity,
+            min_k,
+            advice_columns_phase0: advice_columns(min_k.unwrap_or(auto.max_k)),
+        }
+    }
+}
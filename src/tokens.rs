@@ -0,0 +1,182 @@
+//! Proofs of ERC20 token state at a block: balances, allowances and the total supply.
+//!
+//! Each value is read with a storage proof of the token contract, whose slot is derived in the circuit from the holder
+//! addresses and the storage layout of the token.
+
+use axiom_eth::{storage::EIP1186ResponseDigest, Field};
+use halo2_base::AssignedValue;
+#[cfg(feature = "providers")]
+use {
+    crate::{error::Result, slots::address_mapping_slot_value},
+    axiom_eth::util::encode_addr_to_field,
+    ethers_core::types::{Address, H256},
+    ethers_providers::Middleware,
+    tokio::runtime::Runtime,
+};
+
+use crate::scaffold::AxiomChip;
+
+/// The slots of the state variables of an ERC20 token.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Erc20Layout {
+    /// The slot of `mapping(address => uint256) balances`.
+    pub balances_slot: u64,
+    /// The slot of `mapping(address => mapping(address => uint256)) allowances`, keyed by owner then spender.
+    pub allowances_slot: u64,
+    /// The slot of `uint256 totalSupply`.
+    pub total_supply_slot: u64,
+}
+
+impl Erc20Layout {
+    /// The layout of tokens built on the OpenZeppelin `ERC20` contract, which declares its variables first.
+    pub const OPENZEPPELIN: Self =
+        Self { balances_slot: 0, allowances_slot: 1, total_supply_slot: 2 };
+}
+
+/// A `uint256` value of an ERC20 token at a block, see [`AxiomChip::constrain_erc20_value`].
+#[derive(Clone, Debug)]
+pub struct Erc20Value<F: Field> {
+    pub token: AssignedValue<F>,
+    pub block_number: AssignedValue<F>,
+    /// The block hash as hi-lo 128 bit halves.
+    pub block_hash: (AssignedValue<F>, AssignedValue<F>),
+    /// The addresses the value is keyed by, e.g. the owner and spender of an allowance. Like the token, they are
+    /// private witnesses.
+    pub keys: Vec<AssignedValue<F>>,
+    /// The value as hi-lo 128 bit halves.
+    pub value: [AssignedValue<F>; 2],
+}
+
+#[cfg(feature = "providers")]
+impl<F: Field> AxiomChip<F> {
+    /// Proves the balance of `holder` of the ERC20 token at `token` at block `block_number`.
+    pub fn erc20_balance<M: Middleware>(
+        &mut self,
+        provider: &M,
+        token: Address,
+        holder: Address,
+        block_number: u32,
+        layout: Erc20Layout,
+    ) -> Result<Erc20Value<F>> {
+        Runtime::new()?.block_on(self.erc20_balance_async(
+            provider,
+            token,
+            holder,
+            block_number,
+            layout,
+        ))
+    }
+
+    /// Same as [`AxiomChip::erc20_balance`], for use from within an async context.
+    pub async fn erc20_balance_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        token: Address,
+        holder: Address,
+        block_number: u32,
+        layout: Erc20Layout,
+    ) -> Result<Erc20Value<F>> {
+        self.erc20_value(provider, token, &[holder], layout.balances_slot, block_number).await
+    }
+
+    /// Proves the total supply of the ERC20 token at `token` at block `block_number`.
+    pub fn erc20_total_supply<M: Middleware>(
+        &mut self,
+        provider: &M,
+        token: Address,
+        block_number: u32,
+        layout: Erc20Layout,
+    ) -> Result<Erc20Value<F>> {
+        Runtime::new()?.block_on(self.erc20_total_supply_async(
+            provider,
+            token,
+            block_number,
+            layout,
+        ))
+    }
+
+    /// Same as [`AxiomChip::erc20_total_supply`], for use from within an async context.
+    pub async fn erc20_total_supply_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        token: Address,
+        block_number: u32,
+        layout: Erc20Layout,
+    ) -> Result<Erc20Value<F>> {
+        self.erc20_value(provider, token, &[], layout.total_supply_slot, block_number).await
+    }
+
+    /// Proves the amount of the ERC20 token at `token` that `spender` may transfer from `owner` at block
+    /// `block_number`.
+    pub fn erc20_allowance<M: Middleware>(
+        &mut self,
+        provider: &M,
+        token: Address,
+        owner: Address,
+        spender: Address,
+        block_number: u32,
+        layout: Erc20Layout,
+    ) -> Result<Erc20Value<F>> {
+        Runtime::new()?.block_on(self.erc20_allowance_async(
+            provider,
+            token,
+            owner,
+            spender,
+            block_number,
+            layout,
+        ))
+    }
+
+    /// Same as [`AxiomChip::erc20_allowance`], for use from within an async context.
+    pub async fn erc20_allowance_async<M: Middleware>(
+        &mut self,
+        provider: &M,
+        token: Address,
+        owner: Address,
+        spender: Address,
+        block_number: u32,
+        layout: Erc20Layout,
+    ) -> Result<Erc20Value<F>> {
+        let keys = [owner, spender];
+        self.erc20_value(provider, token, &keys, layout.allowances_slot, block_number).await
+    }
+
+    /// Proves the value of the mapping at `slot` of `token` keyed by `keys` in order, or of `slot` itself without keys.
+    async fn erc20_value<M: Middleware>(
+        &mut self,
+        provider: &M,
+        token: Address,
+        keys: &[Address],
+        slot: u64,
+        block_number: u32,
+    ) -> Result<Erc20Value<F>> {
+        let value_slot = address_mapping_slot_value(H256::from_low_u64_be(slot), keys);
+        let digest =
+            self.eth_getProof_async(provider, token, vec![value_slot], block_number).await?;
+        let keys =
+            keys.iter().map(|key| self.ctx().load_witness(encode_addr_to_field(key))).collect();
+        Ok(self.constrain_erc20_value(&digest, keys, slot))
+    }
+}
+
+impl<F: Field> AxiomChip<F> {
+    /// Constrains the single slot storage proof `digest` to be of the entry for the addresses `keys` of the nested
+    /// mapping at `slot`, in order, or of `slot` itself if there are no keys.
+    pub fn constrain_erc20_value(
+        &self,
+        digest: &EIP1186ResponseDigest<F>,
+        keys: Vec<AssignedValue<F>>,
+        slot: u64,
+    ) -> Erc20Value<F> {
+        assert_eq!(digest.slots_values.len(), 1, "an ERC20 value is proven with a single slot");
+        let value_slot = self.address_mapping_slot(self.constant_slot(slot), &keys);
+        self.constrain_slot(digest, 0, value_slot.hi_lo());
+        Erc20Value {
+            token: digest.address,
+            block_number: digest.block_number,
+            block_hash: digest.block_hash,
+            keys,
+            value: self.storage_value(digest, 0),
+        }
+    }
+}
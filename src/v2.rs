@@ -0,0 +1,433 @@
+//! Encodes a circuit and its proof as an Axiom V2 query, so it can be submitted to the Axiom V2 contracts with
+//! `sendQuery` and its results delivered to a callback contract.
+//!
+//! A V2 query has three parts:
+//! - the data query: the chain data the circuit reads, as subqueries, see [`AxiomChip::axiom_v2_data_query`];
+//! - the compute query: the verifying key and proof of the circuit, see [`ComputeQuery`];
+//! - the callback: the contract that receives the results.
+//!
+//! The results are the public outputs of the circuit, read as 32 byte words each exposed as a `(hi, lo)` pair of 128
+//! bit halves, e.g., the `[hi, lo]` slot values of an [`EIP1186ResponseDigest`](axiom_eth::storage::EIP1186ResponseDigest).
+//! Proofs submitted on-chain must be created with [`TranscriptKind::Keccak`].
+
+use axiom_eth::Field;
+use ethers_core::{
+    abi::Token,
+    types::{Address, Bytes, H256, U256},
+    utils::keccak256,
+};
+use halo2_base::halo2_proofs::{
+    halo2curves::bn256::{Fr, G1Affine},
+    plonk::VerifyingKey,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{AxiomError, Result},
+    proof::{ProofArtifacts, TranscriptKind},
+    providers::chain_id,
+    scaffold::AxiomChip,
+};
+
+/// The version of the query format, the first byte of every encoded data query.
+pub const AXIOM_V2_VERSION: u8 = 2;
+/// The number of public instances taken by a KZG accumulator, see [`AxiomChip::verify_snarks`].
+const ACCUMULATOR_INSTANCES: usize = 12;
+/// The number of fields of an account: nonce, balance, storage root, and code hash.
+const ACCOUNT_FIELDS: u32 = 4;
+
+/// A single read of chain data by a V2 query.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Subquery {
+    /// Field `field_idx` of the header of block `block_number`, in the order of the RLP encoded header.
+    Header { block_number: u32, field_idx: u32 },
+    /// Field `field_idx` of the account at `addr` at block `block_number`, in the order of the RLP encoded account:
+    /// nonce, balance, storage root, and code hash.
+    Account { block_number: u32, addr: Address, field_idx: u32 },
+    /// Storage slot `slot` of the contract at `addr` at block `block_number`.
+    Storage { block_number: u32, addr: Address, slot: H256 },
+}
+
+impl Subquery {
+    pub fn subquery_type(&self) -> u16 {
+        match self {
+            Self::Header { .. } => 1,
+            Self::Account { .. } => 2,
+            Self::Storage { .. } => 3,
+        }
+    }
+
+    /// The packed encoding of the subquery: its type as a big endian `uint16`, followed by its fields.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut encoded = self.subquery_type().to_be_bytes().to_vec();
+        match self {
+            Self::Header { block_number, field_idx } => {
+                encoded.extend(block_number.to_be_bytes());
+                encoded.extend(field_idx.to_be_bytes());
+            }
+            Self::Account { block_number, addr, field_idx } => {
+                encoded.extend(block_number.to_be_bytes());
+                encoded.extend(addr.as_bytes());
+                encoded.extend(field_idx.to_be_bytes());
+            }
+            Self::Storage { block_number, addr, slot } => {
+                encoded.extend(block_number.to_be_bytes());
+                encoded.extend(addr.as_bytes());
+                encoded.extend(slot.as_bytes());
+            }
+        }
+        encoded
+    }
+
+    pub fn hash(&self) -> H256 {
+        keccak256(self.encode()).into()
+    }
+}
+
+/// The chain data read by a V2 query.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataQuery {
+    pub source_chain_id: u64,
+    pub subqueries: Vec<Subquery>,
+}
+
+impl DataQuery {
+    /// The `dataQuery` argument of `sendQuery`: the version, the source chain ID as a `uint64`, the number of
+    /// subqueries as a `uint16`, then each encoded subquery.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut encoded = vec![AXIOM_V2_VERSION];
+        encoded.extend(self.source_chain_id.to_be_bytes());
+        encoded.extend((self.subqueries.len() as u16).to_be_bytes());
+        for subquery in &self.subqueries {
+            encoded.extend(subquery.encode());
+        }
+        encoded
+    }
+
+    /// The `dataQueryHash` argument of `sendQuery`: the keccak hash of the version, the source chain ID, and the hash
+    /// of each subquery.
+    pub fn hash(&self) -> H256 {
+        let mut preimage = vec![AXIOM_V2_VERSION];
+        preimage.extend(self.source_chain_id.to_be_bytes());
+        for subquery in &self.subqueries {
+            preimage.extend(subquery.hash().as_bytes());
+        }
+        keccak256(preimage).into()
+    }
+}
+
+/// The circuit of a V2 query, i.e., the `AxiomV2ComputeQuery` argument of `sendQuery`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComputeQuery {
+    /// The circuit has 2<sup>k</sup> rows.
+    pub k: u8,
+    /// The number of 32 byte results.
+    pub result_len: u16,
+    /// The verifying key as 32 byte words: its transcript representation, then the `x` and `y` coordinates of each
+    /// fixed and permutation commitment.
+    pub vkey: Vec<H256>,
+    /// The accumulator instances, if any, then the results, each as a 32 byte word, followed by the proof.
+    pub compute_proof: Bytes,
+}
+
+impl ComputeQuery {
+    /// Encodes a proof of a circuit that exposed its results as `(hi, lo)` pairs. If `has_accumulator`, the first public
+    /// instances are a KZG accumulator rather than results, see [`AxiomChip::has_accumulator`].
+    ///
+    /// Returns [`AxiomError::InvalidOutput`] if the proof was not created with a Keccak transcript, the instances do not
+    /// form `(hi, lo)` pairs of 128 bit values, or the circuit is too large for a query.
+    pub fn new(artifacts: &ProofArtifacts, has_accumulator: bool) -> Result<Self> {
+        if artifacts.transcript != TranscriptKind::Keccak {
+            return Err(AxiomError::InvalidOutput(
+                "Axiom V2 queries are verified on-chain, so the proof must use a Keccak transcript"
+                    .to_string(),
+            ));
+        }
+        let num_accumulator = if has_accumulator { ACCUMULATOR_INSTANCES } else { 0 };
+        let (accumulator, outputs) = artifacts.instances.split_at(num_accumulator);
+        let results = results_from_hi_lo(outputs)?;
+        let result_len = u16::try_from(results.len()).map_err(|_| {
+            AxiomError::InvalidOutput(format!(
+                "{} results is more than fit in a query",
+                results.len()
+            ))
+        })?;
+
+        let vkey = vkey_words(&artifacts.vk);
+        if vkey.len() > u8::MAX as usize {
+            return Err(AxiomError::InvalidOutput(format!(
+                "the verifying key has {} words, more than fit in a query",
+                vkey.len()
+            )));
+        }
+
+        let mut compute_proof: Vec<u8> =
+            accumulator.iter().flat_map(|x| fr_to_word(x).to_fixed_bytes()).collect();
+        compute_proof.extend(results.iter().flat_map(|result| result.to_fixed_bytes()));
+        compute_proof.extend(&artifacts.proof);
+        Ok(Self {
+            k: artifacts.vk.get_domain().k() as u8,
+            result_len,
+            vkey,
+            compute_proof: compute_proof.into(),
+        })
+    }
+
+    /// The results in the compute proof. `has_accumulator` must be the same as when the query was created.
+    pub fn results(&self, has_accumulator: bool) -> Vec<H256> {
+        let start = if has_accumulator { 32 * ACCUMULATOR_INSTANCES } else { 0 };
+        let end = start + 32 * self.result_len as usize;
+        self.compute_proof[start..end].chunks(32).map(H256::from_slice).collect()
+    }
+
+    /// The packed encoding of the compute query hashed into the query hash.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut encoded = vec![self.k];
+        encoded.extend(self.result_len.to_be_bytes());
+        encoded.push(self.vkey.len() as u8);
+        for word in &self.vkey {
+            encoded.extend(word.as_bytes());
+        }
+        encoded.extend((self.compute_proof.len() as u32).to_be_bytes());
+        encoded.extend(self.compute_proof.iter());
+        encoded
+    }
+
+    /// The ABI encoding of the `AxiomV2ComputeQuery` struct.
+    pub fn to_token(&self) -> Token {
+        Token::Tuple(vec![
+            Token::Uint(self.k.into()),
+            Token::Uint(self.result_len.into()),
+            Token::Array(self.vkey.iter().map(|word| Token::FixedBytes(word.0.to_vec())).collect()),
+            Token::Bytes(self.compute_proof.to_vec()),
+        ])
+    }
+}
+
+/// The contract that receives the results of a V2 query, i.e., the `AxiomV2Callback` argument of `sendQuery`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Callback {
+    pub target: Address,
+    /// Passed unchanged to the callback.
+    pub extra_data: Bytes,
+}
+
+impl Callback {
+    /// The ABI encoding of the `AxiomV2Callback` struct.
+    pub fn to_token(&self) -> Token {
+        Token::Tuple(vec![Token::Address(self.target), Token::Bytes(self.extra_data.to_vec())])
+    }
+}
+
+/// Everything needed to submit a proven circuit to the Axiom V2 contracts.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AxiomV2Query {
+    pub data_query: DataQuery,
+    pub compute_query: ComputeQuery,
+    pub callback: Callback,
+    /// The `axiomResults` passed to the callback once the query is fulfilled.
+    pub results: Vec<H256>,
+}
+
+impl AxiomV2Query {
+    /// Encodes a proof of a circuit reading `data_query`, see [`ComputeQuery::new`].
+    pub fn new(
+        data_query: DataQuery,
+        artifacts: &ProofArtifacts,
+        has_accumulator: bool,
+        callback: Callback,
+    ) -> Result<Self> {
+        let compute_query = ComputeQuery::new(artifacts, has_accumulator)?;
+        let results = compute_query.results(has_accumulator);
+        Ok(Self { data_query, compute_query, callback, results })
+    }
+
+    /// The keccak hash of the version, the source chain ID, the data query hash, and the encoded compute query.
+    pub fn query_hash(&self) -> H256 {
+        let mut preimage = vec![AXIOM_V2_VERSION];
+        preimage.extend(self.data_query.source_chain_id.to_be_bytes());
+        preimage.extend(self.data_query.hash().as_bytes());
+        preimage.extend(self.compute_query.encode());
+        keccak256(preimage).into()
+    }
+}
+
+impl<F: Field> AxiomChip<F> {
+    /// Whether the first public instances of this circuit are a KZG accumulator, see [`AxiomChip::verify_snarks`].
+    pub fn has_accumulator(&self) -> bool {
+        self.has_accumulator
+    }
+
+    /// The data query of the queries made so far: a storage subquery for each slot of each `eth_getProof` query, and
+    /// an account subquery for each field of the account of each `eth_getProof` query without slots, e.g. from
+    /// [`AxiomChip::eth_getAccount`].
+    ///
+    /// Header fields are not read as a whole in V2 queries, so block header queries are not included: add a
+    /// [`Subquery::Header`] for each header field the results depend on.
+    pub fn axiom_v2_data_query(&self) -> Result<DataQuery> {
+        let network = self.network().ok_or_else(|| {
+            AxiomError::MissingInput(
+                "no query has been made, so the network is unknown".to_string(),
+            )
+        })?;
+        let subqueries = self
+            .recorded_proofs
+            .iter()
+            .flat_map(|input| {
+                let (block_number, addr) = (input.block_number as u32, input.proof.address);
+                if input.proof.storage_proof.is_empty() {
+                    (0..ACCOUNT_FIELDS)
+                        .map(|field_idx| Subquery::Account { block_number, addr, field_idx })
+                        .collect::<Vec<_>>()
+                } else {
+                    input
+                        .proof
+                        .storage_proof
+                        .iter()
+                        .map(|slot| Subquery::Storage { block_number, addr, slot: slot.key })
+                        .collect()
+                }
+            })
+            .collect();
+        Ok(DataQuery { source_chain_id: chain_id(network), subqueries })
+    }
+}
+
+fn fr_to_word(x: &Fr) -> H256 {
+    let mut bytes = x.to_bytes();
+    bytes.reverse();
+    H256(bytes)
+}
+
+/// Combines consecutive `(hi, lo)` instances into 32 byte words.
+fn results_from_hi_lo(instances: &[Fr]) -> Result<Vec<H256>> {
+    if instances.len() % 2 != 0 {
+        return Err(AxiomError::InvalidOutput(format!(
+            "results must be exposed as (hi, lo) pairs, but there are {} public outputs",
+            instances.len()
+        )));
+    }
+    instances
+        .chunks(2)
+        .map(|pair| {
+            let [hi, lo] =
+                [pair[0], pair[1]].map(|x| U256::from_big_endian(fr_to_word(&x).as_bytes()));
+            if hi.bits() > 128 || lo.bits() > 128 {
+                return Err(AxiomError::InvalidOutput(format!(
+                    "result ({hi:#x}, {lo:#x}) is not a pair of 128 bit halves"
+                )));
+            }
+            let mut word = [0u8; 32];
+            ((hi << 128) | lo).to_big_endian(&mut word);
+            Ok(H256(word))
+        })
+        .collect()
+}
+
+/// The verifying key as 32 byte words, see [`ComputeQuery::vkey`].
+fn vkey_words(vk: &VerifyingKey<G1Affine>) -> Vec<H256> {
+    let mut words = vec![fr_to_word(&vk.transcript_repr())];
+    for point in vk.fixed_commitments().iter().chain(vk.permutation().commitments()) {
+        for coordinate in [point.x, point.y] {
+            let mut bytes = coordinate.to_bytes();
+            bytes.reverse();
+            words.push(H256(bytes));
+        }
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use axiom_eth::Network;
+    use ethers_core::types::{EIP1186ProofResponse, StorageProof};
+
+    use super::*;
+    use crate::offline::ProofInput;
+
+    #[test]
+    fn test_subquery_encoding() {
+        let addr = Address::from_low_u64_be(0xabcd);
+        let slot = H256::from_low_u64_be(7);
+
+        let header = Subquery::Header { block_number: 0x01020304, field_idx: 12 };
+        assert_eq!(header.encode(), [0, 1, 1, 2, 3, 4, 0, 0, 0, 12]);
+
+        let account = Subquery::Account { block_number: 5, addr, field_idx: 1 };
+        let encoded = account.encode();
+        assert_eq!(encoded.len(), 2 + 4 + 20 + 4);
+        assert_eq!(encoded[..6], [0, 2, 0, 0, 0, 5]);
+        assert_eq!(encoded[6..26], *addr.as_bytes());
+        assert_eq!(encoded[26..], [0, 0, 0, 1]);
+
+        let storage = Subquery::Storage { block_number: 5, addr, slot };
+        let encoded = storage.encode();
+        assert_eq!(encoded.len(), 2 + 4 + 20 + 32);
+        assert_eq!(encoded[..2], [0, 3]);
+        assert_eq!(encoded[26..], *slot.as_bytes());
+        assert_eq!(storage.hash(), H256(keccak256(&encoded)));
+    }
+
+    #[test]
+    fn test_data_query_encoding() {
+        let subqueries = vec![
+            Subquery::Header { block_number: 1, field_idx: 0 },
+            Subquery::Storage { block_number: 1, addr: Address::zero(), slot: H256::zero() },
+        ];
+        let query = DataQuery { source_chain_id: 5, subqueries: subqueries.clone() };
+
+        let mut expected = vec![AXIOM_V2_VERSION, 0, 0, 0, 0, 0, 0, 0, 5, 0, 2];
+        expected.extend(subqueries[0].encode());
+        expected.extend(subqueries[1].encode());
+        assert_eq!(query.encode(), expected);
+
+        let mut preimage = vec![AXIOM_V2_VERSION, 0, 0, 0, 0, 0, 0, 0, 5];
+        preimage.extend(subqueries[0].hash().as_bytes());
+        preimage.extend(subqueries[1].hash().as_bytes());
+        assert_eq!(query.hash(), H256(keccak256(preimage)));
+    }
+
+    #[test]
+    fn test_results_from_hi_lo() {
+        let results = results_from_hi_lo(&[Fr::from(1), Fr::from(2)]).unwrap();
+        let mut expected = [0u8; 32];
+        expected[15] = 1;
+        expected[31] = 2;
+        assert_eq!(results, [H256(expected)]);
+
+        assert!(results_from_hi_lo(&[Fr::from(1)]).is_err());
+        let two_64 = Fr::from(u64::MAX) + Fr::from(1);
+        assert!(results_from_hi_lo(&[two_64 * two_64, Fr::from(0)]).is_err());
+    }
+
+    #[test]
+    fn test_data_query_subqueries() {
+        let addr = Address::from_low_u64_be(1);
+        let slot = H256::from_low_u64_be(2);
+        let mut axiom = AxiomChip::<Fr>::default();
+        axiom.set_network(Network::Goerli);
+        axiom.recorded_proofs = vec![
+            ProofInput {
+                block_number: 10,
+                proof: EIP1186ProofResponse { address: addr, ..Default::default() },
+            },
+            ProofInput {
+                block_number: 11,
+                proof: EIP1186ProofResponse {
+                    address: addr,
+                    storage_proof: vec![StorageProof { key: slot, ..Default::default() }],
+                    ..Default::default()
+                },
+            },
+        ];
+
+        let query = axiom.axiom_v2_data_query().unwrap();
+        assert_eq!(query.source_chain_id, 5);
+        let mut expected: Vec<_> = (0..ACCOUNT_FIELDS)
+            .map(|field_idx| Subquery::Account { block_number: 10, addr, field_idx })
+            .collect();
+        expected.push(Subquery::Storage { block_number: 11, addr, slot });
+        assert_eq!(query.subqueries, expected);
+    }
+}